@@ -1,36 +1,182 @@
-use crate::dirs::CLOAK_ACCOUNTS_FILE_PATH;
-use crate::errors::Result;
-use std::collections::BTreeMap;
-use std::fs;
+use crate::crypto::VaultKey;
+use crate::errors::{Error, Result};
+use crate::files::{self, FileLock, LockMode};
+use crate::keyring;
+use crate::otpauth::split_label;
+use crate::secret::{ExternalKey, KeySource};
+use crate::storage::{self, Accounts, Storage};
+use crate::timestamp;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::env;
+use std::path::Path;
+use std::time::Duration;
+use toml::value::{Table, Value};
 
-#[derive(Debug, Deserialize, Serialize)]
+// Version of the schema of the accounts file, bump it and add a migration to
+// `MIGRATIONS` whenever the format changes
+pub const SCHEMA_VERSION: u32 = 2;
+
+// Migrations of the accounts file, the first one upgrades version 1 to 2, the
+// second one version 2 to 3, and so on
+const MIGRATIONS: [fn(Table) -> Result<Table>; 1] = [nest_accounts];
+
+// Seconds to wait for another cloak process to release the accounts file,
+// unless set by `CLOAK_LOCK_TIMEOUT`
+const LOCK_TIMEOUT: u64 = 10;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct Account {
     pub key: String,
     pub totp: bool,
     pub hash_function: String,
     pub counter: Option<u64>,
+    pub digits: Option<usize>,
+    pub period: Option<u64>,
+    pub t0: Option<u64>,
+    // Steam Guard accounts generate 5 character codes instead of digits
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub steam: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    // Name of the account at the issuer, e.g. a username or an email address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    // Seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<u64>,
+    // Program reading the secret key, `key` is empty then
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_key: Option<ExternalKey>,
+}
+
+impl Account {
+    // Issuer and label of the account, taken from the account name, e.g.
+    // `GitHub:evans`, unless they are set explicitly
+    pub fn issuer_and_label(&self, account_name: &str) -> (Option<String>, String) {
+        let (issuer, label) = split_label(account_name);
+        (
+            self.issuer.clone().or(issuer),
+            self.label.clone().unwrap_or(label),
+        )
+    }
+
+    // Where the secret key comes from, the accounts file unless the key
+    // refers to the keyring or an external program reads it
+    pub fn key_source(&self) -> KeySource<'_> {
+        match &self.external_key {
+            Some(external_key) => KeySource::External(external_key),
+            None => KeySource::parse(&self.key),
+        }
+    }
+
+    // Secret key of the account, read from its source
+    pub fn secret(&self) -> Result<Cow<'_, str>> {
+        match self.key_source() {
+            KeySource::Plain(key) => Ok(Cow::Borrowed(key)),
+            source => Ok(Cow::Owned(source.read()?)),
+        }
+    }
+
+    // Copy of the account holding its secret key, e.g. to export it
+    pub fn with_secret(&self) -> Result<Account> {
+        let mut account = self.clone();
+        account.key = self.secret()?.into_owned();
+        account.external_key = None;
+        Ok(account)
+    }
+
+    // Move a key stored in the accounts file to the keyring, keeping a
+    // reference to it instead
+    pub fn move_to_keyring(&mut self, account_name: &str) -> Result<()> {
+        if let KeySource::Plain(key) = self.key_source() {
+            let id = keyring::new_id()?;
+            keyring::store(&id, account_name, key)?;
+            self.key = format!("{}{}", keyring::KEY_PREFIX, id);
+        }
+        Ok(())
+    }
+}
+
+fn lock_timeout() -> Duration {
+    let seconds = env::var("CLOAK_LOCK_TIMEOUT")
+        .ok()
+        .and_then(|timeout| timeout.parse().ok())
+        .unwrap_or(LOCK_TIMEOUT);
+    Duration::from_secs(seconds)
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
 }
 
 pub struct AccountStore {
-    accounts: BTreeMap<String, Account>,
+    accounts: Accounts,
+    vault_key: Option<VaultKey>,
+    storage: Box<dyn Storage>,
+    // Held until the store is dropped
+    _lock: Option<FileLock>,
 }
 
 impl AccountStore {
-    pub fn new() -> Result<AccountStore> {
-        let accounts_str = fs::read_to_string(CLOAK_ACCOUNTS_FILE_PATH.as_path())?;
-        let accounts: BTreeMap<String, Account> = toml::from_str(&accounts_str)?;
-        Ok(AccountStore { accounts })
+    // Read the accounts of a vault, holding a shared lock on the accounts for
+    // commands that only read them and an exclusive one for the others
+    pub fn new(vault_dir: &Path, lock_mode: LockMode) -> Result<AccountStore> {
+        let lock = files::lock(&vault_dir.join("accounts"), lock_mode, lock_timeout())?;
+        // Readers only upgrade the accounts in memory
+        let mut account_store =
+            AccountStore::open(storage::open(vault_dir)?, lock_mode == LockMode::Exclusive)?;
+        account_store._lock = Some(lock);
+        Ok(account_store)
+    }
+
+    fn open(storage: Box<dyn Storage>, upgrade: bool) -> Result<AccountStore> {
+        let (accounts, vault_key) = storage.read(upgrade)?;
+        Ok(AccountStore {
+            accounts,
+            vault_key,
+            storage,
+            _lock: None,
+        })
+    }
+
+    // Accounts of a previous version, 1 being the latest, and the time it was saved
+    pub fn previous(&self, version: usize) -> Result<(Accounts, u64)> {
+        self.storage.previous(version, self.vault_key.as_ref())
     }
 
     pub fn get(&self, account_name: &str) -> Option<&Account> {
         self.accounts.get(account_name)
     }
 
-    pub fn list(&self) -> &BTreeMap<String, Account> {
+    pub fn get_mut(&mut self, account_name: &str) -> Option<&mut Account> {
+        self.accounts.get_mut(account_name)
+    }
+
+    pub fn list(&self) -> &Accounts {
         &self.accounts
     }
 
-    pub fn add(&mut self, account_name: String, account: Account) {
+    // Copy of the accounts holding their secret keys, including the ones kept
+    // in the keyring
+    pub fn with_secrets(&self) -> Result<Accounts> {
+        self.accounts
+            .iter()
+            .map(|(name, account)| Ok((name.to_string(), account.with_secret()?)))
+            .collect()
+    }
+
+    // New accounts are stamped with the time they are first stored
+    pub fn add(&mut self, account_name: String, mut account: Account) {
+        if account.created_at.is_none() {
+            account.created_at = Some(timestamp::now());
+        }
         self.accounts.insert(account_name, account);
     }
 
@@ -38,9 +184,137 @@ impl AccountStore {
         self.accounts.remove(account_name)
     }
 
+    pub fn is_encrypted(&self) -> bool {
+        self.vault_key.is_some()
+    }
+
+    // Save the accounts encrypted with a new key, or `None` to save them as
+    // plaintext. Previous versions are dropped rather than left readable with
+    // the old key or as plaintext.
+    pub fn change_vault_key(&mut self, vault_key: Option<VaultKey>) -> Result<()> {
+        self.vault_key = vault_key;
+        self.storage.rekey(&self.accounts, self.vault_key.as_ref())
+    }
+
+    // Save the accounts, keeping the stored ones as the latest previous version
     pub fn save(&self) -> Result<()> {
-        let accounts_str = toml::to_string(&self.accounts)?;
-        fs::write(CLOAK_ACCOUNTS_FILE_PATH.as_path(), accounts_str)?;
-        Ok(())
+        self.storage
+            .write(&self.accounts, self.vault_key.as_ref(), true)
+    }
+
+    // Save changes to the usage of the accounts, i.e. HOTP counters and last
+    // use times, which are not worth keeping a previous version for
+    pub fn save_usage(&self) -> Result<()> {
+        self.storage
+            .write(&self.accounts, self.vault_key.as_ref(), false)
+    }
+}
+
+// Version of the schema of the accounts file. Files of version 1 have no
+// version, they are a table of accounts, so an account named `version` is a
+// table rather than an integer.
+pub fn schema_version(table: &Table) -> u32 {
+    match table.get("version").and_then(Value::as_integer) {
+        Some(version) => u32::try_from(version.max(0)).unwrap_or(u32::MAX),
+        None => 1,
+    }
+}
+
+// Upgrade the contents of an accounts file of the given version to the
+// current version
+pub fn migrate(mut table: Table, version: u32) -> Result<Table> {
+    if version == 0 || version > SCHEMA_VERSION {
+        return Err(Error::UnsupportedSchemaVersion(version, SCHEMA_VERSION));
+    }
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        table = migration(table)?;
+    }
+    Ok(table)
+}
+
+// Version 2 adds the schema version, the accounts move to the `accounts` table
+fn nest_accounts(accounts: Table) -> Result<Table> {
+    let mut table = Table::new();
+    table.insert(String::from("version"), Value::Integer(2));
+    table.insert(String::from("accounts"), Value::Table(accounts));
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{migrate, schema_version, Account, AccountStore, SCHEMA_VERSION};
+    use crate::errors::Error;
+    use crate::storage::memory::Memory;
+    use toml::value::Table;
+
+    #[test]
+    fn test_save_keeps_previous_versions() {
+        let account = Account {
+            key: String::from("JBSWY3DPEHPK3PXP"),
+            totp: false,
+            hash_function: String::from("SHA1"),
+            counter: Some(0),
+            digits: None,
+            period: None,
+            t0: None,
+            steam: false,
+            tags: Vec::new(),
+            issuer: None,
+            label: None,
+            notes: None,
+            created_at: None,
+            last_used_at: None,
+            external_key: None,
+        };
+        let mut account_store = AccountStore::open(Box::new(Memory::default()), true).unwrap();
+        account_store.add(String::from("github"), account);
+        assert!(account_store.get("github").unwrap().created_at.is_some());
+        account_store.save().unwrap();
+        account_store.get_mut("github").unwrap().counter = Some(1);
+        account_store.save_usage().unwrap();
+        account_store.delete("github");
+        account_store.save().unwrap();
+
+        let (accounts, _) = account_store.previous(1).unwrap();
+        assert_eq!(accounts["github"].counter, Some(1));
+        assert!(matches!(
+            account_store.previous(2),
+            Err(Error::NoPreviousVersion(2, _))
+        ));
+    }
+
+    #[test]
+    fn test_migrate_version_1() {
+        let table: Table = toml::from_str(
+            "
+[github]
+key = \"JBSWY3DPEHPK3PXP\"
+totp = true
+hash_function = \"SHA1\"
+
+[version]
+key = \"MFZWIYLTMRQXGZCBBI\"
+totp = true
+hash_function = \"SHA1\"
+",
+        )
+        .unwrap();
+        assert_eq!(schema_version(&table), 1);
+
+        let table = migrate(table, 1).unwrap();
+        assert_eq!(schema_version(&table), SCHEMA_VERSION);
+        let accounts = table["accounts"].as_table().unwrap();
+        assert_eq!(accounts.len(), 2);
+        assert!(accounts["version"].is_table());
+    }
+
+    #[test]
+    fn test_migrate_newer_version() {
+        let table: Table = toml::from_str("version = 99\n[accounts]\n").unwrap();
+        assert_eq!(schema_version(&table), 99);
+        assert!(matches!(
+            migrate(table, 99),
+            Err(Error::UnsupportedSchemaVersion(99, SCHEMA_VERSION))
+        ));
     }
 }
@@ -0,0 +1,214 @@
+use crate::account::Account;
+use crate::crypto::{self, VaultKey};
+use crate::errors::{Error, Result};
+use crate::secret::KeySource;
+use crate::timestamp;
+use std::collections::{BTreeMap, BTreeSet};
+
+const FORMAT: &str = "cloak";
+// Version of the backup format, bumped on incompatible changes
+const BACKUP_VERSION: u32 = 1;
+
+// Backup of the whole account store, saved encrypted like a vault
+#[derive(Deserialize, Serialize)]
+pub struct Backup {
+    pub version: u32,
+    // Seconds since the Unix epoch
+    pub created_at: u64,
+    pub cloak_version: String,
+    // Accounts whose key was kept in the keyring, put back there on restore
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub keyring: BTreeSet<String>,
+    pub accounts: BTreeMap<String, Account>,
+}
+
+// Change made to an account of the store by restoring a backup or a previous
+// version of the accounts file
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    Added,
+    Changed,
+    Dropped,
+}
+
+impl Backup {
+    pub fn new(accounts: BTreeMap<String, Account>, keyring: BTreeSet<String>) -> Backup {
+        Backup {
+            version: BACKUP_VERSION,
+            created_at: timestamp::now(),
+            cloak_version: env!("CARGO_PKG_VERSION").to_string(),
+            keyring,
+            accounts,
+        }
+    }
+
+    pub fn encrypt(&self, vault_key: &VaultKey) -> Result<Vec<u8>> {
+        let backup_str = toml::to_string(self)?;
+        crypto::encrypt(backup_str.as_bytes(), vault_key)
+    }
+
+    pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Backup> {
+        if !crypto::is_encrypted(data) {
+            return Err(invalid("not an encrypted cloak backup"));
+        }
+        let plaintext = match crypto::decrypt(data, passphrase) {
+            Ok((plaintext, _)) => plaintext,
+            Err(Error::Decrypt) => return Err(Error::BackupDecrypt(FORMAT)),
+            Err(err) => return Err(err),
+        };
+        let backup_str = String::from_utf8(plaintext).map_err(|_| invalid("not valid UTF-8"))?;
+        let version: toml::Value = toml::from_str(&backup_str)?;
+        match version.get("version").and_then(toml::Value::as_integer) {
+            Some(version) if version == i64::from(BACKUP_VERSION) => {}
+            Some(version) => return Err(invalid(&format!("unsupported version {}", version))),
+            None => return Err(invalid("missing version")),
+        }
+        Ok(toml::from_str(&backup_str)?)
+    }
+}
+
+// Changes to the accounts of the store when restoring accounts. Accounts that
+// are not restored are only dropped when replacing the store.
+pub fn diff<'a>(
+    accounts: &'a BTreeMap<String, Account>,
+    restored: &'a BTreeMap<String, Account>,
+    replace: bool,
+) -> Vec<(&'a str, Change)> {
+    let mut changes: Vec<(&str, Change)> = Vec::new();
+    for (name, account) in restored {
+        match accounts.get(name) {
+            None => changes.push((name, Change::Added)),
+            Some(current) if !same_settings(current, account) => {
+                changes.push((name, Change::Changed))
+            }
+            Some(_) => {}
+        }
+    }
+    if replace {
+        changes.extend(
+            accounts
+                .keys()
+                .filter(|name| !restored.contains_key(*name))
+                .map(|name| (name.as_str(), Change::Dropped)),
+        );
+    }
+    changes.sort_by(|a, b| a.0.cmp(b.0));
+    changes
+}
+
+// Whether the accounts only differ by the last time they were used
+fn same_settings(a: &Account, b: &Account) -> bool {
+    let unused = |account: &Account| Account {
+        key: String::new(),
+        last_used_at: None,
+        ..account.clone()
+    };
+    unused(a) == unused(b) && same_key(a, b)
+}
+
+// A key kept in the keyring is the same as the key it holds, e.g. in a backup
+fn same_key(a: &Account, b: &Account) -> bool {
+    let holds =
+        |account: &Account, key: &str| matches!(account.secret(), Ok(secret) if secret == key);
+    match (a.key_source(), b.key_source()) {
+        (KeySource::Keyring(_), KeySource::Plain(key)) => holds(a, key),
+        (KeySource::Plain(key), KeySource::Keyring(_)) => holds(b, key),
+        _ => a.key == b.key,
+    }
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidBackup(FORMAT, reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, Backup, Change};
+    use crate::account::Account;
+    use crate::crypto::VaultKey;
+    use crate::errors::Error;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    fn account(key: &str) -> Account {
+        Account {
+            key: key.to_string(),
+            totp: true,
+            hash_function: String::from("SHA1"),
+            counter: None,
+            digits: None,
+            period: None,
+            t0: None,
+            steam: false,
+            tags: Vec::new(),
+            issuer: None,
+            label: None,
+            notes: None,
+            created_at: None,
+            last_used_at: None,
+            external_key: None,
+        }
+    }
+
+    fn accounts(accounts: &[(&str, &str)]) -> BTreeMap<String, Account> {
+        accounts
+            .iter()
+            .map(|(name, key)| (name.to_string(), account(key)))
+            .collect()
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let keyring = vec!["github".to_string()].into_iter().collect();
+        let backup = Backup::new(accounts(&[("github", "JBSWY3DPEHPK3PXP")]), keyring);
+        let vault_key = VaultKey::generate("hunter2").unwrap();
+        let data = backup.encrypt(&vault_key).unwrap();
+
+        let restored = Backup::decrypt(&data, "hunter2").unwrap();
+        assert_eq!(restored.version, 1);
+        assert_eq!(restored.created_at, backup.created_at);
+        assert_eq!(restored.cloak_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(restored.accounts, backup.accounts);
+        assert_eq!(restored.keyring, backup.keyring);
+
+        assert!(matches!(
+            Backup::decrypt(&data, "hunter3"),
+            Err(Error::BackupDecrypt(_))
+        ));
+        assert!(matches!(
+            Backup::decrypt(b"[github]", "hunter2"),
+            Err(Error::InvalidBackup(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_diff() {
+        let backup = Backup::new(
+            accounts(&[
+                ("github", "JBSWY3DPEHPK3PXP"),
+                ("gitlab", "4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6"),
+                ("steam", "JRZCL47CMXVOQMNPZR2F7J4RGI"),
+            ]),
+            BTreeSet::new(),
+        );
+        let mut current = accounts(&[
+            ("github", "JBSWY3DPEHPK3PXP"),
+            ("gitlab", "5OM4WOOGPLQEF6UGN3CPEOOLWU"),
+            ("aws", "MFZWIYLTMRQXGZCBBI"),
+        ]);
+        // Using an account since the backup doesn't change it
+        current.get_mut("github").unwrap().last_used_at = Some(1_663_144_200);
+
+        assert_eq!(
+            diff(&current, &backup.accounts, false),
+            vec![("gitlab", Change::Changed), ("steam", Change::Added)]
+        );
+        assert_eq!(
+            diff(&current, &backup.accounts, true),
+            vec![
+                ("aws", Change::Dropped),
+                ("gitlab", Change::Changed),
+                ("steam", Change::Added)
+            ]
+        );
+    }
+}
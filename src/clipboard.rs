@@ -0,0 +1,105 @@
+use crate::errors::{Error, Result};
+use std::env;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+// Hidden subcommand run in the background to clear the clipboard
+pub const CLEAR_SUBCOMMAND: &str = "clear-clipboard";
+
+// Commands copying their standard input to the clipboard, and printing it
+#[cfg(target_os = "macos")]
+fn commands() -> (Command, Command) {
+    (Command::new("pbcopy"), Command::new("pbpaste"))
+}
+
+#[cfg(windows)]
+fn commands() -> (Command, Command) {
+    let mut paste = Command::new("powershell");
+    paste.args(["-NoProfile", "-Command", "Get-Clipboard"]);
+    (Command::new("clip"), paste)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn commands() -> (Command, Command) {
+    if env::var_os("WAYLAND_DISPLAY").is_some() {
+        let mut paste = Command::new("wl-paste");
+        paste.arg("--no-newline");
+        (Command::new("wl-copy"), paste)
+    } else {
+        let mut copy = Command::new("xclip");
+        copy.args(["-selection", "clipboard"]);
+        let mut paste = Command::new("xclip");
+        paste.args(["-selection", "clipboard", "-o"]);
+        (copy, paste)
+    }
+}
+
+// Copy the text to the clipboard. The output of the command is discarded,
+// xclip and wl-copy keep running in the background to serve the clipboard.
+pub fn copy(text: &str) -> Result<()> {
+    let (mut copy, _) = commands();
+    let mut child = copy
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| command_error(&copy, err))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(command_error(&copy, status));
+    }
+    Ok(())
+}
+
+fn paste() -> Result<String> {
+    let (_, mut paste) = commands();
+    let output = paste
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|err| command_error(&paste, err))?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+// Clear the clipboard after some time from a process running in the
+// background, unless something else was copied in the meantime
+pub fn clear_after(text: &str, seconds: u64) -> Result<()> {
+    let mut child = Command::new(env::current_exe()?)
+        .args([CLEAR_SUBCOMMAND, &seconds.to_string()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // The text is given through the standard input to keep it out of the
+    // arguments, which other users can see
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    Ok(())
+}
+
+// Implementation of the hidden `clear-clipboard` subcommand
+pub fn clear(seconds: u64) -> Result<()> {
+    let mut text = String::new();
+    io::stdin().read_to_string(&mut text)?;
+    thread::sleep(Duration::from_secs(seconds));
+    if paste()? == text {
+        copy("")?;
+    }
+    Ok(())
+}
+
+fn command_error(command: &Command, err: impl ToString) -> Error {
+    Error::Clipboard(format!(
+        "{}: {}",
+        command.get_program().to_string_lossy(),
+        err.to_string()
+    ))
+}
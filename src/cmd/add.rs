@@ -1,7 +1,15 @@
 use crate::account::{Account, AccountStore};
-use clap::builder::PossibleValuesParser;
-use clap::{Arg, ArgMatches, Command};
+use crate::config::CONFIG;
+use crate::errors;
+use crate::otp::{HASH_FUNCTIONS, MAX_DIGITS, MIN_DIGITS};
+use crate::otpauth::OtpAuth;
+use crate::qr;
+use crate::secret::{self, ExternalKey};
+use clap::builder::{PossibleValuesParser, RangedU64ValueParser};
+use clap::{value_parser, Arg, ArgMatches, Command};
 use data_encoding::BASE32_NOPAD;
+use std::fs;
+use std::path::PathBuf;
 
 // Create arguments for `add` subcommand
 pub fn subcommand<'a>() -> Command<'a> {
@@ -9,15 +17,57 @@ pub fn subcommand<'a>() -> Command<'a> {
         .about("Add a new account")
         .arg(
             Arg::new("account")
-                .required(true)
-                .help("Name of the account"),
+                .required_unless_present_any(["uri", "qr"])
+                .help(
+                    "Name of the account, defaults to the label of the URI when using --uri or --qr",
+                ),
         )
         .arg(
             Arg::new("key")
-                .required(true)
+                .required_unless_present_any(["uri", "qr", "key-command", "key-gpg", "key-pass"])
                 .help("Secret key of the OTP")
                 .value_parser(is_base32_key),
         )
+        .arg(
+            Arg::new("uri")
+                .long("uri")
+                .takes_value(true)
+                .value_name("URI")
+                .conflicts_with_all(&[
+                    "key",
+                    "key-command",
+                    "key-gpg",
+                    "key-pass",
+                    "totp",
+                    "hotp",
+                    "algorithm",
+                    "digits",
+                    "period",
+                    "epoch",
+                ])
+                .help("Add the account from an otpauth:// provisioning URI"),
+        )
+        .arg(
+            Arg::new("qr")
+                .long("qr")
+                .takes_value(true)
+                .value_name("IMAGE")
+                .conflicts_with_all(&[
+                    "uri",
+                    "key",
+                    "key-command",
+                    "key-gpg",
+                    "key-pass",
+                    "totp",
+                    "hotp",
+                    "algorithm",
+                    "digits",
+                    "period",
+                    "epoch",
+                ])
+                .value_parser(value_parser!(PathBuf))
+                .help("Add the account from a PNG or JPEG image of a QR code"),
+        )
         .arg(
             Arg::new("totp")
                 .long("totp")
@@ -30,21 +80,105 @@ pub fn subcommand<'a>() -> Command<'a> {
                 .short('a')
                 .long("algorithm")
                 .takes_value(true)
-                .value_parser(PossibleValuesParser::new([
-                    "SHA1",
-                    "SHA256",
-                    "SHA384",
-                    "SHA512",
-                    "SHA512_256",
-                ]))
-                .default_value("SHA1")
+                .value_parser(PossibleValuesParser::new(HASH_FUNCTIONS))
                 .value_name("ALGORITHM")
-                .help("Algorithm to use to generate the OTP code"),
+                .help("Algorithm to use to generate the OTP code, defaults to SHA1"),
+        )
+        .arg(
+            Arg::new("digits")
+                .short('d')
+                .long("digits")
+                .takes_value(true)
+                .value_name("NUMBER")
+                .help("Number of digits of the OTP codes, defaults to 6")
+                .value_parser(
+                    RangedU64ValueParser::<usize>::new()
+                        .range(MIN_DIGITS as u64..=MAX_DIGITS as u64),
+                ),
+        )
+        .arg(
+            Arg::new("period")
+                .short('p')
+                .long("period")
+                .takes_value(true)
+                .conflicts_with("hotp")
+                .value_name("SECONDS")
+                .help("Time step of a time based account, defaults to 30 seconds")
+                .value_parser(value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("epoch")
+                .long("epoch")
+                .takes_value(true)
+                .conflicts_with("hotp")
+                .value_name("TIMESTAMP")
+                .help("Unix time to start counting time steps from (T0), defaults to 0")
+                .value_parser(value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("issuer")
+                .long("issuer")
+                .takes_value(true)
+                .value_name("ISSUER")
+                .help("Provider of the account, e.g. GitHub"),
+        )
+        .arg(
+            Arg::new("label")
+                .long("label")
+                .takes_value(true)
+                .value_name("LABEL")
+                .help("Name of the account at the issuer, e.g. a username or an email address"),
+        )
+        .arg(
+            Arg::new("tag")
+                .short('t')
+                .long("tag")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .value_name("TAG")
+                .help("Tag of the account, can be given several times"),
+        )
+        .arg(
+            Arg::new("notes")
+                .long("notes")
+                .takes_value(true)
+                .value_name("NOTES")
+                .help("Notes about the account, e.g. where its recovery codes are kept"),
+        )
+        .arg(
+            Arg::new("keyring")
+                .long("keyring")
+                .help("Keep the secret key in the keyring of the desktop (Secret Service)"),
+        )
+        .arg(
+            Arg::new("key-command")
+                .long("key-command")
+                .takes_value(true)
+                .value_name("COMMAND")
+                .conflicts_with_all(&["key", "key-gpg", "key-pass", "keyring"])
+                .help("Read the secret key from the output of a shell command"),
+        )
+        .arg(
+            Arg::new("key-gpg")
+                .long("key-gpg")
+                .takes_value(true)
+                .value_name("FILE")
+                .conflicts_with_all(&["key", "key-pass", "keyring"])
+                .value_parser(value_parser!(PathBuf))
+                .help("Read the secret key from a file encrypted with gpg"),
+        )
+        .arg(
+            Arg::new("key-pass")
+                .long("key-pass")
+                .takes_value(true)
+                .value_name("NAME")
+                .conflicts_with_all(&["key", "keyring"])
+                .help("Read the secret key from an entry of the pass password store"),
         )
 }
 
 // Validate key provided in arguments is a valid base32 encoding
-fn is_base32_key(value: &str) -> Result<String, String> {
+pub fn is_base32_key(value: &str) -> Result<String, String> {
     let value = value.to_uppercase();
     match BASE32_NOPAD.decode(value.as_bytes()) {
         Ok(_) => Ok(value.to_string()),
@@ -54,10 +188,57 @@ fn is_base32_key(value: &str) -> Result<String, String> {
 
 // Implementation for the `add` subcommand
 pub fn run(args: &ArgMatches, account_store: &mut AccountStore) {
+    let uri = if let Some(uri) = args.get_one::<String>("uri") {
+        Some(uri.to_string())
+    } else if let Some(image) = args.get_one::<PathBuf>("qr") {
+        match qr::decode_file(image) {
+            Ok(uri) => Some(uri),
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        }
+    } else {
+        None
+    };
+    let (account_name, account) = match uri {
+        Some(uri) => match uri.parse::<OtpAuth>() {
+            Ok(otpauth) => account_from_otpauth(args, otpauth),
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        },
+        None => match account_from_args(args) {
+            Ok(account) => account,
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        },
+    };
+    let mut account = with_metadata(args, account);
+    if args.contains_id("keyring") {
+        if account_store.get(&account_name).is_some() {
+            println!("Account already exists");
+            return;
+        }
+        if let Err(err) = account.move_to_keyring(&account_name) {
+            eprintln!("{}", err);
+            return;
+        }
+    }
+    add_account(account_store, account_name, account);
+}
+
+fn account_from_args(args: &ArgMatches) -> errors::Result<(String, Account)> {
     let totp = !args.contains_id("hotp");
-    let hash_function = args.get_one::<String>("algorithm").unwrap();
+    let hash_function = args
+        .get_one::<String>("algorithm")
+        .or(CONFIG.algorithm.as_ref())
+        .map_or("SHA1", String::as_str);
     let account_name = args.get_one::<String>("account").unwrap();
-    let key = args.get_one::<String>("key").unwrap().to_uppercase();
+    let (key, external_key) = key_from_args(args)?;
 
     let counter = if !totp { Some(0) } else { None };
     let account = Account {
@@ -65,12 +246,75 @@ pub fn run(args: &ArgMatches, account_store: &mut AccountStore) {
         totp,
         hash_function: hash_function.to_string(),
         counter,
+        // Defaults of the config file are stored with the account, so that
+        // changing them doesn't change the codes of existing accounts
+        digits: args.get_one::<usize>("digits").copied().or(CONFIG.digits),
+        period: args
+            .get_one::<u64>("period")
+            .copied()
+            .or(CONFIG.period)
+            .filter(|_| totp),
+        t0: args.get_one::<u64>("epoch").copied(),
+        steam: false,
+        tags: Vec::new(),
+        issuer: None,
+        label: None,
+        notes: None,
+        created_at: None,
+        last_used_at: None,
+        external_key,
+    };
+    Ok((account_name.to_string(), account))
+}
+
+// The key, or the program reading it when it is kept outside of the accounts file
+fn key_from_args(args: &ArgMatches) -> errors::Result<(String, Option<ExternalKey>)> {
+    let external_key = if let Some(command) = args.get_one::<String>("key-command") {
+        // The user gave the command, it runs without asking
+        secret::trust_command(command)?;
+        ExternalKey::Command(command.to_string())
+    } else if let Some(path) = args.get_one::<PathBuf>("key-gpg") {
+        // The file is read from any directory later on
+        let path = fs::canonicalize(path)?;
+        ExternalKey::Gpg(path.to_string_lossy().into_owned())
+    } else if let Some(name) = args.get_one::<String>("key-pass") {
+        ExternalKey::Pass(name.to_string())
+    } else {
+        let key = args.get_one::<String>("key").unwrap().to_uppercase();
+        return Ok((key, None));
     };
+    Ok((String::new(), Some(external_key)))
+}
+
+fn account_from_otpauth(args: &ArgMatches, otpauth: OtpAuth) -> (String, Account) {
+    let account_name = args
+        .get_one::<String>("account")
+        .cloned()
+        .unwrap_or_else(|| otpauth.account_name());
+    (account_name, otpauth.into_account())
+}
+
+// Set the metadata given as arguments, overriding the issuer and label of a URI
+fn with_metadata(args: &ArgMatches, mut account: Account) -> Account {
+    if let Some(issuer) = args.get_one::<String>("issuer") {
+        account.issuer = Some(issuer.to_string());
+    }
+    if let Some(label) = args.get_one::<String>("label") {
+        account.label = Some(label.to_string());
+    }
+    if let Some(tags) = args.get_many::<String>("tag") {
+        account.tags = tags.cloned().collect();
+    }
+    account.notes = args.get_one::<String>("notes").cloned();
+    account
+}
 
-    if account_store.get(account_name).is_some() {
+// Store a new account unless an account with the same name already exists
+pub fn add_account(account_store: &mut AccountStore, account_name: String, account: Account) {
+    if account_store.get(&account_name).is_some() {
         println!("Account already exists");
     } else {
-        account_store.add(account_name.to_string(), account);
+        account_store.add(account_name, account);
         match account_store.save() {
             Ok(_) => println!("Account successfully created"),
             Err(err) => eprintln!("{}", err),
@@ -0,0 +1,50 @@
+use crate::account::AccountStore;
+use crate::backup::Backup;
+use crate::crypto;
+use crate::files;
+use crate::secret::KeySource;
+use clap::{value_parser, Arg, ArgMatches, Command};
+use std::path::PathBuf;
+
+// Create arguments for `backup` subcommand
+pub fn subcommand<'a>() -> Command<'a> {
+    Command::new("backup")
+        .about("Back up all accounts to a file encrypted with a passphrase")
+        .arg(
+            Arg::new("file")
+                .required(true)
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .help("File to write the backup to"),
+        )
+}
+
+// Implementation for the `backup` subcommand
+pub fn run(args: &ArgMatches, account_store: &mut AccountStore) {
+    let path = args.get_one::<PathBuf>("file").unwrap();
+    // Secrets kept in the keyring are included, the backup is restored elsewhere
+    let keyring = account_store
+        .list()
+        .iter()
+        .filter(|(_, account)| matches!(account.key_source(), KeySource::Keyring(_)))
+        .map(|(name, _)| name.to_string())
+        .collect();
+    let backup = match account_store.with_secrets() {
+        Ok(accounts) => Backup::new(accounts, keyring),
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+    let result = crypto::read_new_vault_key()
+        .and_then(|vault_key| backup.encrypt(&vault_key))
+        .and_then(|data| files::write_private(path, &data));
+    match result {
+        Ok(_) => println!(
+            "Backed up {} accounts to {}",
+            backup.accounts.len(),
+            path.display()
+        ),
+        Err(err) => eprintln!("{}", err),
+    }
+}
@@ -0,0 +1,77 @@
+use crate::config::{Config, KEYS};
+use crate::errors::Result;
+use clap::builder::PossibleValuesParser;
+use clap::{Arg, ArgMatches, Command};
+
+// Create arguments for `config` subcommand
+pub fn subcommand<'a>() -> Command<'a> {
+    let key = || {
+        Arg::new("key")
+            .required(true)
+            .value_parser(PossibleValuesParser::new(KEYS))
+            .help("Name of the setting")
+    };
+    Command::new("config")
+        .about("Manage the settings of the config file")
+        .subcommand_required(true)
+        .subcommand(Command::new("list").about("List the settings that are set"))
+        .subcommand(
+            Command::new("get")
+                .about("Print the value of a setting")
+                .arg(key()),
+        )
+        .subcommand(
+            Command::new("set").about("Set a setting").arg(key()).arg(
+                Arg::new("value")
+                    .required(true)
+                    .help("Value of the setting"),
+            ),
+        )
+        .subcommand(
+            Command::new("unset")
+                .about("Unset a setting, restoring its default")
+                .arg(key()),
+        )
+}
+
+// Implementation for the `config` subcommand
+pub fn run(args: &ArgMatches) {
+    let result = match args.subcommand() {
+        Some(("list", _)) => list(),
+        Some(("get", sub_m)) => get(sub_m.get_one::<String>("key").unwrap()),
+        Some(("set", sub_m)) => set(
+            sub_m.get_one::<String>("key").unwrap(),
+            Some(sub_m.get_one::<String>("value").unwrap()),
+        ),
+        Some(("unset", sub_m)) => set(sub_m.get_one::<String>("key").unwrap(), None),
+        _ => Ok(()),
+    };
+    if let Err(err) = result {
+        eprintln!("{}", err);
+    }
+}
+
+// The file is read leniently so that invalid settings can be fixed
+fn list() -> Result<()> {
+    let config = Config::load_lenient()?;
+    for key in KEYS {
+        if let Some(value) = config.get(key)? {
+            println!("{} = {}", key, value);
+        }
+    }
+    Ok(())
+}
+
+// Nothing is printed for a setting that is not set
+fn get(key: &str) -> Result<()> {
+    if let Some(value) = Config::load_lenient()?.get(key)? {
+        println!("{}", value);
+    }
+    Ok(())
+}
+
+fn set(key: &str, value: Option<&str>) -> Result<()> {
+    let mut config = Config::load_lenient()?;
+    config.set(key, value)?;
+    config.save()
+}
@@ -0,0 +1,19 @@
+use crate::account::AccountStore;
+use clap::Command;
+
+// `decrypt` subcommand
+pub fn subcommand<'a>() -> Command<'a> {
+    Command::new("decrypt").about("Decrypt the accounts file and store it as plaintext")
+}
+
+// Implementation for the `decrypt` subcommand
+pub fn run(account_store: &mut AccountStore) {
+    if !account_store.is_encrypted() {
+        println!("Accounts file is not encrypted");
+        return;
+    }
+    match account_store.change_vault_key(None) {
+        Ok(_) => println!("Accounts file successfully decrypted"),
+        Err(err) => eprintln!("{}", err),
+    }
+}
@@ -0,0 +1,172 @@
+use crate::account::{Account, AccountStore};
+use crate::cmd::add::is_base32_key;
+use crate::otp::{HASH_FUNCTIONS, MAX_DIGITS, MIN_DIGITS};
+use crate::secret::KeySource;
+use clap::builder::{PossibleValuesParser, RangedU64ValueParser};
+use clap::{value_parser, Arg, ArgGroup, ArgMatches, Command};
+
+// Create arguments for `edit` subcommand
+pub fn subcommand<'a>() -> Command<'a> {
+    Command::new("edit")
+        .about("Change the settings of an account")
+        .arg(
+            Arg::new("account")
+                .required(true)
+                .help("Name of the account"),
+        )
+        .arg(
+            Arg::new("key")
+                .short('k')
+                .long("key")
+                .takes_value(true)
+                .value_name("KEY")
+                .help("New secret key of the OTP")
+                .value_parser(is_base32_key),
+        )
+        .arg(
+            Arg::new("algorithm")
+                .short('a')
+                .long("algorithm")
+                .takes_value(true)
+                .value_parser(PossibleValuesParser::new(HASH_FUNCTIONS))
+                .value_name("ALGORITHM")
+                .help("Algorithm to use to generate the OTP code"),
+        )
+        .arg(
+            Arg::new("digits")
+                .short('d')
+                .long("digits")
+                .takes_value(true)
+                .value_name("NUMBER")
+                .help("Number of digits of the OTP codes")
+                .value_parser(
+                    RangedU64ValueParser::<usize>::new()
+                        .range(MIN_DIGITS as u64..=MAX_DIGITS as u64),
+                ),
+        )
+        .arg(
+            Arg::new("period")
+                .short('p')
+                .long("period")
+                .takes_value(true)
+                .value_name("SECONDS")
+                .help("Time step of a time based account")
+                .value_parser(value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("counter")
+                .short('c')
+                .long("counter")
+                .takes_value(true)
+                .value_name("NUMBER")
+                .help("Counter of a counter based account")
+                .value_parser(value_parser!(u64)),
+        )
+        .group(
+            ArgGroup::new("changes")
+                .args(&["key", "algorithm", "digits", "period", "counter"])
+                .multiple(true)
+                .required(true),
+        )
+}
+
+// Implementation for the `edit` subcommand
+pub fn run(args: &ArgMatches, account_store: &mut AccountStore) {
+    let account_name = args.get_one::<String>("account").unwrap();
+    let account = match account_store.get_mut(account_name) {
+        Some(account) => account,
+        None => {
+            println!(
+                "Account with the name '{}' does not exist. Consider adding it.",
+                account_name
+            );
+            return;
+        }
+    };
+    if account.totp && args.contains_id("counter") {
+        println!("The counter only applies to counter based accounts");
+        return;
+    }
+    if !account.totp && args.contains_id("period") {
+        println!("The period only applies to time based accounts");
+        return;
+    }
+
+    let before = account.clone();
+    if let Some(key) = args.get_one::<String>("key") {
+        account.key = key.to_string();
+        account.external_key = None;
+        // A new secret is stored rather than replacing the one of previous versions
+        if let KeySource::Keyring(_) = before.key_source() {
+            if let Err(err) = account.move_to_keyring(account_name) {
+                eprintln!("{}", err);
+                return;
+            }
+        }
+    }
+    if let Some(algorithm) = args.get_one::<String>("algorithm") {
+        account.hash_function = algorithm.to_string();
+    }
+    if let Some(digits) = args.get_one::<usize>("digits") {
+        account.digits = Some(*digits);
+    }
+    if let Some(period) = args.get_one::<u64>("period") {
+        account.period = Some(*period);
+    }
+    if let Some(counter) = args.get_one::<u64>("counter") {
+        account.counter = Some(*counter);
+    }
+
+    let changes = changes(&before, account);
+    if changes.is_empty() {
+        println!("Nothing to change");
+        return;
+    }
+    match account_store.save() {
+        Ok(_) => {
+            for change in changes {
+                println!("{}", change);
+            }
+            println!("Account successfully updated");
+        }
+        Err(err) => eprintln!("{}", err),
+    }
+}
+
+// Changed settings of an account, as `setting: before -> after`. The key is
+// secret so it is only reported as changed.
+fn changes(before: &Account, after: &Account) -> Vec<String> {
+    let mut changes = Vec::new();
+    if before.key != after.key || before.external_key != after.external_key {
+        changes.push(String::from("key: changed"));
+    }
+    let mut push = |setting: &str, before: String, after: String| {
+        if before != after {
+            changes.push(format!("{}: {} -> {}", setting, before, after));
+        }
+    };
+    push(
+        "algorithm",
+        before.hash_function.to_string(),
+        after.hash_function.to_string(),
+    );
+    push(
+        "digits",
+        before.digits.unwrap_or(6).to_string(),
+        after.digits.unwrap_or(6).to_string(),
+    );
+    if after.totp {
+        push(
+            "period",
+            before.period.unwrap_or(30).to_string(),
+            after.period.unwrap_or(30).to_string(),
+        );
+    } else {
+        push(
+            "counter",
+            before.counter.unwrap_or(0).to_string(),
+            after.counter.unwrap_or(0).to_string(),
+        );
+    }
+    changes
+}
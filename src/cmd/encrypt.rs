@@ -0,0 +1,22 @@
+use crate::account::AccountStore;
+use crate::crypto;
+use clap::Command;
+
+// `encrypt` subcommand
+pub fn subcommand<'a>() -> Command<'a> {
+    Command::new("encrypt").about("Encrypt the accounts file with a passphrase")
+}
+
+// Implementation for the `encrypt` subcommand
+pub fn run(account_store: &mut AccountStore) {
+    if account_store.is_encrypted() {
+        println!("Accounts file is already encrypted");
+        return;
+    }
+    let result = crypto::read_new_vault_key()
+        .and_then(|vault_key| account_store.change_vault_key(Some(vault_key)));
+    match result {
+        Ok(_) => println!("Accounts file successfully encrypted"),
+        Err(err) => eprintln!("{}", err),
+    }
+}
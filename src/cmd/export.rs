@@ -0,0 +1,156 @@
+use crate::account::{Account, AccountStore};
+use crate::config::CONFIG;
+use crate::crypto;
+use crate::errors::Result;
+use crate::files;
+use crate::formats::{aegis, kdbx, paper};
+use crate::otpauth::OtpAuth;
+use crate::timestamp;
+use clap::builder::PossibleValuesParser;
+use clap::{value_parser, Arg, ArgMatches, Command};
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+// Formats accounts can be exported in
+pub const FORMATS: [&str; 4] = ["aegis", "kdbx", "paper", "uri"];
+
+// Create arguments for `export` subcommand
+pub fn subcommand<'a>() -> Command<'a> {
+    Command::new("export")
+        .about("Export accounts")
+        .arg(
+            Arg::new("account")
+                .help("Name of the account to export, exports all accounts if omitted"),
+        )
+        .arg(
+            Arg::new("format")
+                .short('f')
+                .long("format")
+                .takes_value(true)
+                .value_parser(PossibleValuesParser::new(FORMATS))
+                .value_name("FORMAT")
+                .help("Format to export the accounts in, defaults to uri"),
+        )
+        .arg(
+            Arg::new("uri")
+                .long("uri")
+                .conflicts_with("format")
+                .help("Export the accounts as otpauth:// URIs, same as --format=uri"),
+        )
+        .arg(
+            Arg::new("encrypt")
+                .long("encrypt")
+                .help("Encrypt the export with a password, only supported by the aegis format"),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .takes_value(true)
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .help("Write the export to a file instead of the standard output"),
+        )
+        .arg(
+            Arg::new("keyfile")
+                .long("keyfile")
+                .takes_value(true)
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .help("Key file protecting the KeePass database, for the kdbx format"),
+        )
+}
+
+// Implementation for the `export` subcommand
+pub fn run(args: &ArgMatches, account_store: &mut AccountStore) {
+    let accounts: Vec<_> = match args.get_one::<String>("account") {
+        Some(account_name) => match account_store.get(account_name) {
+            Some(account) => vec![(account_name, account)],
+            None => {
+                println!(
+                    "Account with the name '{}' does not exist. Consider adding it.",
+                    account_name
+                );
+                return;
+            }
+        },
+        None => account_store.list().iter().collect(),
+    };
+    let accounts: Vec<(&String, Account)> = match accounts
+        .into_iter()
+        .map(|(name, account)| Ok((name, account.with_secret()?)))
+        .collect::<Result<_>>()
+    {
+        Ok(accounts) => accounts,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+    let accounts: Vec<_> = accounts
+        .iter()
+        .map(|(name, account)| (*name, account))
+        .collect();
+
+    let format = if args.contains_id("uri") {
+        "uri"
+    } else {
+        args.get_one::<String>("format")
+            .or(CONFIG.export_format.as_ref())
+            .map_or("uri", String::as_str)
+    };
+    let output = args.get_one::<PathBuf>("output");
+    if format == "kdbx" && output.is_none() {
+        eprintln!("The kdbx format is binary, use --output to write it to a file");
+        return;
+    }
+    if (format == "uri" || format == "paper") && args.contains_id("encrypt") {
+        eprintln!("Encryption is not supported by the {} format", format);
+        return;
+    }
+
+    let export = match format {
+        "aegis" => export_aegis(args, &accounts),
+        "kdbx" => export_kdbx(args, &accounts),
+        "paper" => {
+            paper::write(&accounts, &timestamp::format(timestamp::now())).map(String::into_bytes)
+        }
+        _ => accounts
+            .iter()
+            .map(|(name, account)| Ok(format!("{}\n", OtpAuth::from_account(name, account)?)))
+            .collect::<Result<String>>()
+            .map(String::into_bytes),
+    };
+    let result = export.and_then(|export| match output {
+        Some(path) => {
+            files::write_private(path, &export)?;
+            println!("Exported {} accounts to {}", accounts.len(), path.display());
+            Ok(())
+        }
+        None => Ok(io::stdout().write_all(&export)?),
+    });
+    if let Err(err) = result {
+        eprintln!("{}", err);
+    }
+}
+
+fn export_aegis(args: &ArgMatches, accounts: &[(&String, &Account)]) -> Result<Vec<u8>> {
+    let password = if args.contains_id("encrypt") {
+        Some(crypto::read_new_passphrase()?)
+    } else {
+        None
+    };
+    let vault = aegis::write(accounts, password.as_deref())?;
+    Ok(format!("{}\n", vault).into_bytes())
+}
+
+// KeePass databases are always encrypted, with a password and an optional key file
+fn export_kdbx(args: &ArgMatches, accounts: &[(&String, &Account)]) -> Result<Vec<u8>> {
+    let password = crypto::read_new_passphrase()?;
+    let key = kdbx::composite_key(
+        &password,
+        args.get_one::<PathBuf>("keyfile").map(PathBuf::as_path),
+    )?;
+    kdbx::write(accounts, &key)
+}
@@ -0,0 +1,231 @@
+use crate::account::{Account, AccountStore};
+use crate::errors::Error;
+use crate::formats::{self, Entry, ImportOptions, IMPORTERS};
+use clap::builder::PossibleValuesParser;
+use clap::{value_parser, Arg, ArgMatches, Command};
+use std::path::PathBuf;
+
+// Create arguments for `import` subcommand
+pub fn subcommand<'a>() -> Command<'a> {
+    let formats: Vec<&str> = IMPORTERS.iter().map(|importer| importer.name()).collect();
+    Command::new("import")
+        .about("Import accounts from another authenticator")
+        .arg(
+            Arg::new("format")
+                .short('f')
+                .long("format")
+                .takes_value(true)
+                .required(true)
+                .value_parser(PossibleValuesParser::new(formats))
+                .value_name("FORMAT")
+                .help("Format of the accounts to import"),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Show the accounts that would be imported without importing them"),
+        )
+        .arg(
+            Arg::new("keyfile")
+                .long("keyfile")
+                .takes_value(true)
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .help("Key file of the KeePass database, for the kdbx format"),
+        )
+        .arg(
+            Arg::new("input")
+                .required(true)
+                .multiple_values(true)
+                .value_name("INPUT")
+                .help(
+                    "Files to import. For google-migration, the otpauth-migration:// URIs or \
+                     images of the QR codes exported by Google Authenticator. For bitwarden \
+                     and 1password, an unencrypted JSON, 1PUX or CSV export.",
+                ),
+        )
+}
+
+// Implementation for the `import` subcommand
+pub fn run(args: &ArgMatches, account_store: &mut AccountStore) {
+    let inputs: Vec<&String> = args.get_many::<String>("input").unwrap().collect();
+    let importer = formats::importer(args.get_one::<String>("format").unwrap()).unwrap();
+    let options = ImportOptions {
+        keyfile: args.get_one::<PathBuf>("keyfile").cloned(),
+    };
+    let entries = match importer.read(&inputs, &options) {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+    if args.contains_id("dry-run") {
+        preview(account_store, &entries);
+    } else {
+        import_accounts(account_store, entries);
+    }
+}
+
+// Print a table of the entries and whether they would be imported
+fn preview(account_store: &AccountStore, entries: &[Entry]) {
+    let mut rows: Vec<[String; 5]> = Vec::with_capacity(entries.len());
+    let (mut importable, mut invalid, mut skipped, mut conflicting) = (0, 0, 0, 0);
+    for entry in entries {
+        let row = match entry {
+            Ok((name, account)) => {
+                let status = if account_store.get(name).is_some() {
+                    conflicting += 1;
+                    String::from("skip: an account with this name already exists")
+                } else {
+                    importable += 1;
+                    String::from("import")
+                };
+                [
+                    name.to_string(),
+                    otp_type(account).to_string(),
+                    account.hash_function.to_string(),
+                    account.digits.unwrap_or(6).to_string(),
+                    status,
+                ]
+            }
+            Err(Error::UnsupportedEntry(name, reason)) => {
+                skipped += 1;
+                unreadable_row(name, format!("skip: {}", reason))
+            }
+            Err(Error::InvalidEntry(name, reason)) => {
+                invalid += 1;
+                unreadable_row(name, format!("error: {}", reason))
+            }
+            Err(err) => {
+                invalid += 1;
+                unreadable_row("-", format!("error: {}", err))
+            }
+        };
+        rows.push(row);
+    }
+
+    let header = ["NAME", "TYPE", "ALGORITHM", "DIGITS", "STATUS"].map(String::from);
+    let mut widths = [0; 5];
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        println!("{}", cells.join("  ").trim_end());
+    }
+
+    if invalid > 0 {
+        println!(
+            "Nothing would be imported, {} of {} entries could not be read",
+            invalid,
+            entries.len()
+        );
+    } else {
+        println!(
+            "{} of {} accounts would be imported{}",
+            importable,
+            entries.len(),
+            summary(skipped, conflicting)
+        );
+    }
+}
+
+fn unreadable_row(name: &str, status: String) -> [String; 5] {
+    let empty = String::from("-");
+    [
+        name.to_string(),
+        empty.clone(),
+        empty.clone(),
+        empty,
+        status,
+    ]
+}
+
+// Add the imported accounts to the store, skipping the accounts whose name is
+// already taken rather than overwriting them. Nothing is imported if one of
+// the entries could not be read, entries of unsupported types are skipped.
+fn import_accounts(account_store: &mut AccountStore, entries: Vec<Entry>) {
+    let total = entries.len();
+    let mut accounts: Vec<(String, Account)> = Vec::new();
+    let (mut invalid, mut skipped) = (0, 0);
+    for entry in entries {
+        match entry {
+            Ok(entry) => accounts.push(entry),
+            Err(err @ Error::UnsupportedEntry(..)) => {
+                eprintln!("{}", err);
+                skipped += 1;
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                invalid += 1;
+            }
+        }
+    }
+    if invalid > 0 {
+        eprintln!(
+            "Nothing was imported, {} of {} entries could not be read",
+            invalid, total
+        );
+        return;
+    }
+
+    let (mut imported, mut conflicting) = (0, 0);
+    for (account_name, account) in accounts {
+        if account_store.get(&account_name).is_some() {
+            println!(
+                "Skipped '{}': an account with this name already exists",
+                account_name
+            );
+            conflicting += 1;
+        } else {
+            println!("Imported '{}'", account_name);
+            account_store.add(account_name, account);
+            imported += 1;
+        }
+    }
+    if imported > 0 {
+        if let Err(err) = account_store.save() {
+            eprintln!("{}", err);
+            return;
+        }
+    }
+    println!(
+        "Imported {} of {} accounts{}",
+        imported,
+        total,
+        summary(skipped, conflicting)
+    );
+}
+
+// Counts of the entries that were not imported, if any
+fn summary(skipped: usize, conflicting: usize) -> String {
+    let mut counts = Vec::new();
+    if skipped > 0 {
+        counts.push(format!("{} skipped", skipped));
+    }
+    if conflicting > 0 {
+        counts.push(format!("{} conflicting", conflicting));
+    }
+    if counts.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", counts.join(", "))
+    }
+}
+
+fn otp_type(account: &Account) -> &'static str {
+    if account.steam {
+        "Steam"
+    } else if account.totp {
+        "TOTP"
+    } else {
+        "HOTP"
+    }
+}
@@ -0,0 +1,32 @@
+use crate::account::AccountStore;
+use crate::crypto;
+use clap::{Arg, ArgMatches, Command};
+
+// Create arguments for `init` subcommand
+pub fn subcommand<'a>() -> Command<'a> {
+    Command::new("init")
+        .about("Initialize the accounts file")
+        .arg(
+            Arg::new("encrypt")
+                .long("encrypt")
+                .help("Encrypt the accounts file with a passphrase"),
+        )
+}
+
+// Implementation for the `init` subcommand
+pub fn run(args: &ArgMatches, account_store: &mut AccountStore) {
+    if account_store.is_encrypted() || !account_store.list().is_empty() {
+        println!("Accounts file is already initialized");
+        return;
+    }
+    let result = if args.contains_id("encrypt") {
+        crypto::read_new_vault_key()
+            .and_then(|vault_key| account_store.change_vault_key(Some(vault_key)))
+    } else {
+        account_store.save()
+    };
+    match result {
+        Ok(_) => println!("Accounts file successfully initialized"),
+        Err(err) => eprintln!("{}", err),
+    }
+}
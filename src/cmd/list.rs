@@ -1,34 +1,103 @@
 use crate::account::AccountStore;
 use crate::otp::OneTimePassword;
-use clap::Command;
+use clap::{Arg, ArgMatches, Command};
 
 // `list` subcommand
 pub fn subcommand<'a>() -> Command<'a> {
-    Command::new("list").about("List OTP for all accounts")
+    Command::new("list")
+        .about("List OTP for all accounts")
+        .arg(
+            Arg::new("no-increment")
+                .long("no-increment")
+                .visible_alias("peek")
+                .help("Do not advance the stored counters of HOTP accounts"),
+        )
+        .arg(
+            Arg::new("tag")
+                .short('t')
+                .long("tag")
+                .takes_value(true)
+                .value_name("TAG")
+                .help("Only list the accounts with this tag"),
+        )
+        .arg(
+            Arg::new("issuer")
+                .short('i')
+                .long("issuer")
+                .takes_value(true)
+                .value_name("ISSUER")
+                .help("Only list the accounts of this issuer"),
+        )
 }
 
 // Implementation for the `list` subcommand
-pub fn run(account_store: &mut AccountStore) {
-    let accounts = account_store.list();
+pub fn run(args: &ArgMatches, account_store: &mut AccountStore) {
+    let increment = !args.contains_id("no-increment");
+    let mut codes = Vec::new();
+    let mut hotp_accounts = Vec::new();
 
-    for (name, account) in accounts {
+    let tag = args.get_one::<String>("tag");
+    let issuer = args.get_one::<String>("issuer");
+    for (name, account) in account_store.list() {
+        // Tags and issuers are matched regardless of case
+        if let Some(tag) = tag {
+            if !account.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                continue;
+            }
+        }
+        if let Some(issuer) = issuer {
+            match account.issuer_and_label(name).0 {
+                Some(account_issuer) if account_issuer.eq_ignore_ascii_case(issuer) => {}
+                _ => continue,
+            }
+        }
+        let key = match account.secret() {
+            Ok(key) => key,
+            Err(err) => {
+                eprintln!("{}", err);
+                continue;
+            }
+        };
         let otp = OneTimePassword::new(
-            &account.key,
+            &key,
             account.totp,
             &account.hash_function,
             account.counter,
-            None,
+            account.digits,
+            account.period,
+            account.t0,
         );
+        let otp = otp.map(|otp| if account.steam { otp.steam() } else { otp });
         match otp {
             Ok(otp) => {
-                if account.totp {
-                    println!("Account: {}\nTOTP: {}", name, otp.generate());
-                } else {
-                    println!("Account: {}\nHOTP: {}", name, otp.generate());
+                codes.push((name.to_owned(), account.totp, otp.generate()));
+                if !account.totp {
+                    hotp_accounts.push(name.to_owned());
                 }
-                println!("\n");
             }
             Err(err) => eprintln!("{}", err),
         }
     }
+
+    // Persist the advanced HOTP counters before showing any of the codes
+    if increment && !hotp_accounts.is_empty() {
+        for name in hotp_accounts {
+            if let Some(account) = account_store.get_mut(&name) {
+                account.counter = Some(account.counter.unwrap_or(0).saturating_add(1));
+            }
+        }
+        if let Err(err) = account_store.save_usage() {
+            eprintln!("{}", err);
+            return;
+        }
+    }
+
+    for (name, totp, code) in codes {
+        if totp {
+            println!("Account: {}\nTOTP: {}", name, code);
+        } else {
+            println!("Account: {}\nHOTP: {}", name, code);
+        }
+        println!("\n");
+    }
 }
@@ -0,0 +1,60 @@
+use crate::account::AccountStore;
+use crate::secret::KeySource;
+use clap::{Arg, ArgMatches, Command};
+
+// `migrate-to-keyring` subcommand
+pub fn subcommand<'a>() -> Command<'a> {
+    Command::new("migrate-to-keyring")
+        .about("Move the secret keys of the accounts to the keyring of the desktop")
+        .arg(
+            Arg::new("account")
+                .multiple_values(true)
+                .help("Names of the accounts to move, defaults to all of them"),
+        )
+}
+
+// Implementation for the `migrate-to-keyring` subcommand
+pub fn run(args: &ArgMatches, account_store: &mut AccountStore) {
+    let account_names: Vec<String> = match args.get_many::<String>("account") {
+        Some(account_names) => account_names.cloned().collect(),
+        None => account_store.list().keys().cloned().collect(),
+    };
+    if let Some(account_name) = account_names
+        .iter()
+        .find(|account_name| account_store.get(account_name).is_none())
+    {
+        println!(
+            "Account with the name '{}' does not exist. Consider adding it.",
+            account_name
+        );
+        return;
+    }
+
+    // The accounts moved before an error are saved, their secrets are already
+    // in the keyring
+    let mut moved = 0;
+    let mut failed = false;
+    for account_name in &account_names {
+        let account = account_store.get_mut(account_name).unwrap();
+        // Keys read from other sources are left there
+        if !matches!(account.key_source(), KeySource::Plain(_)) {
+            continue;
+        }
+        if let Err(err) = account.move_to_keyring(account_name) {
+            eprintln!("{}", err);
+            failed = true;
+            break;
+        }
+        moved += 1;
+    }
+    if moved == 0 {
+        if !failed {
+            println!("Nothing to move, no key is stored in the accounts file");
+        }
+        return;
+    }
+    match account_store.save() {
+        Ok(_) => println!("Moved the keys of {} accounts to the keyring", moved),
+        Err(err) => eprintln!("{}", err),
+    }
+}
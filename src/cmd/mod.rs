@@ -1,4 +1,19 @@
 pub mod add;
+pub mod backup;
+pub mod config;
+pub mod decrypt;
 pub mod delete;
+pub mod edit;
+pub mod encrypt;
+pub mod export;
+pub mod import;
+pub mod init;
 pub mod list;
+pub mod migrate_to_keyring;
+pub mod passwd;
+pub mod qr;
+pub mod rename;
+pub mod restore;
+pub mod validate;
+pub mod vault;
 pub mod view;
@@ -0,0 +1,22 @@
+use crate::account::AccountStore;
+use crate::crypto;
+use clap::Command;
+
+// `passwd` subcommand
+pub fn subcommand<'a>() -> Command<'a> {
+    Command::new("passwd").about("Change the passphrase of the encrypted accounts file")
+}
+
+// Implementation for the `passwd` subcommand
+pub fn run(account_store: &mut AccountStore) {
+    if !account_store.is_encrypted() {
+        println!("Accounts file is not encrypted. Use `cloak encrypt` to encrypt it.");
+        return;
+    }
+    let result = crypto::read_new_vault_key()
+        .and_then(|vault_key| account_store.change_vault_key(Some(vault_key)));
+    match result {
+        Ok(_) => println!("Passphrase successfully changed"),
+        Err(err) => eprintln!("{}", err),
+    }
+}
@@ -0,0 +1,83 @@
+use crate::account::AccountStore;
+use crate::otpauth::OtpAuth;
+use crate::qr;
+use clap::{value_parser, Arg, ArgMatches, Command};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+// Create arguments for `qr` subcommand
+pub fn subcommand<'a>() -> Command<'a> {
+    Command::new("qr")
+        .about("Show an account as a QR code to scan with another authenticator")
+        .arg(
+            Arg::new("account")
+                .required(true)
+                .help("Name of the account"),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .takes_value(true)
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .help("Write the QR code to a PNG or SVG file instead of the terminal"),
+        )
+        .arg(
+            Arg::new("invert")
+                .long("invert")
+                .conflicts_with("output")
+                .help("Invert the colors, for terminals with a light background"),
+        )
+}
+
+// Implementation for the `qr` subcommand
+pub fn run(args: &ArgMatches, account_store: &mut AccountStore) {
+    let account_name = args.get_one::<String>("account").unwrap();
+    let account = match account_store.get(account_name) {
+        Some(account) => account,
+        None => {
+            println!(
+                "Account with the name '{}' does not exist. Consider adding it.",
+                account_name
+            );
+            return;
+        }
+    };
+
+    print!(
+        "This will reveal the secret key of {}, are you sure [N/y]? ",
+        account_name
+    );
+    io::stdout().flush().unwrap();
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        eprintln!("Failed to read input");
+        return;
+    }
+    if answer.trim().to_lowercase() != "y" {
+        println!("Abort.");
+        return;
+    }
+
+    let uri = match account
+        .with_secret()
+        .and_then(|account| OtpAuth::from_account(account_name, &account))
+    {
+        Ok(otpauth) => otpauth.to_string(),
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+    match args.get_one::<PathBuf>("output") {
+        Some(path) => match qr::write_image(&uri, path) {
+            Ok(_) => println!("QR code written to {}", path.display()),
+            Err(err) => eprintln!("{}", err),
+        },
+        None => match qr::render_terminal(&uri, args.contains_id("invert")) {
+            Ok(code) => println!("{}", code),
+            Err(err) => eprintln!("{}", err),
+        },
+    }
+}
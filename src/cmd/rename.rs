@@ -0,0 +1,43 @@
+use crate::account::AccountStore;
+use clap::{Arg, ArgMatches, Command};
+
+// Create arguments for `rename` subcommand
+pub fn subcommand<'a>() -> Command<'a> {
+    Command::new("rename")
+        .about("Rename an account")
+        .arg(
+            Arg::new("account")
+                .required(true)
+                .help("Name of the account"),
+        )
+        .arg(
+            Arg::new("new-name")
+                .required(true)
+                .value_name("NEW_NAME")
+                .help("New name of the account"),
+        )
+}
+
+// Implementation for the `rename` subcommand
+pub fn run(args: &ArgMatches, account_store: &mut AccountStore) {
+    let account_name = args.get_one::<String>("account").unwrap();
+    let new_name = args.get_one::<String>("new-name").unwrap();
+    if account_store.get(account_name).is_none() {
+        println!(
+            "Account with the name '{}' does not exist. Consider adding it.",
+            account_name
+        );
+        return;
+    }
+    if account_store.get(new_name).is_some() {
+        println!("Account with the name '{}' already exists", new_name);
+        return;
+    }
+
+    let account = account_store.delete(account_name).unwrap();
+    account_store.add(new_name.to_string(), account);
+    match account_store.save() {
+        Ok(_) => println!("Account renamed from '{}' to '{}'", account_name, new_name),
+        Err(err) => eprintln!("{}", err),
+    }
+}
@@ -0,0 +1,191 @@
+use crate::account::AccountStore;
+use crate::backup::{diff, Backup, Change};
+use crate::crypto;
+use crate::errors::Result;
+use crate::secret::ExternalKey;
+use crate::storage::Accounts;
+use crate::timestamp;
+use clap::builder::{PossibleValuesParser, RangedU64ValueParser};
+use clap::{value_parser, Arg, ArgMatches, Command};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// Create arguments for `restore` subcommand
+pub fn subcommand<'a>() -> Command<'a> {
+    Command::new("restore")
+        .about("Restore accounts from a backup made with `cloak backup`, or a previous version")
+        .arg(
+            Arg::new("file")
+                .required_unless_present("previous")
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .help("Backup file to restore"),
+        )
+        .arg(
+            Arg::new("mode")
+                .short('m')
+                .long("mode")
+                .takes_value(true)
+                .value_parser(PossibleValuesParser::new(["merge", "replace"]))
+                .default_value("merge")
+                .value_name("MODE")
+                .help(
+                    "Either merge the backup into the current accounts, or replace them \
+                     with the backup, dropping the accounts that are not in it",
+                ),
+        )
+        .arg(
+            Arg::new("previous")
+                .long("previous")
+                .takes_value(true)
+                .min_values(0)
+                .default_missing_value("1")
+                .conflicts_with_all(&["file", "mode"])
+                .value_name("VERSION")
+                .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+                .help(
+                    "Roll back to a previous version of the accounts file instead, 1 being \
+                     the version before the last change",
+                ),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Show the changes the restore would make without making them"),
+        )
+}
+
+// Implementation for the `restore` subcommand
+pub fn run(args: &ArgMatches, account_store: &mut AccountStore) {
+    // Previous versions keep the references to the keyring
+    let (accounts, keyring, replace, restored) = match args.get_one::<usize>("previous") {
+        Some(version) => match account_store.previous(*version) {
+            Ok((accounts, saved_at)) => {
+                println!(
+                    "Version {} of the accounts file, saved on {}",
+                    version,
+                    timestamp::format(saved_at)
+                );
+                (
+                    accounts,
+                    BTreeSet::new(),
+                    true,
+                    format!("version {} of the accounts file", version),
+                )
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        },
+        None => match read_backup(args.get_one::<PathBuf>("file").unwrap()) {
+            Ok(backup) => {
+                println!(
+                    "Backup of {} accounts made on {} by cloak {}",
+                    backup.accounts.len(),
+                    timestamp::format(backup.created_at),
+                    backup.cloak_version
+                );
+                let replace = args.get_one::<String>("mode").unwrap() == "replace";
+                (
+                    backup.accounts,
+                    backup.keyring,
+                    replace,
+                    String::from("the backup"),
+                )
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        },
+    };
+
+    let accounts = without_key_commands(account_store, accounts);
+    let changes: Vec<(String, Change)> = diff(account_store.list(), &accounts, replace)
+        .into_iter()
+        .map(|(name, change)| (name.to_string(), change))
+        .collect();
+    if changes.is_empty() {
+        println!("Nothing to restore, the accounts are up to date");
+        return;
+    }
+    for (name, change) in &changes {
+        let symbol = match change {
+            Change::Added => '+',
+            Change::Changed => '~',
+            Change::Dropped => '-',
+        };
+        println!("{} {}", symbol, name);
+    }
+    if args.contains_id("dry-run") {
+        return;
+    }
+
+    // Changed and dropped accounts are lost, confirm first
+    if changes.iter().any(|(_, change)| change != &Change::Added) {
+        print!("Changed and dropped accounts will be lost, are you sure [N/y]? ");
+        io::stdout().flush().unwrap();
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).is_err() {
+            eprintln!("Failed to read input");
+            return;
+        }
+        if answer.trim().to_lowercase() != "y" {
+            println!("Abort.");
+            return;
+        }
+    }
+
+    let count = changes.len();
+    let mut accounts = accounts;
+    for (name, change) in changes {
+        match change {
+            Change::Dropped => {
+                account_store.delete(&name);
+            }
+            _ => {
+                let mut account = accounts.remove(&name).unwrap();
+                // Keys that were kept in the keyring go back there
+                if keyring.contains(&name) {
+                    if let Err(err) = account.move_to_keyring(&name) {
+                        eprintln!("{}", err);
+                        return;
+                    }
+                }
+                account_store.add(name, account);
+            }
+        }
+    }
+    match account_store.save() {
+        Ok(_) => println!("Restored {}, {} accounts changed", restored, count),
+        Err(err) => eprintln!("{}", err),
+    }
+}
+
+// Commands reading the key are never restored as anyone able to change the
+// backup or a previous version could run them, the current accounts are kept
+fn without_key_commands(account_store: &AccountStore, accounts: Accounts) -> Accounts {
+    accounts
+        .into_iter()
+        .filter_map(|(name, account)| {
+            if !matches!(account.external_key, Some(ExternalKey::Command(_))) {
+                return Some((name, account));
+            }
+            eprintln!(
+                "Skipping '{}', its key is read from a command which is never restored",
+                name
+            );
+            let current = account_store.get(&name).cloned()?;
+            Some((name, current))
+        })
+        .collect()
+}
+
+fn read_backup(path: &Path) -> Result<Backup> {
+    let data = fs::read(path)?;
+    let passphrase = crypto::read_backup_passphrase()?;
+    Backup::decrypt(&data, &passphrase)
+}
@@ -0,0 +1,47 @@
+use crate::account::AccountStore;
+use crate::otp::OneTimePassword;
+use clap::Command;
+use std::process;
+
+// `validate` subcommand
+pub fn subcommand<'a>() -> Command<'a> {
+    Command::new("validate").about("Check that every account can generate OTP codes")
+}
+
+// Implementation for the `validate` subcommand
+pub fn run(account_store: &mut AccountStore) {
+    let accounts = account_store.list();
+    let mut invalid = 0;
+
+    for (name, account) in accounts {
+        let key = match account.secret() {
+            Ok(key) => key,
+            Err(err) => {
+                println!("Account '{}': {}", name, err);
+                invalid += 1;
+                continue;
+            }
+        };
+        let otp = OneTimePassword::new(
+            &key,
+            account.totp,
+            &account.hash_function,
+            account.counter,
+            account.digits,
+            account.period,
+            account.t0,
+        );
+        let otp = otp.map(|otp| if account.steam { otp.steam() } else { otp });
+        if let Err(err) = otp {
+            println!("Account '{}': {}", name, err);
+            invalid += 1;
+        }
+    }
+
+    if invalid == 0 {
+        println!("All {} accounts are valid", accounts.len());
+    } else {
+        println!("{} of {} accounts are invalid", invalid, accounts.len());
+        process::exit(1);
+    }
+}
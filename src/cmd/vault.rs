@@ -0,0 +1,139 @@
+use crate::account::AccountStore;
+use crate::dirs::{self, DEFAULT_VAULT};
+use crate::errors::Result;
+use crate::files::LockMode;
+use clap::{Arg, ArgMatches, Command};
+use std::fs;
+
+// `vault` subcommand
+pub fn subcommand<'a>() -> Command<'a> {
+    Command::new("vault")
+        .about("Manage the vaults holding separate sets of accounts")
+        .subcommand_required(true)
+        .subcommand(Command::new("list").about("List the vaults"))
+        .subcommand(
+            Command::new("create")
+                .about("Create a new vault")
+                .arg(Arg::new("name").required(true).help("Name of the vault")),
+        )
+        .subcommand(
+            Command::new("remove")
+                .about("Remove a vault without any account")
+                .arg(Arg::new("name").required(true).help("Name of the vault")),
+        )
+        .subcommand(
+            Command::new("move-account")
+                .about("Move an account from the selected vault to another vault")
+                .arg(
+                    Arg::new("account")
+                        .required(true)
+                        .help("Name of the account"),
+                )
+                .arg(
+                    Arg::new("to")
+                        .required(true)
+                        .help("Name of the vault to move the account to"),
+                ),
+        )
+}
+
+// Implementation for the `vault` subcommand, `vault` being the selected vault
+pub fn run(args: &ArgMatches, vault: &str) {
+    let result = match args.subcommand() {
+        Some(("list", _)) => list(vault),
+        Some(("create", sub_m)) => create(sub_m.get_one::<String>("name").unwrap()),
+        Some(("remove", sub_m)) => remove(sub_m.get_one::<String>("name").unwrap()),
+        Some(("move-account", sub_m)) => move_account(
+            sub_m.get_one::<String>("account").unwrap(),
+            vault,
+            sub_m.get_one::<String>("to").unwrap(),
+        ),
+        _ => Ok(()),
+    };
+    if let Err(err) = result {
+        eprintln!("{}", err);
+    }
+}
+
+// The selected vault is marked with a `*`
+fn list(vault: &str) -> Result<()> {
+    for name in dirs::vault_names()? {
+        let marker = if name == vault { "*" } else { " " };
+        println!("{} {}", marker, name);
+    }
+    Ok(())
+}
+
+fn create(name: &str) -> Result<()> {
+    fs::create_dir_all(dirs::new_vault_dir(name)?)?;
+    println!("Vault '{}' created", name);
+    Ok(())
+}
+
+// Only empty vaults are removed, so that no account is lost
+fn remove(name: &str) -> Result<()> {
+    if name == DEFAULT_VAULT {
+        println!("The default vault can't be removed");
+        return Ok(());
+    }
+    let vault_dir = dirs::vault_dir(name)?;
+    let accounts = AccountStore::new(&vault_dir, LockMode::Exclusive)?
+        .list()
+        .len();
+    if accounts > 0 {
+        println!(
+            "Vault '{}' still holds {} accounts, move or delete them first",
+            name, accounts
+        );
+        return Ok(());
+    }
+    fs::remove_dir_all(vault_dir)?;
+    println!("Vault '{}' removed", name);
+    Ok(())
+}
+
+// The account is saved to the other vault before it is deleted from this one,
+// so that it is never lost
+fn move_account(account_name: &str, from: &str, to: &str) -> Result<()> {
+    if from == to {
+        println!("The account is already in vault '{}'", to);
+        return Ok(());
+    }
+    let mut source = AccountStore::new(&dirs::vault_dir(from)?, LockMode::Exclusive)?;
+    let mut target = AccountStore::new(&dirs::vault_dir(to)?, LockMode::Exclusive)?;
+    let account = match source.get(account_name) {
+        Some(account) => account.clone(),
+        None => {
+            println!(
+                "Account with the name '{}' does not exist. Consider adding it.",
+                account_name
+            );
+            return Ok(());
+        }
+    };
+    if target.get(account_name).is_some() {
+        println!(
+            "Account with the name '{}' already exists in vault '{}'",
+            account_name, to
+        );
+        return Ok(());
+    }
+    // The key of an account of an encrypted vault is never written unencrypted
+    if source.is_encrypted() && !target.is_encrypted() {
+        println!(
+            "Vault '{}' is encrypted unlike '{}', encrypt it first with `cloak --vault {} encrypt`",
+            from, to, to
+        );
+        return Ok(());
+    }
+
+    target.add(account_name.to_string(), account);
+    target.save()?;
+    source.delete(account_name);
+    source.save()?;
+    println!(
+        "Account '{}' moved from vault '{}' to '{}'",
+        account_name, from, to
+    );
+    Ok(())
+}
@@ -1,5 +1,11 @@
-use crate::account::AccountStore;
+use crate::account::{Account, AccountStore};
+use crate::clipboard;
+use crate::config;
+use crate::errors::Result;
 use crate::otp::OneTimePassword;
+use crate::otp::{MAX_DIGITS, MIN_DIGITS};
+use crate::timestamp;
+use clap::builder::RangedU64ValueParser;
 use clap::{value_parser, Arg, ArgMatches, Command};
 
 // Create arguments for `view` subcommand
@@ -17,35 +23,148 @@ pub fn subcommand<'a>() -> Command<'a> {
                 .long("length")
                 .takes_value(true)
                 .value_name("NUMBER")
-                .default_value("6")
-                .help("Length of the OTP")
-                .value_parser(value_parser!(usize)),
+                .help("Length of the OTP, overriding the digits stored for the account")
+                .value_parser(
+                    RangedU64ValueParser::<usize>::new()
+                        .range(MIN_DIGITS as u64..=MAX_DIGITS as u64),
+                ),
+        )
+        .arg(
+            Arg::new("counter")
+                .short('c')
+                .long("counter")
+                .takes_value(true)
+                .value_name("NUMBER")
+                .help("Generate the HOTP for this counter instead of the stored one")
+                .value_parser(value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("no-increment")
+                .long("no-increment")
+                .visible_alias("peek")
+                .help(
+                    "Do not advance the stored counter of a HOTP account, nor record the \
+                     time the account was used",
+                ),
+        )
+        .arg(Arg::new("copy").long("copy").help(
+            "Copy the OTP to the clipboard instead of printing it, it is cleared after \
+             clipboard_timeout seconds",
+        ))
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .help("Show the issuer, label, tags and notes of the account"),
         )
 }
 
 // Implementation for the `view` subcommand
 pub fn run(args: &ArgMatches, account_store: &mut AccountStore) {
-    let length = args.get_one::<usize>("length").unwrap();
     let account_name = args.get_one::<String>("account").unwrap();
-    match account_store.get(account_name) {
-        Some(account) => {
-            let otp = OneTimePassword::new(
-                &account.key,
-                account.totp,
-                &account.hash_function,
-                account.counter,
-                Some(*length),
+    let increment = !args.contains_id("no-increment");
+    let account = match account_store.get_mut(account_name) {
+        Some(account) => account,
+        None => {
+            println!(
+                "Account with the name '{}' does not exist. Consider adding it.",
+                account_name
             );
-            match otp {
-                Ok(otp) => {
-                    println!("{}", otp.generate());
-                }
-                Err(err) => eprintln!("{}", err),
-            }
-        }
-        None => println!(
-            "Account with the name '{}' does not exist. Consider adding it.",
-            account_name
-        ),
+            return;
+        }
+    };
+
+    let length = args.get_one::<usize>("length").copied().or(account.digits);
+    let counter = args.get_one::<u64>("counter").copied().or(account.counter);
+    let key = match account.secret() {
+        Ok(key) => key.into_owned(),
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
     };
+    let otp = OneTimePassword::new(
+        &key,
+        account.totp,
+        &account.hash_function,
+        counter,
+        length,
+        account.period,
+        account.t0,
+    );
+    let otp = otp.map(|otp| if account.steam { otp.steam() } else { otp });
+    let code = match otp {
+        Ok(otp) => otp.generate(),
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    // Persist the next counter before showing the code, so that a HOTP code
+    // is never displayed twice
+    let details = details(account_name, account);
+    if increment {
+        if !account.totp {
+            account.counter = Some(counter.unwrap_or(0).saturating_add(1));
+        }
+        account.last_used_at = Some(timestamp::now());
+        if let Err(err) = account_store.save_usage() {
+            eprintln!("{}", err);
+            return;
+        }
+    }
+    if args.contains_id("copy") {
+        if let Err(err) = copy(&code) {
+            eprintln!("{}", err);
+            return;
+        }
+    } else {
+        println!("{}", code);
+    }
+    if args.contains_id("verbose") {
+        for (field, value) in details {
+            println!("{}: {}", field, value);
+        }
+    }
+}
+
+fn copy(code: &str) -> Result<()> {
+    clipboard::copy(code)?;
+    match config::clipboard_timeout() {
+        0 => println!("OTP copied to the clipboard"),
+        seconds => {
+            clipboard::clear_after(code, seconds)?;
+            println!(
+                "OTP copied to the clipboard, it is cleared in {} seconds",
+                seconds
+            );
+        }
+    }
+    Ok(())
+}
+
+// Metadata of the account shown by `--verbose`, as it was before this use
+fn details(account_name: &str, account: &Account) -> Vec<(&'static str, String)> {
+    let (issuer, label) = account.issuer_and_label(account_name);
+    let mut details = Vec::new();
+    if let Some(issuer) = issuer {
+        details.push(("Issuer", issuer));
+    }
+    details.push(("Label", label));
+    if !account.tags.is_empty() {
+        details.push(("Tags", account.tags.join(", ")));
+    }
+    if let Some(notes) = &account.notes {
+        details.push(("Notes", notes.to_string()));
+    }
+    if let Some(created_at) = account.created_at {
+        details.push(("Created", timestamp::format(created_at)));
+    }
+    let last_used = account.last_used_at.map(timestamp::format);
+    details.push((
+        "Last used",
+        last_used.unwrap_or_else(|| String::from("never")),
+    ));
+    details
 }
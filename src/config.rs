@@ -0,0 +1,278 @@
+use crate::cmd::export::FORMATS;
+use crate::dirs;
+use crate::errors::{Error, Result};
+use crate::files;
+use crate::otp::{HASH_FUNCTIONS, MAX_DIGITS, MIN_DIGITS};
+use crate::storage;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process;
+use toml::value::{Table, Value};
+
+const CONFIG_DIR_NAME: &str = "cloak";
+const CONFIG_FILE_NAME: &str = "config.toml";
+// Seconds after which a copied code is cleared, unless set by `clipboard_timeout`
+const CLIPBOARD_TIMEOUT: u64 = 30;
+
+// Keys of the config file, in the order they are listed
+pub const KEYS: [&str; 8] = [
+    "algorithm",
+    "digits",
+    "period",
+    "export_format",
+    "clipboard_timeout",
+    "default_vault",
+    "storage",
+    "data_dir",
+];
+
+// Settings of `config.toml`, a missing setting keeps the built-in default
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Config {
+    // Defaults of `cloak add`
+    pub algorithm: Option<String>,
+    pub digits: Option<usize>,
+    pub period: Option<u64>,
+    // Default format of `cloak export`
+    pub export_format: Option<String>,
+    // Seconds after which `cloak view --copy` clears the clipboard, 0 to keep it
+    pub clipboard_timeout: Option<u64>,
+    // Vault used without `--vault` or `CLOAK_VAULT`
+    pub default_vault: Option<String>,
+    // Storage backend of new vaults, existing ones keep theirs
+    pub storage: Option<String>,
+    // Directory holding the accounts instead of the XDG data directory
+    pub data_dir: Option<PathBuf>,
+    // Unknown settings, e.g. of a newer version of cloak, kept as they are
+    #[serde(flatten)]
+    other: Table,
+}
+
+impl Config {
+    // Read the config file, every setting is unset if there is none. Unknown
+    // settings are ignored with a warning, invalid ones are an error.
+    pub fn load() -> Result<Config> {
+        Config::read(false)
+    }
+
+    // Read the config file to change it, ignoring invalid settings and even a
+    // file that can't be parsed with a warning, so that it can be fixed
+    pub fn load_lenient() -> Result<Config> {
+        Config::read(true)
+    }
+
+    fn read(lenient: bool) -> Result<Config> {
+        let path = config_file_path()?;
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Config::default()),
+            Err(err) => return Err(config_error(&path, err)),
+        };
+        let table = match toml::from_str::<Table>(&content) {
+            Ok(table) => table,
+            Err(err) if lenient => {
+                eprintln!(
+                    "{}, it is kept as {} when a setting is changed",
+                    config_error(&path, err),
+                    files::previous_path(&path, 1).display()
+                );
+                return Ok(Config::default());
+            }
+            Err(err) => return Err(config_error(&path, err)),
+        };
+
+        let mut config = Config::default();
+        for (key, value) in table {
+            if !KEYS.contains(&key.as_str()) {
+                eprintln!(
+                    "Ignoring the unknown setting '{}' of {}",
+                    key,
+                    path.display()
+                );
+                config.other.insert(key, value);
+                continue;
+            }
+            // Values are checked the same way as when set with `cloak config set`
+            let result = match &value {
+                Value::String(value) => config.set(&key, Some(value)),
+                Value::Integer(value) => config.set(&key, Some(&value.to_string())),
+                _ => Err(Error::InvalidConfigValue(
+                    "config file",
+                    format!("{} = {}", key, value),
+                )),
+            };
+            match result {
+                Err(err) if lenient => {
+                    eprintln!("Ignoring an invalid setting of {}: {}", path.display(), err)
+                }
+                Err(err) => return Err(config_error(&path, err)),
+                Ok(()) => {}
+            }
+        }
+        Ok(config)
+    }
+
+    // Save the config file, keeping the previous one as `config.toml.1`
+    pub fn save(&self) -> Result<()> {
+        let path = config_file_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        files::write_atomic(&path, toml::to_string(self)?.as_bytes(), 1)
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        let value = match key {
+            "algorithm" => self.algorithm.clone(),
+            "digits" => self.digits.map(|digits| digits.to_string()),
+            "period" => self.period.map(|period| period.to_string()),
+            "export_format" => self.export_format.clone(),
+            "clipboard_timeout" => self.clipboard_timeout.map(|seconds| seconds.to_string()),
+            "default_vault" => self.default_vault.clone(),
+            "storage" => self.storage.clone(),
+            "data_dir" => self
+                .data_dir
+                .as_ref()
+                .map(|dir| dir.to_string_lossy().into_owned()),
+            _ => return Err(Error::UnknownConfigKey(key.to_string())),
+        };
+        Ok(value)
+    }
+
+    // Set a setting from its value as given on the command line, or unset it
+    pub fn set(&mut self, key: &str, value: Option<&str>) -> Result<()> {
+        match key {
+            "algorithm" => self.algorithm = value.map(parse_algorithm).transpose()?,
+            "digits" => self.digits = value.map(parse_digits).transpose()?,
+            "period" => self.period = value.map(parse_period).transpose()?,
+            "export_format" => self.export_format = value.map(parse_export_format).transpose()?,
+            "clipboard_timeout" => {
+                self.clipboard_timeout = value.map(parse_clipboard_timeout).transpose()?
+            }
+            "default_vault" => self.default_vault = value.map(parse_vault).transpose()?,
+            "storage" => self.storage = value.map(storage::parse_storage).transpose()?,
+            "data_dir" => self.data_dir = value.map(parse_data_dir).transpose()?,
+            _ => return Err(Error::UnknownConfigKey(key.to_string())),
+        }
+        Ok(())
+    }
+}
+
+fn parse_algorithm(value: &str) -> Result<String> {
+    let algorithm = value.to_uppercase();
+    if !HASH_FUNCTIONS.contains(&algorithm.as_str()) {
+        return Err(Error::UnknownHashFunction(value.to_string()));
+    }
+    Ok(algorithm)
+}
+
+fn parse_digits(value: &str) -> Result<usize> {
+    match value.parse() {
+        Ok(digits) if (MIN_DIGITS..=MAX_DIGITS).contains(&digits) => Ok(digits),
+        Ok(digits) => Err(Error::InvalidDigits(digits)),
+        Err(_) => Err(Error::InvalidConfigValue("digits", value.to_string())),
+    }
+}
+
+fn parse_period(value: &str) -> Result<u64> {
+    match value.parse() {
+        Ok(0) => Err(Error::InvalidPeriod),
+        Ok(period) => Ok(period),
+        Err(_) => Err(Error::InvalidConfigValue("period", value.to_string())),
+    }
+}
+
+fn parse_export_format(value: &str) -> Result<String> {
+    if !FORMATS.contains(&value) {
+        return Err(Error::InvalidConfigValue(
+            "export_format",
+            format!("{}, expected one of {}", value, FORMATS.join(", ")),
+        ));
+    }
+    Ok(value.to_string())
+}
+
+fn parse_clipboard_timeout(value: &str) -> Result<u64> {
+    value
+        .parse()
+        .map_err(|_| Error::InvalidConfigValue("clipboard_timeout", value.to_string()))
+}
+
+fn parse_vault(value: &str) -> Result<String> {
+    if value != dirs::DEFAULT_VAULT && !dirs::is_vault_name(value) {
+        return Err(Error::InvalidVaultName(value.to_string()));
+    }
+    Ok(value.to_string())
+}
+
+fn parse_data_dir(value: &str) -> Result<PathBuf> {
+    let dir = PathBuf::from(value);
+    if !dir.is_absolute() {
+        return Err(Error::InvalidConfigValue(
+            "data_dir",
+            format!("{} is not an absolute path", value),
+        ));
+    }
+    Ok(dir)
+}
+
+// `$XDG_CONFIG_HOME/cloak/config.toml`, falling back to the configuration
+// directory of the platform
+pub fn config_file_path() -> Result<PathBuf> {
+    let dir = dirs::xdg_dir("XDG_CONFIG_HOME", dirs_next::config_dir)?;
+    Ok(dir.join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME))
+}
+
+fn config_error(path: &Path, err: impl ToString) -> Error {
+    Error::Config(path.display().to_string(), err.to_string())
+}
+
+// Seconds after which a copied code is cleared from the clipboard
+pub fn clipboard_timeout() -> u64 {
+    CONFIG.clipboard_timeout.unwrap_or(CLIPBOARD_TIMEOUT)
+}
+
+// Vault selected by `--vault`, `CLOAK_VAULT` or the config file
+pub fn vault(arg: Option<&String>) -> String {
+    arg.cloned()
+        .or_else(|| env::var("CLOAK_VAULT").ok())
+        .or_else(|| CONFIG.default_vault.clone())
+        .unwrap_or_else(|| String::from(dirs::DEFAULT_VAULT))
+}
+
+lazy_static! {
+    pub static ref CONFIG: Config = Config::load().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_set_and_get() {
+        let mut config = Config::default();
+        config.set("algorithm", Some("sha256")).unwrap();
+        config.set("digits", Some("8")).unwrap();
+        config.set("data_dir", Some("/srv/cloak")).unwrap();
+        assert_eq!(config.get("algorithm").unwrap().as_deref(), Some("SHA256"));
+        assert_eq!(config.digits, Some(8));
+        assert_eq!(config.data_dir, Some(PathBuf::from("/srv/cloak")));
+
+        config.set("digits", None).unwrap();
+        assert_eq!(config.get("digits").unwrap(), None);
+        assert!(config.set("digits", Some("12")).is_err());
+        assert!(config.set("period", Some("0")).is_err());
+        assert!(config.set("export_format", Some("csv")).is_err());
+        assert!(config.set("default_vault", Some("../work")).is_err());
+        assert!(config.set("data_dir", Some("relative/dir")).is_err());
+        assert!(config.set("storage", Some("git")).is_err());
+        assert!(config.set("clipboard_timeout", Some("-1")).is_err());
+        assert!(config.set("clipboard", Some("10")).is_err());
+    }
+}
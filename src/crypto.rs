@@ -0,0 +1,370 @@
+use crate::errors::{Error, Result};
+use crate::otp::HashFunction;
+use ring::aead::{
+    self, Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, CHACHA20_POLY1305, NONCE_LEN,
+};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::convert::TryInto;
+use std::env;
+use std::num::NonZeroU32;
+
+// Magic bytes identifying an encrypted accounts file
+const MAGIC: &[u8; 8] = b"CLOAKENC";
+const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+// magic | version | log_n | r | p | salt | nonce
+const HEADER_LEN: usize = MAGIC.len() + 1 + 1 + 4 + 4 + SALT_LEN + NONCE_LEN;
+
+// scrypt cost parameters used for new vaults (N = 2^15, r = 8, p = 1)
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+// Highest scrypt cost parameters accepted from the header of a vault or a
+// backup, which isn't authenticated until the key is derived. They bound the
+// memory used to 1 GiB (128 * r * N bytes).
+const MAX_SCRYPT_LOG_N: u8 = 20;
+const MAX_SCRYPT_R: u32 = 8;
+const MAX_SCRYPT_P: u32 = 4;
+
+// Environment variables used to provide passphrases non-interactively
+const PASSPHRASE_ENV: &str = "CLOAK_PASSPHRASE";
+const NEW_PASSPHRASE_ENV: &str = "CLOAK_NEW_PASSPHRASE";
+// Password of backups, made by cloak or exported by other authenticators
+const BACKUP_PASSPHRASE_ENV: &str = "CLOAK_BACKUP_PASSPHRASE";
+
+// Key derived from a passphrase, along with the parameters used to derive it
+pub struct VaultKey {
+    key: [u8; KEY_LEN],
+    salt: [u8; SALT_LEN],
+    log_n: u8,
+    r: u32,
+    p: u32,
+}
+
+impl VaultKey {
+    // Derive a key from the passphrase using a fresh random salt
+    pub fn generate(passphrase: &str) -> Result<VaultKey> {
+        let mut salt = [0_u8; SALT_LEN];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|_| Error::Crypto("could not generate a random salt"))?;
+        VaultKey::derive(passphrase, salt, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)
+    }
+
+    fn derive(
+        passphrase: &str,
+        salt: [u8; SALT_LEN],
+        log_n: u8,
+        r: u32,
+        p: u32,
+    ) -> Result<VaultKey> {
+        let key = scrypt_key(passphrase, &salt, log_n, r, p)?;
+        Ok(VaultKey {
+            key,
+            salt,
+            log_n,
+            r,
+            p,
+        })
+    }
+
+    fn aead_key(&self) -> LessSafeKey {
+        LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &self.key).unwrap())
+    }
+}
+
+// Check whether the contents of a file are an encrypted vault
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+// Encrypt the plaintext, returning the header followed by the ciphertext
+pub fn encrypt(plaintext: &[u8], key: &VaultKey) -> Result<Vec<u8>> {
+    let mut nonce = [0_u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| Error::Crypto("could not generate a random nonce"))?;
+
+    let mut data = Vec::with_capacity(HEADER_LEN + plaintext.len() + aead::MAX_TAG_LEN);
+    data.extend_from_slice(MAGIC);
+    data.push(FORMAT_VERSION);
+    data.push(key.log_n);
+    data.extend_from_slice(&key.r.to_be_bytes());
+    data.extend_from_slice(&key.p.to_be_bytes());
+    data.extend_from_slice(&key.salt);
+    data.extend_from_slice(&nonce);
+
+    // The header is authenticated so that the KDF parameters can't be altered
+    let mut in_out = plaintext.to_vec();
+    key.aead_key()
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(&data[..]),
+            &mut in_out,
+        )
+        .map_err(|_| Error::Crypto("encryption failed"))?;
+    data.extend_from_slice(&in_out);
+    Ok(data)
+}
+
+// Decrypt a vault, returning the plaintext and the key used so that the
+// vault can be encrypted again on save
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<(Vec<u8>, VaultKey)> {
+    let (salt, log_n, r, p) = read_header(data)?;
+    let key = VaultKey::derive(passphrase, salt, log_n, r, p)?;
+    let plaintext = open(data, &key)?;
+    Ok((plaintext, key))
+}
+
+// Decrypt a vault with a key derived earlier, which only works if the vault
+// was encrypted with the same key, e.g. a previous version of the accounts file
+pub fn decrypt_with_key(data: &[u8], key: &VaultKey) -> Result<Vec<u8>> {
+    let (salt, log_n, r, p) = read_header(data)?;
+    if (salt, log_n, r, p) != (key.salt, key.log_n, key.r, key.p) {
+        return Err(Error::Decrypt);
+    }
+    open(data, key)
+}
+
+// Read the key derivation parameters from the header of a vault
+fn read_header(data: &[u8]) -> Result<([u8; SALT_LEN], u8, u32, u32)> {
+    if !is_encrypted(data) || data.len() < HEADER_LEN + aead::MAX_TAG_LEN {
+        return Err(Error::MalformedVault);
+    }
+    let version = data[MAGIC.len()];
+    if version != FORMAT_VERSION {
+        return Err(Error::UnsupportedVaultVersion(version));
+    }
+    let log_n = data[MAGIC.len() + 1];
+    let r = u32::from_be_bytes(data[10..14].try_into().unwrap());
+    let p = u32::from_be_bytes(data[14..18].try_into().unwrap());
+    let salt: [u8; SALT_LEN] = data[18..18 + SALT_LEN].try_into().unwrap();
+    Ok((salt, log_n, r, p))
+}
+
+fn open(data: &[u8], key: &VaultKey) -> Result<Vec<u8>> {
+    let (header, ciphertext) = data.split_at(HEADER_LEN);
+    let nonce: [u8; NONCE_LEN] = header[18 + SALT_LEN..].try_into().unwrap();
+    let mut in_out = ciphertext.to_vec();
+    let plaintext_len = key
+        .aead_key()
+        .open_in_place(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(header),
+            &mut in_out,
+        )
+        .map_err(|_| Error::Decrypt)?
+        .len();
+    in_out.truncate(plaintext_len);
+    Ok(in_out)
+}
+
+// Derive a 256 bit key from a passphrase with scrypt
+pub fn scrypt_key(
+    passphrase: &str,
+    salt: &[u8],
+    log_n: u8,
+    r: u32,
+    p: u32,
+) -> Result<[u8; KEY_LEN]> {
+    if log_n > MAX_SCRYPT_LOG_N || r > MAX_SCRYPT_R || p > MAX_SCRYPT_P {
+        return Err(Error::Crypto(
+            "the key derivation parameters exceed the limits of cloak",
+        ));
+    }
+    let params = scrypt::Params::new(log_n, r, p)
+        .map_err(|_| Error::Crypto("invalid key derivation parameters"))?;
+    let mut key = [0_u8; KEY_LEN];
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key)
+        .map_err(|_| Error::Crypto("key derivation failed"))?;
+    Ok(key)
+}
+
+// Derive a 256 bit key from a passphrase with PBKDF2, used by the backups of
+// other authenticators
+pub fn pbkdf2_key(
+    hash_function: &HashFunction,
+    passphrase: &str,
+    salt: &[u8],
+    iterations: u32,
+) -> Result<[u8; KEY_LEN]> {
+    let algorithm = match hash_function {
+        HashFunction::Sha1 => pbkdf2::PBKDF2_HMAC_SHA1,
+        HashFunction::Sha256 => pbkdf2::PBKDF2_HMAC_SHA256,
+        HashFunction::Sha384 => pbkdf2::PBKDF2_HMAC_SHA384,
+        HashFunction::Sha512 => pbkdf2::PBKDF2_HMAC_SHA512,
+        HashFunction::Sha512_256 => return Err(Error::Crypto("unsupported PBKDF2 hash function")),
+    };
+    let iterations =
+        NonZeroU32::new(iterations).ok_or(Error::Crypto("invalid key derivation parameters"))?;
+    let mut key = [0_u8; KEY_LEN];
+    pbkdf2::derive(algorithm, iterations, salt, passphrase.as_bytes(), &mut key);
+    Ok(key)
+}
+
+pub fn random_bytes(len: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![0_u8; len];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| Error::Crypto("could not generate random bytes"))?;
+    Ok(bytes)
+}
+
+// Encrypt with AES-256-GCM, as used by the backups of other authenticators.
+// Returns the random nonce and the ciphertext followed by the tag.
+pub fn aes_gcm_seal(key: &[u8], plaintext: &[u8]) -> Result<([u8; NONCE_LEN], Vec<u8>)> {
+    let key = UnboundKey::new(&AES_256_GCM, key).map_err(|_| Error::Crypto("invalid key"))?;
+    let nonce: [u8; NONCE_LEN] = random_bytes(NONCE_LEN)?.try_into().unwrap();
+    let mut in_out = plaintext.to_vec();
+    LessSafeKey::new(key)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::empty(),
+            &mut in_out,
+        )
+        .map_err(|_| Error::Crypto("encryption failed"))?;
+    Ok((nonce, in_out))
+}
+
+// Decrypt the ciphertext, followed by its tag, with AES-256-GCM. Returns `None`
+// if the key is wrong or the data has been tampered with.
+pub fn aes_gcm_open(key: &[u8], nonce: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+    let key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).ok()?);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+    let mut in_out = ciphertext.to_vec();
+    let plaintext_len = key
+        .open_in_place(nonce, Aad::empty(), &mut in_out)
+        .ok()?
+        .len();
+    in_out.truncate(plaintext_len);
+    Some(in_out)
+}
+
+// Read the passphrase of an encrypted vault
+pub fn read_passphrase() -> Result<String> {
+    match env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => Ok(rpassword::prompt_password("Passphrase: ")?),
+    }
+}
+
+// Read a new passphrase, asking for it twice to rule out typos
+pub fn read_new_passphrase() -> Result<String> {
+    let passphrase = match env::var(NEW_PASSPHRASE_ENV) {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            let passphrase = rpassword::prompt_password("New passphrase: ")?;
+            let confirmation = rpassword::prompt_password("Confirm new passphrase: ")?;
+            if passphrase != confirmation {
+                return Err(Error::PassphraseMismatch);
+            }
+            passphrase
+        }
+    };
+    if passphrase.is_empty() {
+        return Err(Error::EmptyPassphrase);
+    }
+    Ok(passphrase)
+}
+
+// Read the password of a backup, made by cloak or another authenticator
+pub fn read_backup_passphrase() -> Result<String> {
+    match env::var(BACKUP_PASSPHRASE_ENV) {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => Ok(rpassword::prompt_password("Backup password: ")?),
+    }
+}
+
+// Read a new passphrase and derive a new key from it
+pub fn read_new_vault_key() -> Result<VaultKey> {
+    let passphrase = read_new_passphrase()?;
+    VaultKey::generate(&passphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        aes_gcm_open, aes_gcm_seal, decrypt, decrypt_with_key, encrypt, is_encrypted, VaultKey,
+    };
+    use crate::errors::Error;
+
+    fn test_key(passphrase: &str) -> VaultKey {
+        // Cheap parameters to keep the tests fast
+        VaultKey::derive(passphrase, [7; 16], 4, 8, 1).unwrap()
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let key = test_key("hunter2");
+        let data = encrypt(b"[github]\nkey = \"ABC\"\n", &key).unwrap();
+        assert!(is_encrypted(&data));
+        let (plaintext, _) = decrypt(&data, "hunter2").unwrap();
+        assert_eq!(plaintext, b"[github]\nkey = \"ABC\"\n");
+    }
+
+    #[test]
+    fn test_decrypt_with_key() {
+        let key = test_key("hunter2");
+        let data = encrypt(b"secret", &key).unwrap();
+        assert_eq!(decrypt_with_key(&data, &key).unwrap(), b"secret");
+        // A key derived from the same passphrase with another salt is different
+        let other_key = VaultKey::derive("hunter2", [8; 16], 4, 8, 1).unwrap();
+        assert!(matches!(
+            decrypt_with_key(&data, &other_key),
+            Err(Error::Decrypt)
+        ));
+    }
+
+    #[test]
+    fn test_decrypt_wrong_passphrase() {
+        let key = test_key("hunter2");
+        let data = encrypt(b"secret", &key).unwrap();
+        assert!(matches!(decrypt(&data, "hunter3"), Err(Error::Decrypt)));
+    }
+
+    #[test]
+    fn test_decrypt_tampered_data() {
+        let key = test_key("hunter2");
+        let mut data = encrypt(b"secret", &key).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        assert!(matches!(decrypt(&data, "hunter2"), Err(Error::Decrypt)));
+
+        // Tampering with the authenticated header is detected as well
+        let mut data = encrypt(b"secret", &key).unwrap();
+        data[20] ^= 1;
+        assert!(matches!(decrypt(&data, "hunter2"), Err(Error::Decrypt)));
+    }
+
+    #[test]
+    fn test_decrypt_expensive_parameters() {
+        let key = test_key("hunter2");
+        let data = encrypt(b"secret", &key).unwrap();
+        // log_n, then r and p as big-endian integers
+        for (index, value) in [(9, 40), (13, 0xff), (17, 0xff)] {
+            let mut data = data.clone();
+            data[index] = value;
+            assert!(matches!(decrypt(&data, "hunter2"), Err(Error::Crypto(_))));
+        }
+    }
+
+    #[test]
+    fn test_decrypt_malformed_data() {
+        assert!(!is_encrypted(b"[github]"));
+        assert!(matches!(
+            decrypt(b"CLOAKENC\x01", "hunter2"),
+            Err(Error::MalformedVault)
+        ));
+    }
+
+    #[test]
+    fn test_aes_gcm_roundtrip() {
+        let key = [3_u8; 32];
+        let (nonce, ciphertext) = aes_gcm_seal(&key, b"secret").unwrap();
+        assert_eq!(ciphertext.len(), b"secret".len() + 16);
+        assert_eq!(aes_gcm_open(&key, &nonce, &ciphertext).unwrap(), b"secret");
+        assert!(aes_gcm_open(&[4_u8; 32], &nonce, &ciphertext).is_none());
+    }
+}
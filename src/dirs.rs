@@ -1,20 +1,132 @@
+use crate::config::CONFIG;
 use crate::errors::{Error, Result};
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+// Directory used by previous versions, in the home directory
 const CLOAK_DIR_NAME: &str = ".cloak/";
-const CLOAK_ACCOUNTS_FILE_NAME: &str = "accounts";
+const DATA_DIR_NAME: &str = "cloak";
+pub const CLOAK_ACCOUNTS_FILE_NAME: &str = "accounts";
+const VAULTS_DIR_NAME: &str = "vaults";
+// Name of the vault kept in the data directory itself
+pub const DEFAULT_VAULT: &str = "default";
 
-fn accounts_file_path() -> Result<PathBuf> {
-    let cloak_dir = env::var("CLOAK_ACCOUNTS_DIR")
+// Directory holding the accounts: `CLOAK_ACCOUNTS_DIR`, the `data_dir` of the
+// config file or `$XDG_DATA_HOME/cloak`
+fn cloak_dir() -> Result<PathBuf> {
+    let cloak_dir = match env::var("CLOAK_ACCOUNTS_DIR")
         .ok()
         .map(PathBuf::from)
         .filter(|acc_dir| acc_dir.is_absolute())
-        .or_else(|| dirs_next::home_dir().map(|d| d.join(CLOAK_DIR_NAME)))
-        .ok_or(Error::CloakDirNotFound)?;
+        .or_else(|| CONFIG.data_dir.clone())
+    {
+        Some(cloak_dir) => cloak_dir,
+        None => data_dir()?,
+    };
     fs::create_dir_all(&cloak_dir)?;
-    let file_path = Path::new(&cloak_dir).join(CLOAK_ACCOUNTS_FILE_NAME);
+    Ok(cloak_dir)
+}
+
+// Accounts kept in `~/.cloak/` by previous versions are moved to the data
+// directory the first time it is used
+fn data_dir() -> Result<PathBuf> {
+    let data_dir = xdg_dir("XDG_DATA_HOME", dirs_next::data_dir)?.join(DATA_DIR_NAME);
+    let legacy_dir = dirs_next::home_dir()
+        .ok_or(Error::CloakDirNotFound)?
+        .join(CLOAK_DIR_NAME);
+    if data_dir.exists() || !legacy_dir.is_dir() {
+        return Ok(data_dir);
+    }
+    if let Some(parent) = data_dir.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::rename(&legacy_dir, &data_dir) {
+        Ok(()) => eprintln!(
+            "Moved the accounts from {} to {}",
+            legacy_dir.display(),
+            data_dir.display()
+        ),
+        // Another cloak process moved them first
+        Err(_) if data_dir.is_dir() => {}
+        Err(err) => {
+            return Err(Error::DataDirMigration(
+                legacy_dir.display().to_string(),
+                data_dir.display().to_string(),
+                err,
+            ))
+        }
+    }
+    Ok(data_dir)
+}
+
+// Base directory given by an XDG environment variable, which is ignored unless
+// it is an absolute path, or else the directory of the platform
+pub fn xdg_dir(var: &str, platform_dir: fn() -> Option<PathBuf>) -> Result<PathBuf> {
+    env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(platform_dir)
+        .ok_or(Error::CloakDirNotFound)
+}
+
+// Directory of a vault. The default vault is the data directory itself, the
+// others are kept under `vaults/`.
+pub fn vault_dir(vault: &str) -> Result<PathBuf> {
+    if vault == DEFAULT_VAULT {
+        return Ok(CLOAK_DIR.to_path_buf());
+    }
+    let dir = vaults_dir().join(vault);
+    if !is_vault_name(vault) || !dir.is_dir() {
+        return Err(Error::VaultNotFound(vault.to_string()));
+    }
+    Ok(dir)
+}
+
+// Directory of a new vault, which doesn't exist yet
+pub fn new_vault_dir(vault: &str) -> Result<PathBuf> {
+    if !is_vault_name(vault) || vault == DEFAULT_VAULT {
+        return Err(Error::InvalidVaultName(vault.to_string()));
+    }
+    let dir = vaults_dir().join(vault);
+    if dir.exists() {
+        return Err(Error::VaultExists(vault.to_string()));
+    }
+    Ok(dir)
+}
+
+// Names of the vaults, the default one first
+pub fn vault_names() -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    if vaults_dir().is_dir() {
+        for entry in fs::read_dir(vaults_dir())? {
+            let entry = entry?;
+            match entry.file_name().into_string() {
+                Ok(name) if entry.path().is_dir() && is_vault_name(&name) => names.push(name),
+                _ => {}
+            }
+        }
+    }
+    names.sort();
+    names.insert(0, DEFAULT_VAULT.to_string());
+    Ok(names)
+}
+
+fn vaults_dir() -> PathBuf {
+    CLOAK_DIR.join(VAULTS_DIR_NAME)
+}
+
+// Vault names are used as directory names
+pub fn is_vault_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// Path of the accounts file of a vault, created if it doesn't exist yet
+pub fn accounts_file_path(vault_dir: &Path) -> Result<PathBuf> {
+    let file_path = vault_dir.join(CLOAK_ACCOUNTS_FILE_NAME);
     if !file_path.is_file() {
         create_file(&file_path)?;
     }
@@ -22,7 +134,7 @@ fn accounts_file_path() -> Result<PathBuf> {
 }
 
 #[cfg(unix)]
-fn create_file(file_path: &Path) -> Result<()> {
+pub fn create_file(file_path: &Path) -> Result<()> {
     use std::os::unix::fs::OpenOptionsExt;
     let mut options = fs::OpenOptions::new();
     options.mode(0o600);
@@ -31,7 +143,7 @@ fn create_file(file_path: &Path) -> Result<()> {
 }
 
 #[cfg(not(unix))]
-fn create_file(file_path: &Path) -> Result<()> {
+pub fn create_file(file_path: &Path) -> Result<()> {
     fs::OpenOptions::new()
         .write(true)
         .create_new(true)
@@ -40,6 +152,5 @@ fn create_file(file_path: &Path) -> Result<()> {
 }
 
 lazy_static! {
-    pub static ref CLOAK_ACCOUNTS_FILE_PATH: PathBuf =
-        accounts_file_path().expect("Could not get cloak's accounts file path");
+    pub static ref CLOAK_DIR: PathBuf = cloak_dir().expect("Could not get cloak's directory");
 }
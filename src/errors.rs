@@ -1,8 +1,11 @@
 use data_encoding::DecodeError;
+use image::ImageError;
+use qrcode::types::QrError;
 use std::io;
 use std::result;
 use thiserror::Error as ThisError;
 use toml::{de, ser};
+use zip::result::ZipError;
 
 // `Result` returned by this crate
 pub type Result<T> = result::Result<T, Error>;
@@ -16,6 +19,56 @@ pub enum Error {
         cause: Box<DecodeError>,
     },
 
+    #[error("Invalid otpauth URI: {}", _0)]
+    InvalidUri(String),
+
+    #[error(
+        "Account '{}' counts time steps from {} rather than the Unix epoch, which can't be \
+         exported",
+        _0,
+        _1
+    )]
+    UnsupportedEpoch(String, u64),
+
+    #[error("Account '{}' uses {}, which {} doesn't support", _0, _1, _2)]
+    UnsupportedHashFunction(String, String, &'static str),
+
+    #[error("Invalid Google Authenticator export: {}", _0)]
+    InvalidMigrationPayload(String),
+
+    #[error("Invalid {} backup: {}", _0, _1)]
+    InvalidBackup(&'static str, String),
+
+    #[error(
+        "Could not decrypt the {} backup: wrong password or the file has been tampered with",
+        _0
+    )]
+    BackupDecrypt(&'static str),
+
+    #[error("Skipped '{}': {}", _0, _1)]
+    UnsupportedEntry(String, String),
+
+    #[error("Invalid entry '{}': {}", _0, _1)]
+    InvalidEntry(String, String),
+
+    #[error("Could not parse the CSV file: {}", _0)]
+    Csv(#[from] csv::Error),
+
+    #[error("Could not read the zip archive: {}", _0)]
+    Zip(#[from] ZipError),
+
+    #[error("Could not parse the JSON file: {}", _0)]
+    Json(#[from] serde_json::Error),
+
+    #[error("Unsupported hash function '{}'", _0)]
+    UnknownHashFunction(String),
+
+    #[error("Invalid number of digits {}, codes can have 1 to 9 digits", _0)]
+    InvalidDigits(usize),
+
+    #[error("The period of a time based account must be greater than zero")]
+    InvalidPeriod,
+
     #[error("Cloak directory not found")]
     CloakDirNotFound,
 
@@ -27,4 +80,130 @@ pub enum Error {
 
     #[error("Could not parse the accounts file: {}", _0)]
     TomlDeserialize(#[from] de::Error),
+
+    #[error(
+        "Could not decrypt the accounts file: wrong passphrase or the file has been tampered with"
+    )]
+    Decrypt,
+
+    #[error("The accounts file is not a valid encrypted vault")]
+    MalformedVault,
+
+    #[error(
+        "The accounts file has schema version {}, this version of cloak supports up to \
+         version {}. Upgrade cloak to read it.",
+        _0,
+        _1
+    )]
+    UnsupportedSchemaVersion(u32, u32),
+
+    #[error(
+        "The accounts file is used by another cloak process, gave up waiting after {} \
+         seconds. Set CLOAK_LOCK_TIMEOUT to wait longer.",
+        _0
+    )]
+    Locked(u64),
+
+    #[error(
+        "There is no version {} of the accounts file, up to {} previous versions are kept",
+        _0,
+        _1
+    )]
+    NoPreviousVersion(usize, usize),
+
+    #[error("The {} storage doesn't keep previous versions of the accounts", _0)]
+    PreviousVersionsUnsupported(&'static str),
+
+    #[error("Unknown storage '{}', expected one of file, directory or sqlite", _0)]
+    UnknownStorage(String),
+
+    #[error("The vault in {} uses the {} storage, not {}", _0, _1, _2)]
+    StorageMismatch(String, String, String),
+
+    #[error(
+        "The vault in {} holds accounts of the {} storages, select one with CLOAK_STORAGE",
+        _0,
+        _1
+    )]
+    AmbiguousStorage(String, String),
+
+    #[error("Could not read the key from '{}': {}", _0, _1)]
+    KeySource(String, String),
+
+    #[error(
+        "Vault '{}' does not exist, create it with `cloak vault create {}`",
+        _0,
+        _0
+    )]
+    VaultNotFound(String),
+
+    #[error("Vault '{}' already exists", _0)]
+    VaultExists(String),
+
+    #[error(
+        "Invalid vault name '{}', use letters, digits, '-' and '_' other than 'default'",
+        _0
+    )]
+    InvalidVaultName(String),
+
+    #[error("Could not read the config file {}: {}", _0, _1)]
+    Config(String, String),
+
+    #[error(
+        "Unknown config key '{}', expected one of algorithm, digits, period, \
+         export_format, clipboard_timeout, default_vault, storage or data_dir",
+        _0
+    )]
+    UnknownConfigKey(String),
+
+    #[error("Invalid value for {}: {}", _0, _1)]
+    InvalidConfigValue(&'static str, String),
+
+    #[error("Could not move the accounts from {} to {}: {}", _0, _1, _2)]
+    DataDirMigration(String, String, io::Error),
+
+    #[error("Could not use the clipboard: {}", _0)]
+    Clipboard(String),
+
+    #[error("Keyring error: {}", _0)]
+    Keyring(String),
+
+    #[error("The secret {} is missing from the keyring", _0)]
+    KeyringSecretNotFound(String),
+
+    #[error("Could not reach the keyring: {}", _0)]
+    DBus(#[from] zbus::Error),
+
+    #[error("SQLite error: {}", _0)]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("Unsupported encrypted vault version {}", _0)]
+    UnsupportedVaultVersion(u8),
+
+    #[error("Encryption error: {}", _0)]
+    Crypto(&'static str),
+
+    #[error("Passphrases do not match")]
+    PassphraseMismatch,
+
+    #[error("Passphrase cannot be empty")]
+    EmptyPassphrase,
+
+    #[error("Could not read the image: {}", _0)]
+    Image(#[from] ImageError),
+
+    #[error("No QR code found in the image")]
+    QrCodeNotFound,
+
+    #[error("Found {} QR codes in the image, expected exactly one", _0)]
+    MultipleQrCodes(usize),
+
+    #[error("Could not decode the QR code in the image")]
+    QrCodeDecode,
+
+    #[error("Could not create the QR code: {}", _0)]
+    QrCodeEncode(#[from] QrError),
+
+    #[error("Unsupported image format '{}', use a .png or .svg file", _0)]
+    UnsupportedImageFormat(String),
 }
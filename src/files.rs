@@ -0,0 +1,251 @@
+use crate::errors::{Error, Result};
+use fs2::FileExt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Interval between attempts to take a lock held by another process
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+// Advisory lock, shared by processes that only read the file or held by a
+// single process that changes it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+// Lock on a file, released when dropped. It is held on a separate
+// `<file>.lock` file since saving replaces the file itself.
+pub struct FileLock {
+    _file: File,
+}
+
+// Lock the file, waiting up to `timeout` for other processes to release it
+pub fn lock(path: &Path, mode: LockMode, timeout: Duration) -> Result<FileLock> {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".lock");
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options.open(path.with_file_name(file_name))?;
+
+    let start = Instant::now();
+    loop {
+        // Called through the trait, newer versions of std have methods of the same name
+        let result = match mode {
+            LockMode::Shared => FileExt::try_lock_shared(&file),
+            LockMode::Exclusive => FileExt::try_lock_exclusive(&file),
+        };
+        match result {
+            Ok(_) => return Ok(FileLock { _file: file }),
+            Err(err) if err.kind() == fs2::lock_contended_error().kind() => {
+                if start.elapsed() >= timeout {
+                    return Err(Error::Locked(timeout.as_secs()));
+                }
+                thread::sleep(LOCK_RETRY_INTERVAL);
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+// Path of a previous version of a file, 1 being the latest, e.g. `accounts.1`
+pub fn previous_path(path: &Path, version: usize) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".{}", version));
+    path.with_file_name(file_name)
+}
+
+// Replace the contents of a file without ever leaving it truncated: the data is
+// written to a temporary file in the same directory, flushed to the disk and
+// renamed over the file. The current contents are kept as the latest of
+// `keep` previous versions, older versions being shifted out.
+pub fn write_atomic(path: &Path, data: &[u8], keep: usize) -> Result<()> {
+    replace(path, data, keep, Some(path))
+}
+
+// Write a file holding secrets, e.g. an export, only readable by the user even
+// if it already exists with wider permissions
+pub fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    replace(path, data, 0, None)
+}
+
+fn replace(path: &Path, data: &[u8], keep: usize, original: Option<&Path>) -> Result<()> {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".tmp");
+    let temp_path = path.with_file_name(file_name);
+
+    let result = write_synced(&temp_path, data, original).and_then(|_| {
+        if keep > 0 && fs::metadata(path).map(|m| m.len() > 0).unwrap_or(false) {
+            rotate(path, keep)?;
+        }
+        fs::rename(&temp_path, path)?;
+        sync_dir(path)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+// Shift the previous versions and copy the file as the latest one
+fn rotate(path: &Path, keep: usize) -> Result<()> {
+    for version in (1..keep).rev() {
+        let previous = previous_path(path, version);
+        if previous.exists() {
+            fs::rename(&previous, previous_path(path, version + 1))?;
+        }
+    }
+    fs::copy(path, previous_path(path, 1))?;
+    Ok(())
+}
+
+// Write the data to a new file with the permissions of `original`, or only
+// readable by the user if there is none or it doesn't exist
+fn write_synced(path: &Path, data: &[u8], original: Option<&Path>) -> Result<()> {
+    // Permissions are only set on new files, remove any leftover of a failed save
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+        _ => {}
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        let mode = original
+            .and_then(|original| fs::metadata(original).ok())
+            .map(|metadata| metadata.permissions().mode() & 0o777)
+            .unwrap_or(0o600);
+        options.mode(mode);
+    }
+    #[cfg(not(unix))]
+    let _ = original;
+    let mut file = options.open(path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+// Flush the rename to the disk, renames are only durable once the directory is
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lock, previous_path, write_atomic, write_private, LockMode};
+    use crate::errors::Error;
+    use assert_fs::TempDir;
+    use std::fs;
+    use std::path::Path;
+    use std::time::Duration;
+
+    #[test]
+    fn test_previous_path() {
+        assert_eq!(
+            previous_path(Path::new("/home/evans/.cloak/accounts"), 2),
+            Path::new("/home/evans/.cloak/accounts.2")
+        );
+    }
+
+    #[test]
+    fn test_write_atomic_rotates_previous_versions() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("accounts");
+        for contents in ["", "first", "second", "third", "fourth"] {
+            write_atomic(&path, contents.as_bytes(), 2).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth");
+        assert_eq!(
+            fs::read_to_string(previous_path(&path, 1)).unwrap(),
+            "third"
+        );
+        assert_eq!(
+            fs::read_to_string(previous_path(&path, 2)).unwrap(),
+            "second"
+        );
+        assert!(!previous_path(&path, 3).exists());
+        assert!(!temp_dir.path().join("accounts.tmp").exists());
+
+        // Saves that don't keep the previous version leave the others alone
+        write_atomic(&path, b"fifth", 0).unwrap();
+        assert_eq!(
+            fs::read_to_string(previous_path(&path, 1)).unwrap(),
+            "third"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("export.txt");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        write_private(&path, b"otpauth://").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "otpauth://");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_lock() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("accounts");
+        let timeout = Duration::from_millis(100);
+
+        let shared = lock(&path, LockMode::Shared, timeout).unwrap();
+        assert!(lock(&path, LockMode::Shared, timeout).is_ok());
+        assert!(matches!(
+            lock(&path, LockMode::Exclusive, timeout),
+            Err(Error::Locked(_))
+        ));
+        drop(shared);
+
+        let _exclusive = lock(&path, LockMode::Exclusive, timeout).unwrap();
+        assert!(matches!(
+            lock(&path, LockMode::Shared, timeout),
+            Err(Error::Locked(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("accounts");
+        write_atomic(&path, b"first", 1).unwrap();
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        write_atomic(&path, b"second", 1).unwrap();
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+    }
+}
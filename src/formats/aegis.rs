@@ -0,0 +1,554 @@
+use crate::account::Account;
+use crate::crypto;
+use crate::errors::{Error, Result};
+use crate::formats::{self, entry_name, EntryFields, ImportOptions, Importer};
+use crate::otpauth::OtpAuth;
+use data_encoding::{BASE64, HEXLOWER, HEXLOWER_PERMISSIVE};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+
+const FORMAT: &str = "Aegis";
+const VAULT_VERSION: u32 = 1;
+const DATABASE_VERSION: u32 = 3;
+// Key slots unlocked with a password, other slots use biometrics
+const PASSWORD_SLOT: u8 = 1;
+// scrypt parameters used by Aegis (N = 2^15, r = 8, p = 1)
+const SCRYPT_N: u64 = 1 << 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const SALT_LEN: usize = 32;
+const MASTER_KEY_LEN: usize = 32;
+// Hash functions Aegis generates codes with, besides MD5 for mOTP
+const HASH_FUNCTIONS: [&str; 3] = ["SHA1", "SHA256", "SHA512"];
+
+// [Aegis vault](https://github.com/beemdevelopment/Aegis/blob/master/docs/vault.md)
+// exported by the Aegis Android app. The database is either a JSON object, or
+// a base64 string when the vault is encrypted.
+#[derive(Deserialize, Serialize)]
+struct Vault {
+    version: u32,
+    header: Header,
+    db: Value,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct Header {
+    slots: Option<Vec<Slot>>,
+    params: Option<KeyParams>,
+}
+
+// Master key encrypted with a key derived from the password
+#[derive(Deserialize, Serialize)]
+struct Slot {
+    #[serde(rename = "type")]
+    slot_type: u8,
+    uuid: String,
+    key: String,
+    key_params: KeyParams,
+    n: Option<u64>,
+    r: Option<u32>,
+    p: Option<u32>,
+    salt: Option<String>,
+}
+
+// AES-GCM nonce and tag, hex encoded
+#[derive(Deserialize, Serialize)]
+struct KeyParams {
+    nonce: String,
+    tag: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Database {
+    version: u32,
+    entries: Vec<Entry>,
+    #[serde(default)]
+    groups: Vec<Group>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Group {
+    uuid: String,
+    name: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Entry {
+    #[serde(rename = "type")]
+    entry_type: String,
+    uuid: String,
+    name: String,
+    issuer: String,
+    #[serde(default)]
+    note: String,
+    #[serde(default)]
+    favorite: bool,
+    #[serde(default)]
+    icon: Option<String>,
+    info: Value,
+    // Databases before version 3 hold the name of a single group
+    #[serde(default, skip_serializing)]
+    group: Option<String>,
+    #[serde(default)]
+    groups: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct OtpInfo {
+    secret: String,
+    algo: String,
+    digits: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    period: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    counter: Option<u64>,
+}
+
+// Importer of Aegis vaults, asking for the password of encrypted ones
+pub struct Aegis;
+
+impl Importer for Aegis {
+    fn name(&self) -> &'static str {
+        "aegis"
+    }
+
+    fn read(&self, inputs: &[&String], _options: &ImportOptions) -> Result<Vec<formats::Entry>> {
+        let mut entries = Vec::new();
+        for input in inputs {
+            let json = fs::read_to_string(input)?;
+            entries.extend(read(&json, crypto::read_backup_passphrase)?);
+        }
+        Ok(entries)
+    }
+}
+
+// Read the accounts of an Aegis vault. Entries that can't be imported, e.g. of
+// an unsupported type, are returned as errors so that the others can still be
+// imported. The password is only read if the vault is encrypted.
+pub fn read<F>(json: &str, read_password: F) -> Result<Vec<formats::Entry>>
+where
+    F: FnOnce() -> Result<String>,
+{
+    let vault: Vault = serde_json::from_str(json)?;
+    if vault.version != VAULT_VERSION {
+        return Err(invalid(&format!("unsupported version {}", vault.version)));
+    }
+    let database: Database = match vault.db {
+        Value::String(db) => {
+            let plaintext = decrypt_database(&vault.header, &db, &read_password()?)?;
+            serde_json::from_slice(&plaintext)?
+        }
+        db => serde_json::from_value(db)?,
+    };
+
+    let groups: BTreeMap<&str, &str> = database
+        .groups
+        .iter()
+        .map(|group| (group.uuid.as_str(), group.name.as_str()))
+        .collect();
+    Ok(database
+        .entries
+        .iter()
+        .map(|entry| entry_to_account(entry, &groups))
+        .collect())
+}
+
+// Write the accounts as an Aegis vault, encrypted if a password is given
+pub fn write(accounts: &[(&String, &Account)], password: Option<&str>) -> Result<String> {
+    let mut groups: Vec<Group> = Vec::new();
+    let mut entries = Vec::with_capacity(accounts.len());
+    for (name, account) in accounts {
+        let mut entry_groups = Vec::new();
+        for tag in account.tags.iter() {
+            let uuid = match groups.iter().find(|group| &group.name == tag) {
+                Some(group) => group.uuid.clone(),
+                None => {
+                    let uuid = new_uuid()?;
+                    groups.push(Group {
+                        uuid: uuid.clone(),
+                        name: tag.clone(),
+                    });
+                    uuid
+                }
+            };
+            entry_groups.push(uuid);
+        }
+
+        // Refused rather than giving different codes once imported
+        if !HASH_FUNCTIONS.contains(&account.hash_function.as_str()) {
+            return Err(Error::UnsupportedHashFunction(
+                name.to_string(),
+                account.hash_function.clone(),
+                FORMAT,
+            ));
+        }
+        let otpauth = OtpAuth::from_account(name, account)?;
+        let entry_type = if account.steam {
+            "steam"
+        } else if account.totp {
+            "totp"
+        } else {
+            "hotp"
+        };
+        let info = OtpInfo {
+            secret: otpauth.secret,
+            algo: otpauth.algorithm,
+            digits: otpauth.digits,
+            period: otpauth.period,
+            counter: otpauth.counter,
+        };
+        entries.push(Entry {
+            entry_type: entry_type.to_string(),
+            uuid: new_uuid()?,
+            name: otpauth.label,
+            issuer: otpauth.issuer.unwrap_or_default(),
+            note: account.notes.clone().unwrap_or_default(),
+            favorite: false,
+            icon: None,
+            info: serde_json::to_value(info)?,
+            group: None,
+            groups: entry_groups,
+        });
+    }
+
+    let database = serde_json::to_value(Database {
+        version: DATABASE_VERSION,
+        entries,
+        groups,
+    })?;
+    let vault = match password {
+        Some(password) => encrypt_database(&database, password)?,
+        None => Vault {
+            version: VAULT_VERSION,
+            header: Header::default(),
+            db: database,
+        },
+    };
+    Ok(serde_json::to_string_pretty(&vault)?)
+}
+
+fn entry_to_account(entry: &Entry, groups: &BTreeMap<&str, &str>) -> formats::Entry {
+    let mut tags: Vec<String> = entry
+        .groups
+        .iter()
+        .filter_map(|uuid| groups.get(uuid.as_str()))
+        .map(|group| group.to_string())
+        .collect();
+    tags.extend(entry.group.clone());
+
+    let mut fields = EntryFields {
+        issuer: entry.issuer.clone(),
+        name: entry.name.clone(),
+        otp_type: entry.entry_type.clone(),
+        secret: String::new(),
+        algorithm: String::new(),
+        digits: 0,
+        period: None,
+        counter: None,
+        tags,
+    };
+    // The info of unsupported types, e.g. mOTP, may not match the one of TOTP
+    if matches!(entry.entry_type.as_str(), "totp" | "hotp" | "steam") {
+        let info: OtpInfo = serde_json::from_value(entry.info.clone()).map_err(|err| {
+            Error::InvalidEntry(entry_name(&entry.issuer, &entry.name), err.to_string())
+        })?;
+        fields.secret = info.secret;
+        fields.algorithm = info.algo;
+        fields.digits = info.digits;
+        fields.period = info.period;
+        fields.counter = info.counter;
+    }
+    let mut account = fields.into_account()?;
+    account.1.notes = Some(entry.note.clone()).filter(|note| !note.is_empty());
+    Ok(account)
+}
+
+// Unlock the master key with the password slots, then decrypt the database
+fn decrypt_database(header: &Header, db: &str, password: &str) -> Result<Vec<u8>> {
+    let slots = header
+        .slots
+        .as_ref()
+        .ok_or_else(|| invalid("missing key slots"))?;
+    let params = header
+        .params
+        .as_ref()
+        .ok_or_else(|| invalid("missing database parameters"))?;
+
+    let mut master_key = None;
+    for slot in slots.iter().filter(|slot| slot.slot_type == PASSWORD_SLOT) {
+        let (n, r, p, salt) = match (slot.n, slot.r, slot.p, &slot.salt) {
+            (Some(n), Some(r), Some(p), Some(salt)) => (n, r, p, decode_hex(salt)?),
+            _ => return Err(invalid("missing key derivation parameters")),
+        };
+        if !n.is_power_of_two() || n < 2 {
+            return Err(invalid("invalid key derivation parameters"));
+        }
+        let key = crypto::scrypt_key(password, &salt, n.trailing_zeros() as u8, r, p)?;
+        let ciphertext = [decode_hex(&slot.key)?, decode_hex(&slot.key_params.tag)?].concat();
+        master_key = crypto::aes_gcm_open(&key, &decode_hex(&slot.key_params.nonce)?, &ciphertext);
+        if master_key.is_some() {
+            break;
+        }
+    }
+    let master_key = master_key.ok_or(Error::BackupDecrypt(FORMAT))?;
+
+    let db = BASE64
+        .decode(db.as_bytes())
+        .map_err(|_| invalid("the database is not valid base64"))?;
+    let ciphertext = [db, decode_hex(&params.tag)?].concat();
+    crypto::aes_gcm_open(&master_key, &decode_hex(&params.nonce)?, &ciphertext)
+        .ok_or(Error::BackupDecrypt(FORMAT))
+}
+
+// Encrypt the database with a random master key, stored in a single password slot
+fn encrypt_database(database: &Value, password: &str) -> Result<Vault> {
+    let master_key = crypto::random_bytes(MASTER_KEY_LEN)?;
+    let salt = crypto::random_bytes(SALT_LEN)?;
+    let key = crypto::scrypt_key(
+        password,
+        &salt,
+        SCRYPT_N.trailing_zeros() as u8,
+        SCRYPT_R,
+        SCRYPT_P,
+    )?;
+    let (nonce, encrypted_key) = crypto::aes_gcm_seal(&key, &master_key)?;
+    let (key, tag) = encrypted_key.split_at(MASTER_KEY_LEN);
+    let slot = Slot {
+        slot_type: PASSWORD_SLOT,
+        uuid: new_uuid()?,
+        key: HEXLOWER.encode(key),
+        key_params: KeyParams {
+            nonce: HEXLOWER.encode(&nonce),
+            tag: HEXLOWER.encode(tag),
+        },
+        n: Some(SCRYPT_N),
+        r: Some(SCRYPT_R),
+        p: Some(SCRYPT_P),
+        salt: Some(HEXLOWER.encode(&salt)),
+    };
+
+    let plaintext = serde_json::to_vec(database)?;
+    let (nonce, ciphertext) = crypto::aes_gcm_seal(&master_key, &plaintext)?;
+    let (db, tag) = ciphertext.split_at(plaintext.len());
+    Ok(Vault {
+        version: VAULT_VERSION,
+        header: Header {
+            slots: Some(vec![slot]),
+            params: Some(KeyParams {
+                nonce: HEXLOWER.encode(&nonce),
+                tag: HEXLOWER.encode(tag),
+            }),
+        },
+        db: Value::String(BASE64.encode(db)),
+    })
+}
+
+// Random (version 4) UUID
+fn new_uuid() -> Result<String> {
+    let mut bytes = crypto::random_bytes(16)?;
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = HEXLOWER.encode(&bytes);
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>> {
+    HEXLOWER_PERMISSIVE
+        .decode(value.as_bytes())
+        .map_err(|_| invalid("invalid hex encoding"))
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidBackup(FORMAT, reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, write};
+    use crate::account::Account;
+    use crate::errors::Error;
+
+    const VAULT: &str = r#"{
+        "version": 1,
+        "header": {"slots": null, "params": null},
+        "db": {
+            "version": 3,
+            "entries": [
+                {
+                    "type": "totp",
+                    "uuid": "3ae6f1ad-2e65-4ed2-a953-1ec0dff2386d",
+                    "name": "Mason",
+                    "issuer": "Deno",
+                    "note": "Recovery codes in the safe",
+                    "favorite": false,
+                    "icon": null,
+                    "info": {"secret": "4SJHB4GSD43FZBAI7C2HLRJGPQ", "algo": "SHA1", "digits": 6, "period": 30},
+                    "groups": ["e3a0c8ab-0c8b-4a2a-8a5e-8f4bd3b0a6b1"]
+                },
+                {
+                    "type": "hotp",
+                    "uuid": "7a7b4d5c-2c5e-4b2b-9a1e-2d9f1c0b8e7a",
+                    "name": "James",
+                    "issuer": "SPDX",
+                    "info": {"secret": "5OM4WOOGPLQEF6UGN3CPEOOLWU", "algo": "SHA256", "digits": 7, "counter": 10},
+                    "groups": []
+                },
+                {
+                    "type": "steam",
+                    "uuid": "5b11ae3b-6fc3-4d46-8ca7-cf0aea7de920",
+                    "name": "Sophia",
+                    "issuer": "Boeing",
+                    "info": {"secret": "JRZCL47CMXVOQMNPZR2F7J4RGI", "algo": "SHA1", "digits": 5, "period": 30}
+                },
+                {
+                    "type": "motp",
+                    "uuid": "1c3a0e6f-9b2d-4f7a-8e5c-6d4b2a1f0e9d",
+                    "name": "Benjamin",
+                    "issuer": "Air Canada",
+                    "info": {"secret": "e3152afee62599c8", "algo": "MD5", "digits": 6, "period": 10, "pin": "1234"}
+                }
+            ],
+            "groups": [{"uuid": "e3a0c8ab-0c8b-4a2a-8a5e-8f4bd3b0a6b1", "name": "Work"}]
+        }
+    }"#;
+
+    fn no_password() -> crate::errors::Result<String> {
+        panic!("the password should not be read for a plain vault")
+    }
+
+    #[test]
+    fn test_read_plain_vault() {
+        let entries = read(VAULT, no_password).unwrap();
+        assert_eq!(entries.len(), 4);
+
+        let (name, account) = entries[0].as_ref().unwrap();
+        assert_eq!(name, "Deno:Mason");
+        assert_eq!(account.key, "4SJHB4GSD43FZBAI7C2HLRJGPQ");
+        assert!(account.totp);
+        assert_eq!(account.period, Some(30));
+        assert_eq!(account.tags, vec![String::from("Work")]);
+        assert_eq!(account.notes.as_deref(), Some("Recovery codes in the safe"));
+
+        let (name, account) = entries[1].as_ref().unwrap();
+        assert_eq!(name, "SPDX:James");
+        assert_eq!(account.notes, None);
+        assert!(!account.totp);
+        assert_eq!(account.hash_function, "SHA256");
+        assert_eq!((account.digits, account.counter), (Some(7), Some(10)));
+
+        let (name, account) = entries[2].as_ref().unwrap();
+        assert_eq!(name, "Boeing:Sophia");
+        assert!(account.steam);
+        assert_eq!(account.digits, Some(5));
+
+        match &entries[3] {
+            Err(Error::UnsupportedEntry(name, reason)) => {
+                assert_eq!(name, "Air Canada:Benjamin");
+                assert_eq!(reason, "unsupported type 'motp'");
+            }
+            _ => panic!("mOTP entries should be skipped"),
+        }
+    }
+
+    #[test]
+    fn test_write_read_roundtrip() {
+        let github = String::from("GitHub:evans");
+        let account = Account {
+            key: String::from("4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6"),
+            totp: false,
+            hash_function: String::from("SHA512"),
+            counter: Some(3),
+            digits: Some(8),
+            period: None,
+            t0: None,
+            steam: false,
+            tags: vec![String::from("Work"), String::from("Code")],
+            issuer: None,
+            label: None,
+            notes: Some(String::from("Recovery codes in the safe")),
+            created_at: None,
+            last_used_at: None,
+            external_key: None,
+        };
+        let accounts = vec![(&github, &account)];
+
+        let json = write(&accounts, None).unwrap();
+        let entries = read(&json, no_password).unwrap();
+        let (name, imported) = entries[0].as_ref().unwrap();
+        assert_eq!(name, &github);
+        assert_eq!(imported.key, account.key);
+        assert_eq!(imported.hash_function, "SHA512");
+        assert_eq!((imported.digits, imported.counter), (Some(8), Some(3)));
+        assert_eq!(imported.tags, account.tags);
+        assert_eq!(imported.notes, account.notes);
+    }
+
+    #[test]
+    fn test_write_unsupported_hash_function() {
+        let github = String::from("GitHub:evans");
+        let account = Account {
+            key: String::from("4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6"),
+            totp: true,
+            hash_function: String::from("SHA384"),
+            counter: None,
+            digits: None,
+            period: None,
+            t0: None,
+            steam: false,
+            tags: Vec::new(),
+            issuer: None,
+            label: None,
+            notes: None,
+            created_at: None,
+            last_used_at: None,
+            external_key: None,
+        };
+        assert!(matches!(
+            write(&[(&github, &account)], None),
+            Err(Error::UnsupportedHashFunction(name, hash_function, "Aegis"))
+                if name == github && hash_function == "SHA384"
+        ));
+    }
+
+    #[test]
+    fn test_encrypted_roundtrip() {
+        let name = String::from("Steam:gaben");
+        let account = Account {
+            key: String::from("JBSWY3DPEHPK3PXP"),
+            totp: true,
+            hash_function: String::from("SHA1"),
+            counter: None,
+            digits: Some(5),
+            period: Some(30),
+            t0: None,
+            steam: true,
+            tags: Vec::new(),
+            issuer: None,
+            label: None,
+            notes: None,
+            created_at: None,
+            last_used_at: None,
+            external_key: None,
+        };
+        let json = write(&[(&name, &account)], Some("hunter2")).unwrap();
+        assert!(!json.contains("JBSWY3DPEHPK3PXP"));
+
+        let entries = read(&json, || Ok(String::from("hunter2"))).unwrap();
+        let (imported_name, imported) = entries[0].as_ref().unwrap();
+        assert_eq!(imported_name, &name);
+        assert!(imported.steam);
+
+        assert!(matches!(
+            read(&json, || Ok(String::from("hunter3"))),
+            Err(Error::BackupDecrypt(_))
+        ));
+    }
+}
@@ -0,0 +1,175 @@
+use crate::crypto;
+use crate::errors::{Error, Result};
+use crate::formats::{Entry, EntryFields, ImportOptions, Importer};
+use crate::otp::HashFunction;
+use std::convert::TryInto;
+use std::fs;
+
+const FORMAT: &str = "andOTP";
+// Encrypted backups start with the number of PBKDF2 iterations, the salt and
+// the AES-GCM nonce, followed by the ciphertext and its tag
+const ITERATIONS_LEN: usize = 4;
+const SALT_LEN: usize = 12;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = ITERATIONS_LEN + SALT_LEN + NONCE_LEN;
+// Highest number of PBKDF2 iterations accepted from the header, which isn't
+// authenticated until the key is derived. andOTP uses about 150000.
+const MAX_ITERATIONS: u32 = 1_000_000;
+
+// Importer of andOTP backups, either plain JSON or encrypted with a password
+pub struct AndOtp;
+
+impl Importer for AndOtp {
+    fn name(&self) -> &'static str {
+        "andotp"
+    }
+
+    fn read(&self, inputs: &[&String], _options: &ImportOptions) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        for input in inputs {
+            let data = fs::read(input)?;
+            entries.extend(read(&data, crypto::read_backup_passphrase)?);
+        }
+        Ok(entries)
+    }
+}
+
+#[derive(Deserialize)]
+struct AndOtpEntry {
+    secret: String,
+    #[serde(default)]
+    issuer: String,
+    label: String,
+    digits: usize,
+    #[serde(rename = "type")]
+    entry_type: String,
+    algorithm: String,
+    period: Option<u64>,
+    counter: Option<u64>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+// Read the accounts of an andOTP backup. The password is only read if the
+// backup is encrypted, i.e. is not a JSON array.
+pub fn read<F>(data: &[u8], read_password: F) -> Result<Vec<Entry>>
+where
+    F: FnOnce() -> Result<String>,
+{
+    let entries: Vec<AndOtpEntry> = if is_plain(data) {
+        serde_json::from_slice(data)?
+    } else {
+        serde_json::from_slice(&decrypt(data, &read_password()?)?)?
+    };
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            EntryFields {
+                issuer: entry.issuer,
+                name: entry.label,
+                otp_type: entry.entry_type,
+                secret: entry.secret,
+                algorithm: entry.algorithm,
+                digits: entry.digits,
+                period: entry.period,
+                counter: entry.counter,
+                tags: entry.tags,
+            }
+            .into_account()
+        })
+        .collect())
+}
+
+fn is_plain(data: &[u8]) -> bool {
+    data.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'[')
+}
+
+fn decrypt(data: &[u8], password: &str) -> Result<Vec<u8>> {
+    if data.len() < HEADER_LEN {
+        return Err(Error::InvalidBackup(FORMAT, String::from("file too short")));
+    }
+    let iterations = u32::from_be_bytes(data[..ITERATIONS_LEN].try_into().unwrap());
+    if iterations > MAX_ITERATIONS {
+        return Err(Error::InvalidBackup(
+            FORMAT,
+            String::from("too many key derivation iterations"),
+        ));
+    }
+    let salt = &data[ITERATIONS_LEN..ITERATIONS_LEN + SALT_LEN];
+    let nonce = &data[ITERATIONS_LEN + SALT_LEN..HEADER_LEN];
+    let key = crypto::pbkdf2_key(&HashFunction::Sha1, password, salt, iterations)?;
+    crypto::aes_gcm_open(&key, nonce, &data[HEADER_LEN..]).ok_or(Error::BackupDecrypt(FORMAT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read;
+    use crate::crypto;
+    use crate::errors::Error;
+    use crate::otp::HashFunction;
+
+    const BACKUP: &str = r#"[
+        {"secret": "4SJHB4GSD43FZBAI7C2HLRJGPQ", "issuer": "Deno", "label": "Mason", "digits": 6,
+         "type": "TOTP", "algorithm": "SHA1", "thumbnail": "Default", "last_used": 0,
+         "used_frequency": 0, "period": 30, "tags": ["Work"]},
+        {"secret": "5OM4WOOGPLQEF6UGN3CPEOOLWU", "issuer": "SPDX", "label": "James", "digits": 7,
+         "type": "HOTP", "algorithm": "SHA256", "thumbnail": "Default", "last_used": 0,
+         "used_frequency": 0, "counter": 10, "tags": []},
+        {"secret": "JRZCL47CMXVOQMNPZR2F7J4RGI", "issuer": "Steam", "label": "gaben", "digits": 5,
+         "type": "STEAM", "algorithm": "SHA1", "period": 30, "tags": []},
+        {"secret": "e3152afee62599c8", "issuer": "", "label": "Benjamin", "digits": 6,
+         "type": "MOTP", "algorithm": "MD5", "period": 10, "pin": "1234", "tags": []}
+    ]"#;
+
+    fn no_password() -> crate::errors::Result<String> {
+        panic!("the password should not be read for a plain backup")
+    }
+
+    #[test]
+    fn test_read_plain_backup() {
+        let entries = read(BACKUP.as_bytes(), no_password).unwrap();
+        assert_eq!(entries.len(), 4);
+
+        let (name, account) = entries[0].as_ref().unwrap();
+        assert_eq!(name, "Deno:Mason");
+        assert!(account.totp);
+        assert_eq!(account.tags, vec![String::from("Work")]);
+
+        let (name, account) = entries[1].as_ref().unwrap();
+        assert_eq!(name, "SPDX:James");
+        assert_eq!(account.hash_function, "SHA256");
+        assert_eq!((account.digits, account.counter), (Some(7), Some(10)));
+
+        let (name, account) = entries[2].as_ref().unwrap();
+        assert_eq!(name, "Steam:gaben");
+        assert!(account.steam);
+
+        assert!(matches!(&entries[3], Err(Error::UnsupportedEntry(name, _)) if name == "Benjamin"));
+    }
+
+    #[test]
+    fn test_read_encrypted_backup() {
+        let iterations: u32 = 1000;
+        let salt = crypto::random_bytes(12).unwrap();
+        let key = crypto::pbkdf2_key(&HashFunction::Sha1, "hunter2", &salt, iterations).unwrap();
+        let (nonce, ciphertext) = crypto::aes_gcm_seal(&key, BACKUP.as_bytes()).unwrap();
+        let data = [&iterations.to_be_bytes()[..], &salt, &nonce, &ciphertext].concat();
+
+        let entries = read(&data, || Ok(String::from("hunter2"))).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert!(matches!(
+            read(&data, || Ok(String::from("hunter3"))),
+            Err(Error::BackupDecrypt(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_backup_with_too_many_iterations() {
+        let iterations: u32 = 100_000_000;
+        let data = [&iterations.to_be_bytes()[..], &[0_u8; 56]].concat();
+        assert!(matches!(
+            read(&data, || Ok(String::from("hunter2"))),
+            Err(Error::InvalidBackup("andOTP", _))
+        ));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::errors::{Error, Result};
+use crate::formats::{self, entry_name, totp_entry, Entry, ImportOptions, Importer};
+use std::collections::BTreeMap;
+use std::fs;
+
+const FORMAT: &str = "Bitwarden";
+// Item type of logins, the only items with a TOTP field
+const LOGIN_TYPE: u8 = 1;
+
+// Importer of the TOTP fields of Bitwarden's unencrypted JSON or CSV exports
+pub struct Bitwarden;
+
+impl Importer for Bitwarden {
+    fn name(&self) -> &'static str {
+        "bitwarden"
+    }
+
+    fn read(&self, inputs: &[&String], _options: &ImportOptions) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        for input in inputs {
+            entries.extend(read(&fs::read_to_string(input)?)?);
+        }
+        Ok(entries)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Export {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    folders: Vec<Folder>,
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Folder {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    #[serde(rename = "type")]
+    item_type: u8,
+    name: String,
+    folder_id: Option<String>,
+    login: Option<Login>,
+}
+
+#[derive(Deserialize)]
+struct Login {
+    username: Option<String>,
+    totp: Option<String>,
+}
+
+// Read the login items with a TOTP field of a Bitwarden JSON or CSV export
+pub fn read(content: &str) -> Result<Vec<Entry>> {
+    if !content.trim_start().starts_with('{') {
+        return formats::read_csv(content);
+    }
+    let export: Export = serde_json::from_str(content)?;
+    if export.encrypted {
+        let reason =
+            String::from("encrypted exports are not supported, export as unencrypted JSON");
+        return Err(Error::InvalidBackup(FORMAT, reason));
+    }
+
+    let folders: BTreeMap<&str, &str> = export
+        .folders
+        .iter()
+        .map(|folder| (folder.id.as_str(), folder.name.as_str()))
+        .collect();
+    Ok(export
+        .items
+        .iter()
+        .filter(|item| item.item_type == LOGIN_TYPE)
+        .filter_map(|item| {
+            let login = item.login.as_ref()?;
+            let totp = login
+                .totp
+                .as_deref()
+                .filter(|totp| !totp.trim().is_empty())?;
+            let username = login.username.as_deref().unwrap_or_default();
+            let tags = item
+                .folder_id
+                .as_deref()
+                .and_then(|id| folders.get(id))
+                .map(|folder| vec![folder.to_string()])
+                .unwrap_or_default();
+            Some(totp_entry(entry_name(&item.name, username), totp, tags))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read;
+    use crate::errors::Error;
+
+    #[test]
+    fn test_read_json_export() {
+        let json = r#"{
+            "encrypted": false,
+            "folders": [{"id": "4e8a4b2c", "name": "Work"}],
+            "items": [
+                {"id": "1", "folderId": "4e8a4b2c", "type": 1, "name": "GitHub", "favorite": false,
+                 "login": {"username": "evans", "password": "x", "totp": "JBSW Y3DP EHPK 3PXP"}},
+                {"id": "2", "folderId": null, "type": 1, "name": "Steam",
+                 "login": {"username": "gaben", "totp": "steam://JRZCL47CMXVOQMNPZR2F7J4RGI"}},
+                {"id": "3", "folderId": null, "type": 1, "name": "GitLab",
+                 "login": {"username": null,
+                           "totp": "otpauth://totp/GitLab:james?secret=5OM4WOOGPLQEF6UGN3CPEOOLWU&digits=8"}},
+                {"id": "4", "type": 1, "name": "email", "login": {"username": "evans", "totp": null}},
+                {"id": "5", "type": 2, "name": "note", "secureNote": {"type": 0}}
+            ]
+        }"#;
+        let entries = read(json).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        let (name, account) = entries[0].as_ref().unwrap();
+        assert_eq!(name, "GitHub:evans");
+        assert_eq!(account.key, "JBSWY3DPEHPK3PXP");
+        assert_eq!(account.tags, vec![String::from("Work")]);
+
+        let (name, account) = entries[1].as_ref().unwrap();
+        assert_eq!(name, "Steam:gaben");
+        assert!(account.steam);
+
+        let (name, account) = entries[2].as_ref().unwrap();
+        assert_eq!(name, "GitLab");
+        assert_eq!(account.digits, Some(8));
+    }
+
+    #[test]
+    fn test_read_csv_export() {
+        let csv = "folder,favorite,type,name,notes,fields,reprompt,login_uri,login_username,login_password,login_totp\n\
+                   Work,,login,GitHub,,,0,https://github.com,evans,x,JBSWY3DPEHPK3PXP\n\
+                   ,,login,email,,,0,,evans,x,\n\
+                   ,,login,broken,,,0,,evans,x,not-base32!\n";
+        let entries = read(csv).unwrap();
+        assert_eq!(entries.len(), 2);
+        let (name, account) = entries[0].as_ref().unwrap();
+        assert_eq!(name, "GitHub:evans");
+        assert_eq!(account.tags, vec![String::from("Work")]);
+        assert!(matches!(entries[1], Err(Error::InvalidEntry(_, _))));
+    }
+
+    #[test]
+    fn test_read_encrypted_export() {
+        let json = r#"{"encrypted": true, "passwordProtected": true, "data": "..."}"#;
+        assert!(matches!(read(json), Err(Error::InvalidBackup(_, _))));
+    }
+}
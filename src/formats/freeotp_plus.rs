@@ -0,0 +1,115 @@
+use crate::errors::Result;
+use crate::formats::{Entry, EntryFields, ImportOptions, Importer};
+use crate::otpauth::OtpAuth;
+use data_encoding::BASE32_NOPAD;
+use std::fs;
+
+// Importer of FreeOTP+ backups, either the JSON export or the list of
+// otpauth:// URIs, one per line
+pub struct FreeOtpPlus;
+
+impl Importer for FreeOtpPlus {
+    fn name(&self) -> &'static str {
+        "freeotp-plus"
+    }
+
+    fn read(&self, inputs: &[&String], _options: &ImportOptions) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        for input in inputs {
+            entries.extend(read(&fs::read_to_string(input)?)?);
+        }
+        Ok(entries)
+    }
+}
+
+#[derive(Deserialize)]
+struct Backup {
+    tokens: Vec<Token>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Token {
+    // Raw secret, as the signed bytes of Java
+    secret: Vec<i8>,
+    #[serde(default)]
+    issuer_ext: String,
+    label: String,
+    algo: String,
+    digits: usize,
+    #[serde(rename = "type")]
+    token_type: String,
+    period: Option<u64>,
+    counter: Option<u64>,
+}
+
+// Read the accounts of a FreeOTP+ JSON export or URI list
+pub fn read(content: &str) -> Result<Vec<Entry>> {
+    if content.trim_start().starts_with('{') {
+        let backup: Backup = serde_json::from_str(content)?;
+        return Ok(backup.tokens.into_iter().map(token_to_account).collect());
+    }
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|uri| {
+            let otpauth: OtpAuth = uri.parse()?;
+            Ok((otpauth.account_name(), otpauth.into_account()))
+        })
+        .collect())
+}
+
+fn token_to_account(token: Token) -> Entry {
+    let secret: Vec<u8> = token.secret.iter().map(|&byte| byte as u8).collect();
+    EntryFields {
+        issuer: token.issuer_ext,
+        name: token.label,
+        otp_type: token.token_type,
+        secret: BASE32_NOPAD.encode(&secret),
+        algorithm: token.algo,
+        digits: token.digits,
+        period: token.period,
+        counter: token.counter,
+        tags: Vec::new(),
+    }
+    .into_account()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read;
+
+    #[test]
+    fn test_read_json_export() {
+        let json = r#"{
+            "tokenOrder": ["GitHub:evans", "counter"],
+            "tokens": [
+                {"algo": "SHA1", "counter": 0, "digits": 6, "issuerExt": "GitHub", "label": "evans",
+                 "period": 30, "secret": [72, 101, 108, 108, 111, 33, -34, -83, -66, -17], "type": "TOTP"},
+                {"algo": "SHA512", "counter": 4, "digits": 8, "issuerExt": "", "label": "counter",
+                 "period": 30, "secret": [72, 101, 108, 108, 111, 33, -34, -83, -66, -17], "type": "HOTP"}
+            ]
+        }"#;
+        let entries = read(json).unwrap();
+        let (name, account) = entries[0].as_ref().unwrap();
+        assert_eq!(name, "GitHub:evans");
+        assert_eq!(account.key, "JBSWY3DPEHPK3PXP");
+        assert!(account.totp);
+
+        let (name, account) = entries[1].as_ref().unwrap();
+        assert_eq!(name, "counter");
+        assert_eq!(account.hash_function, "SHA512");
+        assert_eq!((account.counter, account.period), (Some(4), None));
+    }
+
+    #[test]
+    fn test_read_uri_list() {
+        let uris = "otpauth://totp/GitHub:evans?secret=JBSWY3DPEHPK3PXP&issuer=GitHub\n\n\
+                    otpauth://hotp/counter?secret=JBSWY3DPEHPK3PXP&counter=3\n";
+        let entries = read(uris).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].as_ref().unwrap().0, "GitHub:evans");
+        assert_eq!(entries[1].as_ref().unwrap().1.counter, Some(3));
+    }
+}
@@ -0,0 +1,357 @@
+use crate::errors::{Error, Result};
+use crate::formats::{Entry, ImportOptions, Importer};
+use crate::otpauth::{join_label, OtpAuth};
+use crate::qr;
+use data_encoding::{BASE32_NOPAD, BASE64, BASE64_NOPAD};
+use percent_encoding::percent_decode_str;
+use std::path::Path;
+
+const SCHEME: &str = "otpauth-migration://offline?";
+
+// Batch of accounts exported by Google Authenticator in an
+// `otpauth-migration://offline?data=...` URI, holding a protobuf `MigrationPayload`
+#[derive(Debug, Default)]
+pub struct MigrationPayload {
+    // An account that can't be read doesn't prevent reading the others
+    pub accounts: Vec<Result<OtpAuth>>,
+    pub batch_size: u64,
+    pub batch_index: u64,
+    pub batch_id: u64,
+}
+
+impl MigrationPayload {
+    pub fn from_uri(uri: &str) -> Result<MigrationPayload> {
+        let query = uri
+            .strip_prefix(SCHEME)
+            .ok_or_else(|| invalid("the URI must start with otpauth-migration://offline"))?;
+        let data = query
+            .split('&')
+            .find_map(|param| param.strip_prefix("data="))
+            .ok_or_else(|| invalid("missing data"))?;
+        let data = percent_decode_str(data)
+            .decode_utf8()
+            .map_err(|_| invalid("data is not valid UTF-8"))?;
+        let data = BASE64
+            .decode(data.as_bytes())
+            .or_else(|_| BASE64_NOPAD.decode(data.trim_end_matches('=').as_bytes()))
+            .map_err(|_| invalid("data is not valid base64"))?;
+        MigrationPayload::decode(&data)
+    }
+
+    fn decode(data: &[u8]) -> Result<MigrationPayload> {
+        let mut payload = MigrationPayload {
+            batch_size: 1,
+            ..Default::default()
+        };
+        let mut reader = ProtoReader { data };
+        while let Some((tag, field)) = reader.read_field()? {
+            match (tag, field) {
+                (1, Field::Bytes(bytes)) => payload.accounts.push(decode_otp_parameters(bytes)),
+                (3, Field::Varint(value)) => payload.batch_size = value,
+                (4, Field::Varint(value)) => payload.batch_index = value,
+                (5, Field::Varint(value)) => payload.batch_id = value,
+                _ => (),
+            }
+        }
+        Ok(payload)
+    }
+}
+
+// Importer of Google Authenticator exports, given as `otpauth-migration://`
+// URIs or images of the QR codes. Large exports are split in several batches,
+// i.e. QR codes.
+pub struct GoogleMigration;
+
+impl Importer for GoogleMigration {
+    fn name(&self) -> &'static str {
+        "google-migration"
+    }
+
+    fn read(&self, inputs: &[&String], _options: &ImportOptions) -> Result<Vec<Entry>> {
+        let mut payloads: Vec<MigrationPayload> = Vec::new();
+        for input in inputs {
+            let uri = if input.starts_with("otpauth-migration:") {
+                input.to_string()
+            } else {
+                qr::decode_file(Path::new(input))?
+            };
+            let payload = MigrationPayload::from_uri(&uri)?;
+            // The same QR code may have been scanned twice
+            if !payloads
+                .iter()
+                .any(|p| p.batch_id == payload.batch_id && p.batch_index == payload.batch_index)
+            {
+                payloads.push(payload);
+            }
+        }
+        payloads.sort_by_key(|payload| (payload.batch_id, payload.batch_index));
+
+        let mut batches: Vec<(u64, u64)> = payloads
+            .iter()
+            .map(|payload| (payload.batch_id, payload.batch_size))
+            .collect();
+        batches.dedup();
+        for (batch_id, batch_size) in batches {
+            let missing: Vec<String> = (0..batch_size)
+                .filter(|&index| {
+                    !payloads
+                        .iter()
+                        .any(|p| p.batch_id == batch_id && p.batch_index == index)
+                })
+                .map(|index| (index + 1).to_string())
+                .collect();
+            if !missing.is_empty() {
+                eprintln!(
+                    "Missing QR code {} of {} of the export, the accounts it holds are not imported",
+                    missing.join(", "),
+                    batch_size
+                );
+            }
+        }
+
+        Ok(payloads
+            .into_iter()
+            .flat_map(|payload| payload.accounts)
+            .map(|otpauth| otpauth.map(|otpauth| (otpauth.account_name(), otpauth.into_account())))
+            .collect())
+    }
+}
+
+// Decode an `OtpParameters` message into the account it describes
+fn decode_otp_parameters(data: &[u8]) -> Result<OtpAuth> {
+    let mut secret = Vec::new();
+    let mut name = String::new();
+    let mut issuer = String::new();
+    let mut algorithm = 1;
+    let mut digits = 1;
+    let mut otp_type = 2;
+    let mut counter = 0;
+
+    let mut reader = ProtoReader { data };
+    while let Some((tag, field)) = reader.read_field()? {
+        match (tag, field) {
+            (1, Field::Bytes(bytes)) => secret = bytes.to_vec(),
+            (2, Field::Bytes(bytes)) => name = to_string(bytes)?,
+            (3, Field::Bytes(bytes)) => issuer = to_string(bytes)?,
+            (4, Field::Varint(value)) => algorithm = value,
+            (5, Field::Varint(value)) => digits = value,
+            (6, Field::Varint(value)) => otp_type = value,
+            (7, Field::Varint(value)) => counter = value,
+            _ => (),
+        }
+    }
+
+    // Names are usually prefixed with the issuer, e.g. `GitHub:evans`
+    let (issuer, label) = if issuer.is_empty() {
+        (None, name)
+    } else {
+        let label = match name.strip_prefix(&format!("{}:", issuer)) {
+            Some(label) => label.to_string(),
+            None => name,
+        };
+        (Some(issuer), label)
+    };
+    let account_name = join_label(issuer.as_deref(), &label);
+    let invalid = |reason: String| Error::InvalidEntry(account_name.clone(), reason);
+
+    if secret.is_empty() {
+        return Err(invalid(String::from("missing secret")));
+    }
+    // Enum values of `Algorithm`, `DigitCount` and `OtpType`, 0 is unspecified
+    let algorithm = match algorithm {
+        0 | 1 => "SHA1",
+        2 => "SHA256",
+        3 => "SHA512",
+        4 => {
+            let reason = Error::UnknownHashFunction(String::from("MD5")).to_string();
+            return Err(Error::UnsupportedEntry(account_name, reason));
+        }
+        _ => return Err(invalid(format!("unknown algorithm {}", algorithm))),
+    };
+    let digits = match digits {
+        0 | 1 => 6,
+        2 => 8,
+        _ => return Err(invalid(format!("unknown digit count {}", digits))),
+    };
+    let totp = match otp_type {
+        1 => false,
+        0 | 2 => true,
+        _ => return Err(invalid(format!("unknown OTP type {}", otp_type))),
+    };
+
+    Ok(OtpAuth {
+        totp,
+        label,
+        issuer,
+        secret: BASE32_NOPAD.encode(&secret),
+        algorithm: algorithm.to_string(),
+        digits,
+        period: if totp { Some(30) } else { None },
+        counter: if totp { None } else { Some(counter) },
+        steam: false,
+    })
+}
+
+fn to_string(bytes: &[u8]) -> Result<String> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| invalid("text is not valid UTF-8"))
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidMigrationPayload(reason.to_string())
+}
+
+// Field of a protobuf message, only the wire types used by `MigrationPayload` are kept
+enum Field<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+// Minimal reader of the protobuf wire format
+struct ProtoReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ProtoReader<'a> {
+    fn read_varint(&mut self) -> Result<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self
+                .data
+                .split_first()
+                .ok_or_else(|| invalid("truncated payload"))?;
+            self.data = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("varint is too long"))
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.data.len() {
+            return Err(invalid("truncated payload"));
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    // Read the next field and its tag, `None` at the end of the message
+    fn read_field(&mut self) -> Result<Option<(u64, Field<'a>)>> {
+        if self.data.is_empty() {
+            return Ok(None);
+        }
+        let key = self.read_varint()?;
+        let field = match key & 0b111 {
+            0 => Field::Varint(self.read_varint()?),
+            1 => {
+                self.read_bytes(8)?;
+                Field::Fixed
+            }
+            2 => {
+                let len = self.read_varint()? as usize;
+                Field::Bytes(self.read_bytes(len)?)
+            }
+            5 => {
+                self.read_bytes(4)?;
+                Field::Fixed
+            }
+            wire_type => return Err(invalid(&format!("unsupported wire type {}", wire_type))),
+        };
+        Ok(Some((key >> 3, field)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MigrationPayload;
+    use crate::errors::Error;
+    use crate::otpauth::OtpAuth;
+
+    // Export of three accounts sharing the secret "Hello!\xde\xad\xbe\xef"
+    const URI: &str = "otpauth-migration://offline?data=CjEKCkhlbGxvId6tvu8SGFRlc3QxOnRlc3QxQGV4YW1wbGUxLmNvbRoFVGVzdDEgASgBMAIKMQoKSGVsbG8h3q2%2B7xIYVGVzdDI6dGVzdDJAZXhhbXBsZTIuY29tGgVUZXN0MiABKAEwAgoxCgpIZWxsbyHerb7vEhhUZXN0Mzp0ZXN0M0BleGFtcGxlMy5jb20aBVRlc3QzIAEoATACEAEYASAAKICRs%2BQH";
+
+    #[test]
+    fn test_decode_payload() {
+        let payload = MigrationPayload::from_uri(URI).unwrap();
+        assert_eq!(payload.batch_size, 1);
+        assert_eq!(payload.batch_index, 0);
+        assert_eq!(payload.batch_id, 2089601152);
+        assert_eq!(payload.accounts.len(), 3);
+        assert_eq!(
+            *payload.accounts[0].as_ref().unwrap(),
+            OtpAuth {
+                totp: true,
+                label: String::from("test1@example1.com"),
+                issuer: Some(String::from("Test1")),
+                secret: String::from("JBSWY3DPEHPK3PXP"),
+                algorithm: String::from("SHA1"),
+                digits: 6,
+                period: Some(30),
+                counter: None,
+                steam: false,
+            }
+        );
+        assert_eq!(
+            payload.accounts[2].as_ref().unwrap().account_name(),
+            "Test3:test3@example3.com"
+        );
+    }
+
+    #[test]
+    fn test_decode_hotp_account() {
+        // Single HOTP account "acme" with 8 digits, SHA256 and counter 5
+        let mut data = vec![0x0a, 0x15, 0x0a, 0x05];
+        data.extend_from_slice(b"hello");
+        data.extend_from_slice(&[0x12, 0x04]);
+        data.extend_from_slice(b"acme");
+        data.extend_from_slice(&[0x20, 0x02, 0x28, 0x02, 0x30, 0x01, 0x38, 0x05]);
+        data.extend_from_slice(&[0x18, 0x02, 0x20, 0x01, 0x28, 0x07]);
+        let payload = MigrationPayload::decode(&data).unwrap();
+        assert_eq!(
+            (payload.batch_size, payload.batch_index, payload.batch_id),
+            (2, 1, 7)
+        );
+        let account = payload.accounts[0].as_ref().unwrap();
+        assert!(!account.totp);
+        assert_eq!(account.account_name(), "acme");
+        assert_eq!(account.secret, "NBSWY3DP");
+        assert_eq!(account.algorithm, "SHA256");
+        assert_eq!(account.digits, 8);
+        assert_eq!(account.counter, Some(5));
+    }
+
+    #[test]
+    fn test_decode_unsupported_account() {
+        // An MD5 account "md5" between two SHA1 accounts "first" and "last"
+        let mut data = Vec::new();
+        for (name, algorithm) in [("first", 1), ("md5", 4), ("last", 1)] {
+            data.extend_from_slice(&[0x0a, 0x0d + name.len() as u8, 0x0a, 0x05]);
+            data.extend_from_slice(b"hello");
+            data.extend_from_slice(&[0x12, name.len() as u8]);
+            data.extend_from_slice(name.as_bytes());
+            data.extend_from_slice(&[0x20, algorithm, 0x30, 0x02]);
+        }
+        let payload = MigrationPayload::decode(&data).unwrap();
+        assert_eq!(payload.accounts.len(), 3);
+        assert_eq!(
+            payload.accounts[0].as_ref().unwrap().account_name(),
+            "first"
+        );
+        assert!(matches!(
+            &payload.accounts[1],
+            Err(Error::UnsupportedEntry(name, _)) if name == "md5"
+        ));
+        assert_eq!(payload.accounts[2].as_ref().unwrap().account_name(), "last");
+    }
+
+    #[test]
+    fn test_invalid_payload() {
+        assert!(MigrationPayload::from_uri("otpauth://totp/acme?secret=NBSWY3DP").is_err());
+        assert!(MigrationPayload::from_uri("otpauth-migration://offline?data=%%%").is_err());
+        // Truncated message
+        assert!(MigrationPayload::decode(&[0x0a, 0x12, 0x0a]).is_err());
+    }
+}
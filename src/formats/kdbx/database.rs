@@ -0,0 +1,504 @@
+use super::xml::{self, Element};
+use super::{invalid, FORMAT};
+use crate::crypto;
+use crate::errors::{Error, Result};
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes256;
+use argon2::{Argon2, Params, Version};
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use chacha20::cipher::StreamCipher;
+use chacha20::ChaCha20;
+use data_encoding::{BASE64, HEXLOWER_PERMISSIVE};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use ring::digest::{self, Context, SHA256, SHA512};
+use ring::hmac;
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::io::{Read, Write};
+
+const SIGNATURE: [u8; 8] = [0x03, 0xd9, 0xa2, 0x9a, 0x67, 0xfb, 0x4b, 0xb5];
+const MAJOR_VERSION: u16 = 4;
+
+// Outer header fields
+const END_OF_HEADER: u8 = 0;
+const CIPHER_ID: u8 = 2;
+const COMPRESSION_FLAGS: u8 = 3;
+const MASTER_SEED: u8 = 4;
+const ENCRYPTION_IV: u8 = 7;
+const KDF_PARAMETERS: u8 = 11;
+
+// Inner header fields
+const INNER_RANDOM_STREAM_ID: u8 = 1;
+const INNER_RANDOM_STREAM_KEY: u8 = 2;
+const CHACHA20_STREAM: u32 = 3;
+
+const AES256_CBC: [u8; 16] = uuid(0x31c1f2e6_bf71_4350_be58_05216afc5aff);
+const CHACHA20: [u8; 16] = uuid(0xd6038a2b_8b6f_4cb5_a524_339a31dbb59a);
+const AES_KDF: [u8; 16] = uuid(0xc9d9f39a_628a_4460_bf74_0d08c18a4fea);
+// Identifier of AES-KDF in KDBX 3 databases, kept by some KDBX 4 databases
+const AES_KDF_KDBX3: [u8; 16] = uuid(0x7c02bb82_79a7_4ac0_927d_114a00648238);
+const ARGON2D: [u8; 16] = uuid(0xef636ddf_8c29_444b_91f7_a9a403e30a0c);
+const ARGON2ID: [u8; 16] = uuid(0x9e298b19_56db_4773_b23d_fc3ec6f0a1e6);
+
+// Key derivation of new databases, the defaults of KeePassXC
+const ARGON2_MEMORY: u64 = 64 * 1024 * 1024;
+const ARGON2_ITERATIONS: u64 = 10;
+const ARGON2_PARALLELISM: u32 = 2;
+const BLOCK_SIZE: usize = 1024 * 1024;
+
+const fn uuid(value: u128) -> [u8; 16] {
+    value.to_be_bytes()
+}
+
+// Key of a database, made of a password and/or a key file
+pub struct CompositeKey([u8; 32]);
+
+impl CompositeKey {
+    pub fn new(password: Option<&str>, keyfile: Option<&[u8]>) -> Result<CompositeKey> {
+        let mut context = Context::new(&SHA256);
+        if let Some(password) = password {
+            context.update(digest::digest(&SHA256, password.as_bytes()).as_ref());
+        }
+        if let Some(keyfile) = keyfile {
+            context.update(&keyfile_key(keyfile)?);
+        }
+        Ok(CompositeKey(context.finish().as_ref().try_into().unwrap()))
+    }
+}
+
+// Key files are either XML files holding the key, 32 raw bytes, 64 hex
+// characters, or any other file whose hash is the key
+fn keyfile_key(keyfile: &[u8]) -> Result<Vec<u8>> {
+    if let Some(data) = std::str::from_utf8(keyfile)
+        .ok()
+        .and_then(|content| xml::parse(content).ok())
+        .filter(|root| root.name == "KeyFile")
+        .and_then(|root| {
+            root.child("Key")?
+                .child("Data")
+                .map(|data| data.text.clone())
+        })
+    {
+        let data: String = data.split_whitespace().collect();
+        let key = HEXLOWER_PERMISSIVE
+            .decode(data.as_bytes())
+            .or_else(|_| BASE64.decode(data.as_bytes()))
+            .map_err(|_| invalid("invalid key file"))?;
+        return Ok(key);
+    }
+    if keyfile.len() == 32 {
+        return Ok(keyfile.to_vec());
+    }
+    if keyfile.len() == 64 {
+        if let Ok(key) = HEXLOWER_PERMISSIVE.decode(keyfile) {
+            return Ok(key);
+        }
+    }
+    Ok(digest::digest(&SHA256, keyfile).as_ref().to_vec())
+}
+
+// Decrypt a KDBX 4 database, returning its XML document with the protected
+// values in clear
+pub fn open(data: &[u8], key: &CompositeKey) -> Result<Element> {
+    let mut reader = ByteReader { data };
+    if reader.take(SIGNATURE.len())? != SIGNATURE {
+        return Err(invalid("not a KeePass database"));
+    }
+    let _minor_version = reader.u16()?;
+    let major_version = reader.u16()?;
+    if major_version != MAJOR_VERSION {
+        return Err(invalid(&format!(
+            "unsupported KDBX version {}, save the database in the KDBX 4 format",
+            major_version
+        )));
+    }
+
+    let mut fields = BTreeMap::new();
+    loop {
+        let id = reader.u8()?;
+        let len = reader.u32()? as usize;
+        let value = reader.take(len)?;
+        if id == END_OF_HEADER {
+            break;
+        }
+        fields.insert(id, value);
+    }
+    let header = &data[..data.len() - reader.data.len()];
+    let field = |id: u8| {
+        fields
+            .get(&id)
+            .copied()
+            .ok_or_else(|| invalid("missing header field"))
+    };
+    let header_hash = reader.take(32)?;
+    if digest::digest(&SHA256, header).as_ref() != header_hash {
+        return Err(invalid("corrupted header"));
+    }
+
+    let kdf_parameters = VariantDictionary::parse(field(KDF_PARAMETERS)?)?;
+    let transformed_key = transform_key(key, &kdf_parameters)?;
+    let master_seed = field(MASTER_SEED)?;
+    let hmac_key = hmac_base_key(master_seed, &transformed_key);
+    let header_hmac = reader.take(32)?;
+    hmac::verify(&block_hmac_key(&hmac_key, u64::MAX), header, header_hmac)
+        .map_err(|_| Error::BackupDecrypt(FORMAT))?;
+
+    let mut ciphertext = Vec::new();
+    for index in 0_u64.. {
+        let block_hmac = reader.take(32)?;
+        let len = reader.u32()?;
+        let block = reader.take(len as usize)?;
+        let message = [&index.to_le_bytes()[..], &len.to_le_bytes(), block].concat();
+        hmac::verify(&block_hmac_key(&hmac_key, index), &message, block_hmac)
+            .map_err(|_| invalid("corrupted data block"))?;
+        if block.is_empty() {
+            break;
+        }
+        ciphertext.extend_from_slice(block);
+    }
+
+    let cipher_key = sha256(&[master_seed, &transformed_key]);
+    let iv = field(ENCRYPTION_IV)?;
+    let compressed = match field(CIPHER_ID)? {
+        id if id == AES256_CBC => cbc::Decryptor::<Aes256>::new_from_slices(&cipher_key, iv)
+            .map_err(|_| invalid("invalid encryption IV"))?
+            .decrypt_padded_vec_mut::<Pkcs7>(&ciphertext)
+            .map_err(|_| Error::BackupDecrypt(FORMAT))?,
+        id if id == CHACHA20 => {
+            let mut plaintext = ciphertext;
+            chacha20(&cipher_key, iv)?.apply_keystream(&mut plaintext);
+            plaintext
+        }
+        _ => return Err(invalid("unsupported cipher, use AES-256 or ChaCha20")),
+    };
+    let compression = ByteReader {
+        data: field(COMPRESSION_FLAGS)?,
+    }
+    .u32()?;
+    let plaintext = match compression {
+        0 => compressed,
+        1 => {
+            let mut plaintext = Vec::new();
+            GzDecoder::new(compressed.as_slice()).read_to_end(&mut plaintext)?;
+            plaintext
+        }
+        _ => return Err(invalid("unsupported compression")),
+    };
+
+    let mut reader = ByteReader { data: &plaintext };
+    let (mut stream_id, mut stream_key) = (0, &[][..]);
+    loop {
+        let id = reader.u8()?;
+        let len = reader.u32()? as usize;
+        let value = reader.take(len)?;
+        match id {
+            END_OF_HEADER => break,
+            INNER_RANDOM_STREAM_ID => stream_id = ByteReader { data: value }.u32()?,
+            INNER_RANDOM_STREAM_KEY => stream_key = value,
+            _ => (),
+        }
+    }
+    if stream_id != CHACHA20_STREAM {
+        return Err(invalid("unsupported inner stream cipher"));
+    }
+
+    let content = std::str::from_utf8(reader.data).map_err(|_| invalid("malformed XML"))?;
+    let mut root = xml::parse(content)?;
+    let mut stream = inner_stream(stream_key)?;
+    root.visit_mut(&mut |element| {
+        if is_protected(element) {
+            let mut value = BASE64
+                .decode(element.text.as_bytes())
+                .map_err(|_| invalid("invalid protected value"))?;
+            stream.apply_keystream(&mut value);
+            element.text =
+                String::from_utf8(value).map_err(|_| invalid("invalid protected value"))?;
+        }
+        Ok(())
+    })?;
+    Ok(root)
+}
+
+// Encrypt an XML document as a KDBX 4 database, with AES-256 and Argon2d
+pub fn save(mut root: Element, key: &CompositeKey) -> Result<Vec<u8>> {
+    let master_seed = crypto::random_bytes(32)?;
+    let iv = crypto::random_bytes(16)?;
+    let mut kdf_parameters = VariantDictionary::default();
+    kdf_parameters.insert("$UUID", Variant::Bytes(ARGON2D.to_vec()));
+    kdf_parameters.insert("S", Variant::Bytes(crypto::random_bytes(32)?));
+    kdf_parameters.insert("M", Variant::UInt64(ARGON2_MEMORY));
+    kdf_parameters.insert("I", Variant::UInt64(ARGON2_ITERATIONS));
+    kdf_parameters.insert("P", Variant::UInt32(ARGON2_PARALLELISM));
+    kdf_parameters.insert("V", Variant::UInt32(0x13));
+
+    let mut header = SIGNATURE.to_vec();
+    header.extend_from_slice(&0_u16.to_le_bytes());
+    header.extend_from_slice(&MAJOR_VERSION.to_le_bytes());
+    write_field(&mut header, CIPHER_ID, &AES256_CBC);
+    write_field(&mut header, COMPRESSION_FLAGS, &1_u32.to_le_bytes());
+    write_field(&mut header, MASTER_SEED, &master_seed);
+    write_field(&mut header, ENCRYPTION_IV, &iv);
+    write_field(&mut header, KDF_PARAMETERS, &kdf_parameters.to_bytes());
+    write_field(&mut header, END_OF_HEADER, b"\r\n\r\n");
+
+    let transformed_key = transform_key(key, &kdf_parameters)?;
+    let hmac_key = hmac_base_key(&master_seed, &transformed_key);
+    let mut data = header.clone();
+    data.extend_from_slice(digest::digest(&SHA256, &header).as_ref());
+    data.extend_from_slice(hmac::sign(&block_hmac_key(&hmac_key, u64::MAX), &header).as_ref());
+
+    let stream_key = crypto::random_bytes(64)?;
+    let mut stream = inner_stream(&stream_key)?;
+    root.visit_mut(&mut |element| {
+        if is_protected(element) {
+            let mut value = element.text.as_bytes().to_vec();
+            stream.apply_keystream(&mut value);
+            element.text = BASE64.encode(&value);
+        }
+        Ok(())
+    })?;
+    let mut plaintext = Vec::new();
+    write_field(
+        &mut plaintext,
+        INNER_RANDOM_STREAM_ID,
+        &CHACHA20_STREAM.to_le_bytes(),
+    );
+    write_field(&mut plaintext, INNER_RANDOM_STREAM_KEY, &stream_key);
+    write_field(&mut plaintext, END_OF_HEADER, &[]);
+    plaintext.extend_from_slice(xml::write(&root)?.as_bytes());
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&plaintext)?;
+    let compressed = encoder.finish()?;
+    let cipher_key = sha256(&[&master_seed, &transformed_key]);
+    let ciphertext = cbc::Encryptor::<Aes256>::new_from_slices(&cipher_key, &iv)
+        .map_err(|_| Error::Crypto("invalid key"))?
+        .encrypt_padded_vec_mut::<Pkcs7>(&compressed);
+
+    let blocks = ciphertext
+        .chunks(BLOCK_SIZE)
+        .chain(std::iter::once(&[][..]));
+    for (index, block) in (0_u64..).zip(blocks) {
+        let len = (block.len() as u32).to_le_bytes();
+        let message = [&index.to_le_bytes()[..], &len, block].concat();
+        data.extend_from_slice(hmac::sign(&block_hmac_key(&hmac_key, index), &message).as_ref());
+        data.extend_from_slice(&len);
+        data.extend_from_slice(block);
+    }
+    Ok(data)
+}
+
+fn is_protected(element: &Element) -> bool {
+    element.name == "Value" && element.attribute("Protected") == Some("True")
+}
+
+fn transform_key(key: &CompositeKey, parameters: &VariantDictionary) -> Result<[u8; 32]> {
+    let mut transformed = [0_u8; 32];
+    match parameters.bytes("$UUID")? {
+        id if id == AES_KDF || id == AES_KDF_KDBX3 => {
+            let cipher = Aes256::new_from_slice(parameters.bytes("S")?)
+                .map_err(|_| invalid("invalid key derivation parameters"))?;
+            let rounds = parameters.u64("R")?;
+            transformed = key.0;
+            for half in transformed.chunks_mut(16) {
+                let block = GenericArray::from_mut_slice(half);
+                for _ in 0..rounds {
+                    cipher.encrypt_block(block);
+                }
+            }
+            transformed = sha256(&[&transformed]);
+        }
+        id if id == ARGON2D || id == ARGON2ID => {
+            let algorithm = if id == ARGON2D {
+                argon2::Algorithm::Argon2d
+            } else {
+                argon2::Algorithm::Argon2id
+            };
+            let version = match parameters.u32("V")? {
+                0x10 => Version::V0x10,
+                0x13 => Version::V0x13,
+                _ => return Err(invalid("unsupported Argon2 version")),
+            };
+            let params = Params::new(
+                (parameters.u64("M")? / 1024) as u32,
+                parameters.u64("I")? as u32,
+                parameters.u32("P")?,
+                Some(transformed.len()),
+            )
+            .map_err(|_| invalid("invalid key derivation parameters"))?;
+            Argon2::new(algorithm, version, params)
+                .hash_password_into(&key.0, parameters.bytes("S")?, &mut transformed)
+                .map_err(|_| Error::Crypto("key derivation failed"))?;
+        }
+        _ => return Err(invalid("unsupported key derivation function")),
+    }
+    Ok(transformed)
+}
+
+fn hmac_base_key(master_seed: &[u8], transformed_key: &[u8]) -> Vec<u8> {
+    let mut context = Context::new(&SHA512);
+    context.update(master_seed);
+    context.update(transformed_key);
+    context.update(&[1]);
+    context.finish().as_ref().to_vec()
+}
+
+fn block_hmac_key(base_key: &[u8], index: u64) -> hmac::Key {
+    let mut context = Context::new(&SHA512);
+    context.update(&index.to_le_bytes());
+    context.update(base_key);
+    hmac::Key::new(hmac::HMAC_SHA256, context.finish().as_ref())
+}
+
+// Stream cipher protecting the values of passwords and other sensitive fields
+fn inner_stream(stream_key: &[u8]) -> Result<ChaCha20> {
+    let hash = digest::digest(&SHA512, stream_key);
+    chacha20(&hash.as_ref()[..32], &hash.as_ref()[32..44])
+}
+
+fn chacha20(key: &[u8], nonce: &[u8]) -> Result<ChaCha20> {
+    ChaCha20::new_from_slices(key, nonce).map_err(|_| invalid("invalid encryption IV"))
+}
+
+fn sha256(parts: &[&[u8]]) -> [u8; 32] {
+    let mut context = Context::new(&SHA256);
+    for part in parts {
+        context.update(part);
+    }
+    context.finish().as_ref().try_into().unwrap()
+}
+
+fn write_field(data: &mut Vec<u8>, id: u8, value: &[u8]) {
+    data.push(id);
+    data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    data.extend_from_slice(value);
+}
+
+struct ByteReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(invalid("truncated file"));
+        }
+        let (value, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(value)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+enum Variant {
+    UInt32(u32),
+    UInt64(u64),
+    Bytes(Vec<u8>),
+    // Booleans, signed integers and strings, unused by the key derivation
+    // functions
+    Other(u8, Vec<u8>),
+}
+
+const VARIANT_DICTIONARY_VERSION: u16 = 0x0100;
+const UINT32: u8 = 0x04;
+const UINT64: u8 = 0x05;
+const BYTES: u8 = 0x42;
+
+// Key value map holding the parameters of the key derivation function
+#[derive(Default)]
+struct VariantDictionary(BTreeMap<String, Variant>);
+
+impl VariantDictionary {
+    fn parse(data: &[u8]) -> Result<VariantDictionary> {
+        let mut reader = ByteReader { data };
+        if reader.u16()? >> 8 != VARIANT_DICTIONARY_VERSION >> 8 {
+            return Err(invalid("unsupported key derivation parameters"));
+        }
+        let mut dictionary = VariantDictionary::default();
+        loop {
+            let value_type = reader.u8()?;
+            if value_type == 0 {
+                return Ok(dictionary);
+            }
+            let len = reader.u32()? as usize;
+            let key = String::from_utf8_lossy(reader.take(len)?).into_owned();
+            let len = reader.u32()? as usize;
+            let value = reader.take(len)?;
+            let value = match (value_type, value.len()) {
+                (UINT32, 4) => Variant::UInt32(u32::from_le_bytes(value.try_into().unwrap())),
+                (UINT64, 8) => Variant::UInt64(u64::from_le_bytes(value.try_into().unwrap())),
+                (BYTES, _) => Variant::Bytes(value.to_vec()),
+                (UINT32, _) | (UINT64, _) => {
+                    return Err(invalid("invalid key derivation parameters"))
+                }
+                _ => Variant::Other(value_type, value.to_vec()),
+            };
+            dictionary.0.insert(key, value);
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut data = VARIANT_DICTIONARY_VERSION.to_le_bytes().to_vec();
+        for (key, value) in self.0.iter() {
+            let (value_type, value) = match value {
+                Variant::UInt32(value) => (UINT32, value.to_le_bytes().to_vec()),
+                Variant::UInt64(value) => (UINT64, value.to_le_bytes().to_vec()),
+                Variant::Bytes(value) => (BYTES, value.clone()),
+                Variant::Other(value_type, value) => (*value_type, value.clone()),
+            };
+            data.push(value_type);
+            data.extend_from_slice(&(key.len() as u32).to_le_bytes());
+            data.extend_from_slice(key.as_bytes());
+            data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            data.extend_from_slice(&value);
+        }
+        data.push(0);
+        data
+    }
+
+    fn insert(&mut self, key: &str, value: Variant) {
+        self.0.insert(key.to_string(), value);
+    }
+
+    fn get(&self, key: &str) -> Result<&Variant> {
+        self.0
+            .get(key)
+            .ok_or_else(|| invalid("missing key derivation parameter"))
+    }
+
+    fn u32(&self, key: &str) -> Result<u32> {
+        match self.get(key)? {
+            Variant::UInt32(value) => Ok(*value),
+            _ => Err(invalid("invalid key derivation parameters")),
+        }
+    }
+
+    fn u64(&self, key: &str) -> Result<u64> {
+        match self.get(key)? {
+            Variant::UInt64(value) => Ok(*value),
+            _ => Err(invalid("invalid key derivation parameters")),
+        }
+    }
+
+    fn bytes(&self, key: &str) -> Result<&[u8]> {
+        match self.get(key)? {
+            Variant::Bytes(value) => Ok(value),
+            _ => Err(invalid("invalid key derivation parameters")),
+        }
+    }
+}
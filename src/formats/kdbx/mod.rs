@@ -0,0 +1,349 @@
+use self::database::CompositeKey;
+use self::xml::Element;
+use crate::account::Account;
+use crate::crypto;
+use crate::errors::{Error, Result};
+use crate::formats::{self, entry_name, EntryFields, ImportOptions, Importer};
+use crate::otpauth::OtpAuth;
+use data_encoding::BASE64;
+use std::fs;
+use std::path::Path;
+
+mod database;
+mod xml;
+
+const FORMAT: &str = "KeePass";
+
+// Importer of the TOTP entries of KeePass databases in the KDBX 4 format, as
+// saved by KeePassXC. The database is unlocked with a password and/or a key file.
+pub struct Kdbx;
+
+impl Importer for Kdbx {
+    fn name(&self) -> &'static str {
+        "kdbx"
+    }
+
+    fn read(&self, inputs: &[&String], options: &ImportOptions) -> Result<Vec<formats::Entry>> {
+        let keyfile = match &options.keyfile {
+            Some(path) => Some(fs::read(path)?),
+            None => None,
+        };
+        let password = crypto::read_backup_passphrase()?;
+        // A database can be protected by a key file alone
+        let password = Some(password).filter(|password| !password.is_empty() || keyfile.is_none());
+        let key = CompositeKey::new(password.as_deref(), keyfile.as_deref())?;
+
+        let mut entries = Vec::new();
+        for input in inputs {
+            entries.extend(read(&fs::read(input)?, &key)?);
+        }
+        Ok(entries)
+    }
+}
+
+// Read the accounts of the entries holding TOTP settings, other entries are ignored
+pub fn read(data: &[u8], key: &CompositeKey) -> Result<Vec<formats::Entry>> {
+    let root = database::open(data, key)?;
+    let recycle_bin = root
+        .child("Meta")
+        .and_then(|meta| meta.child("RecycleBinUUID"))
+        .map(|uuid| uuid.text.as_str());
+    let mut entries = Vec::new();
+    if let Some(group) = root.child("Root").and_then(|root| root.child("Group")) {
+        read_group(group, None, recycle_bin, &mut entries);
+    }
+    Ok(entries)
+}
+
+// Write the accounts as a new KDBX 4 database, holding an entry per account
+pub fn write(accounts: &[(&String, &Account)], key: &CompositeKey) -> Result<Vec<u8>> {
+    let mut group = Element::new("Group");
+    group
+        .children
+        .push(Element::with_text("UUID", &new_uuid()?));
+    group.children.push(Element::with_text("Name", "cloak"));
+    for (name, account) in accounts {
+        let (issuer, label) = account.issuer_and_label(name);
+        let (title, username) = match issuer {
+            Some(issuer) => (issuer, label),
+            None => (label, String::new()),
+        };
+        let otpauth = OtpAuth::from_account(name, account)?.to_string();
+
+        let mut entry = Element::new("Entry");
+        entry
+            .children
+            .push(Element::with_text("UUID", &new_uuid()?));
+        entry
+            .children
+            .push(Element::with_text("Tags", &account.tags.join(";")));
+        entry.children.push(string_field("Title", &title, false));
+        entry
+            .children
+            .push(string_field("UserName", &username, false));
+        entry.children.push(string_field("Password", "", true));
+        entry.children.push(string_field("otp", &otpauth, true));
+        group.children.push(entry);
+    }
+
+    let mut meta = Element::new("Meta");
+    meta.children.push(Element::with_text("Generator", "cloak"));
+    meta.children
+        .push(Element::with_text("DatabaseName", "cloak"));
+    let mut root = Element::new("Root");
+    root.children.push(group);
+    let mut document = Element::new("KeePassFile");
+    document.children.push(meta);
+    document.children.push(root);
+    database::save(document, key)
+}
+
+pub fn composite_key(password: &str, keyfile: Option<&Path>) -> Result<CompositeKey> {
+    let keyfile = match keyfile {
+        Some(path) => Some(fs::read(path)?),
+        None => None,
+    };
+    CompositeKey::new(Some(password), keyfile.as_deref())
+}
+
+fn read_group(
+    group: &Element,
+    tag: Option<&str>,
+    recycle_bin: Option<&str>,
+    entries: &mut Vec<formats::Entry>,
+) {
+    let uuid = group.child("UUID").map(|uuid| uuid.text.as_str());
+    if uuid.is_some() && uuid == recycle_bin {
+        return;
+    }
+    for entry in group.children_named("Entry") {
+        if let Some(entry) = read_entry(entry, tag) {
+            entries.push(entry);
+        }
+    }
+    for child in group.children_named("Group") {
+        let name = child.child("Name").map(|name| name.text.as_str());
+        read_group(child, name, recycle_bin, entries);
+    }
+}
+
+// Read the TOTP settings of an entry, either an otpauth URI in the `otp`
+// attribute or the legacy `TOTP Seed` and `TOTP Settings` attributes of KeePassXC
+fn read_entry(entry: &Element, group: Option<&str>) -> Option<formats::Entry> {
+    let field = |key: &str| {
+        entry
+            .children_named("String")
+            .find(|string| string.child("Key").map(|k| k.text.as_str()) == Some(key))
+            .and_then(|string| string.child("Value"))
+            .map(|value| value.text.trim())
+            .filter(|value| !value.is_empty())
+    };
+    let title = field("Title").unwrap_or_default();
+    let username = field("UserName").unwrap_or_default();
+    let name = entry_name(title, username);
+    let mut tags: Vec<String> = entry
+        .child("Tags")
+        .map(|tags| tags.text.as_str())
+        .unwrap_or_default()
+        .split(&[';', ','][..])
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(String::from)
+        .collect();
+    tags.extend(
+        group
+            .map(String::from)
+            .filter(|group| !tags.contains(group)),
+    );
+
+    if let Some(uri) = field("otp") {
+        let otpauth = match uri.parse::<OtpAuth>() {
+            Ok(otpauth) => otpauth,
+            Err(err) => return Some(Err(Error::InvalidEntry(name, err.to_string()))),
+        };
+        let name = if name.is_empty() {
+            otpauth.account_name()
+        } else {
+            name
+        };
+        let mut account = otpauth.into_account();
+        account.tags = tags;
+        return Some(Ok((name, account)));
+    }
+
+    let seed = field("TOTP Seed")?;
+    // `<period>;<digits>`, where the digits are `S` for Steam
+    let settings = field("TOTP Settings").unwrap_or("30;6");
+    let mut settings = settings.split(';');
+    let period = settings.next().and_then(|period| period.parse().ok());
+    let (otp_type, digits) = match settings.next() {
+        Some("S") => ("steam", 5),
+        digits => ("totp", digits.and_then(|d| d.parse().ok()).unwrap_or(6)),
+    };
+    let fields = EntryFields {
+        issuer: title.to_string(),
+        name: username.to_string(),
+        otp_type: otp_type.to_string(),
+        secret: seed.to_string(),
+        algorithm: String::from("SHA1"),
+        digits,
+        period: period.or(Some(30)),
+        counter: None,
+        tags,
+    };
+    Some(fields.into_account())
+}
+
+fn string_field(key: &str, value: &str, protected: bool) -> Element {
+    let mut value = Element::with_text("Value", value);
+    if protected {
+        value
+            .attributes
+            .push((String::from("Protected"), String::from("True")));
+    }
+    let mut string = Element::new("String");
+    string.children.push(Element::with_text("Key", key));
+    string.children.push(value);
+    string
+}
+
+fn new_uuid() -> Result<String> {
+    Ok(BASE64.encode(&crypto::random_bytes(16)?))
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidBackup(FORMAT, reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::database::{self, CompositeKey};
+    use super::xml::Element;
+    use super::{read, write};
+    use crate::account::Account;
+    use crate::errors::Error;
+
+    fn account(key: &str, tags: Vec<String>) -> Account {
+        Account {
+            key: key.to_string(),
+            totp: true,
+            hash_function: String::from("SHA256"),
+            counter: None,
+            digits: Some(8),
+            period: Some(60),
+            t0: None,
+            steam: false,
+            tags,
+            issuer: None,
+            label: None,
+            notes: None,
+            created_at: None,
+            last_used_at: None,
+            external_key: None,
+        }
+    }
+
+    #[test]
+    fn test_write_read_roundtrip() {
+        let key = CompositeKey::new(Some("hunter2"), None).unwrap();
+        let (github, gitlab) = (String::from("GitHub:evans"), String::from("gitlab"));
+        let github_account = account(
+            "4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6",
+            vec![String::from("Work")],
+        );
+        let gitlab_account = account("JBSWY3DPEHPK3PXP", Vec::new());
+        let data = write(
+            &[(&github, &github_account), (&gitlab, &gitlab_account)],
+            &key,
+        )
+        .unwrap();
+
+        let entries = read(&data, &key).unwrap();
+        let (name, imported) = entries[0].as_ref().unwrap();
+        assert_eq!(name, &github);
+        assert_eq!(imported.key, github_account.key);
+        assert_eq!(imported.hash_function, "SHA256");
+        assert_eq!((imported.digits, imported.period), (Some(8), Some(60)));
+        assert_eq!(imported.tags, github_account.tags);
+        assert_eq!(entries[1].as_ref().unwrap().0, gitlab);
+
+        let wrong_key = CompositeKey::new(Some("hunter3"), None).unwrap();
+        assert!(matches!(
+            read(&data, &wrong_key),
+            Err(Error::BackupDecrypt(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_legacy_settings_and_groups() {
+        let string = |key: &str, value: &str| {
+            let mut string = Element::new("String");
+            string.children.push(Element::with_text("Key", key));
+            string.children.push(Element::with_text("Value", value));
+            string
+        };
+        let entry = |strings: Vec<Element>| {
+            let mut entry = Element::new("Entry");
+            entry.children.extend(strings);
+            entry
+        };
+        let group = |name: &str, uuid: &str, children: Vec<Element>| {
+            let mut group = Element::new("Group");
+            group.children.push(Element::with_text("UUID", uuid));
+            group.children.push(Element::with_text("Name", name));
+            group.children.extend(children);
+            group
+        };
+
+        let steam = entry(vec![
+            string("Title", "Steam"),
+            string("UserName", "gaben"),
+            string("TOTP Seed", "JRZC L47C MXVO QMNP ZR2F 7J4R GI"),
+            string("TOTP Settings", "30;S"),
+        ]);
+        let legacy = entry(vec![
+            string("Title", "legacy"),
+            string("TOTP Seed", "JBSWY3DPEHPK3PXP"),
+            string("TOTP Settings", "60;8"),
+        ]);
+        let password_only = entry(vec![string("Title", "email"), string("Password", "x")]);
+        let deleted = entry(vec![
+            string("Title", "deleted"),
+            string("otp", "otpauth://totp/deleted?secret=JBSWY3DPEHPK3PXP"),
+        ]);
+        let root_group = group(
+            "Root",
+            "cm9vdA==",
+            vec![
+                legacy,
+                password_only,
+                group("Games", "Z2FtZXM=", vec![steam]),
+                group("Recycle Bin", "YmluYmluYg==", vec![deleted]),
+            ],
+        );
+
+        let mut meta = Element::new("Meta");
+        meta.children
+            .push(Element::with_text("RecycleBinUUID", "YmluYmluYg=="));
+        let mut root = Element::new("Root");
+        root.children.push(root_group);
+        let mut document = Element::new("KeePassFile");
+        document.children.push(meta);
+        document.children.push(root);
+
+        let key = CompositeKey::new(None, Some(b"key file content")).unwrap();
+        let data = database::save(document, &key).unwrap();
+        let entries = read(&data, &key).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let (name, account) = entries[0].as_ref().unwrap();
+        assert_eq!(name, "legacy");
+        assert_eq!((account.digits, account.period), (Some(8), Some(60)));
+
+        let (name, account) = entries[1].as_ref().unwrap();
+        assert_eq!(name, "Steam:gaben");
+        assert!(account.steam);
+        assert_eq!(account.key, "JRZCL47CMXVOQMNPZR2F7J4RGI");
+        assert_eq!(account.tags, vec![String::from("Games")]);
+    }
+}
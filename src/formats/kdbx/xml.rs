@@ -0,0 +1,159 @@
+use super::invalid;
+use crate::errors::Result;
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+
+// Element of the XML document of a KeePass database. The documents are small
+// enough to be held in memory, which keeps the handling of protected values,
+// decrypted in document order, simple.
+#[derive(Debug, Default, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub text: String,
+    pub children: Vec<Element>,
+}
+
+impl Element {
+    pub fn new(name: &str) -> Element {
+        Element {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_text(name: &str, text: &str) -> Element {
+        Element {
+            text: text.to_string(),
+            ..Element::new(name)
+        }
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    // Visit the element and its descendants in document order
+    pub fn visit_mut<F: FnMut(&mut Element) -> Result<()>>(&mut self, visit: &mut F) -> Result<()> {
+        visit(self)?;
+        for child in self.children.iter_mut() {
+            child.visit_mut(visit)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn parse(xml: &str) -> Result<Element> {
+    let mut reader = Reader::from_str(xml);
+    let mut stack: Vec<Element> = Vec::new();
+    loop {
+        let element = match reader.read_event().map_err(xml_error)? {
+            Event::Start(start) => {
+                stack.push(start_element(&start)?);
+                continue;
+            }
+            Event::Empty(start) => start_element(&start)?,
+            Event::End(_) => stack.pop().ok_or_else(|| invalid("malformed XML"))?,
+            Event::Text(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text.unescape().map_err(xml_error)?);
+                }
+                continue;
+            }
+            Event::CData(data) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&String::from_utf8_lossy(&data));
+                }
+                continue;
+            }
+            Event::Eof => return Err(invalid("malformed XML")),
+            _ => continue,
+        };
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(element),
+            None => return Ok(element),
+        }
+    }
+}
+
+pub fn write(root: &Element) -> Result<String> {
+    let mut writer = Writer::new_with_indent(Vec::new(), b'\t', 1);
+    let declaration = BytesDecl::new("1.0", Some("utf-8"), Some("yes"));
+    writer
+        .write_event(Event::Decl(declaration))
+        .map_err(xml_error)?;
+    write_element(&mut writer, root)?;
+    String::from_utf8(writer.into_inner()).map_err(|_| invalid("malformed XML"))
+}
+
+fn write_element(writer: &mut Writer<Vec<u8>>, element: &Element) -> Result<()> {
+    let mut start = BytesStart::new(element.name.as_str());
+    for (key, value) in element.attributes.iter() {
+        start.push_attribute((key.as_str(), value.as_str()));
+    }
+    if element.text.is_empty() && element.children.is_empty() {
+        return writer.write_event(Event::Empty(start)).map_err(xml_error);
+    }
+    writer.write_event(Event::Start(start)).map_err(xml_error)?;
+    if !element.text.is_empty() {
+        let text = BytesText::new(&element.text);
+        writer.write_event(Event::Text(text)).map_err(xml_error)?;
+    }
+    for child in element.children.iter() {
+        write_element(writer, child)?;
+    }
+    let end = BytesEnd::new(element.name.as_str());
+    writer.write_event(Event::End(end)).map_err(xml_error)
+}
+
+fn start_element(start: &BytesStart) -> Result<Element> {
+    let mut element = Element::new(&String::from_utf8_lossy(start.name().as_ref()));
+    for attribute in start.attributes() {
+        let attribute = attribute.map_err(xml_error)?;
+        let key = String::from_utf8_lossy(attribute.key.as_ref()).into_owned();
+        let value = attribute.unescape_value().map_err(xml_error)?;
+        element.attributes.push((key, value.into_owned()));
+    }
+    Ok(element)
+}
+
+fn xml_error<E: ToString>(err: E) -> crate::errors::Error {
+    invalid(&format!("malformed XML: {}", err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, write, Element};
+
+    #[test]
+    fn test_parse_write_roundtrip() {
+        let mut root = Element::new("KeePassFile");
+        let mut value = Element::with_text("Value", "a < b & \"c\"");
+        value
+            .attributes
+            .push((String::from("Protected"), String::from("True")));
+        root.children.push(value);
+        root.children.push(Element::new("Empty"));
+
+        let xml = write(&root).unwrap();
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\""));
+        let parsed = parse(&xml).unwrap();
+        assert_eq!(parsed.child("Value").unwrap().text, "a < b & \"c\"");
+        assert_eq!(
+            parsed.child("Value").unwrap().attribute("Protected"),
+            Some("True")
+        );
+        assert_eq!(parsed.child("Empty"), Some(&Element::new("Empty")));
+    }
+}
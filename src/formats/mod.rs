@@ -0,0 +1,238 @@
+use crate::account::Account;
+use crate::cmd::add::is_base32_key;
+use crate::errors::{Error, Result};
+use crate::otp::{HashFunction, MAX_DIGITS, MIN_DIGITS, STEAM_DIGITS};
+use crate::otpauth::{join_label, OtpAuth};
+use std::path::PathBuf;
+
+pub mod aegis;
+pub mod andotp;
+pub mod bitwarden;
+pub mod freeotp_plus;
+pub mod google_migration;
+pub mod kdbx;
+pub mod onepassword;
+pub mod paper;
+pub mod raivo;
+pub mod twofas;
+
+// Account read from a backup, or the reason why it could not be read
+pub type Entry = Result<(String, Account)>;
+
+// Options of `cloak import` used by some of the formats only
+#[derive(Default)]
+pub struct ImportOptions {
+    // Key file unlocking the database, along with the password
+    pub keyfile: Option<PathBuf>,
+}
+
+// Reads the accounts exported by another authenticator
+pub trait Importer {
+    // Name of the format, given to `cloak import --format`
+    fn name(&self) -> &'static str;
+
+    // Read the accounts of the inputs, usually files. Entries that can't be
+    // imported are returned as errors so that they can all be reported at once.
+    fn read(&self, inputs: &[&String], options: &ImportOptions) -> Result<Vec<Entry>>;
+}
+
+pub const IMPORTERS: [&dyn Importer; 9] = [
+    &aegis::Aegis,
+    &andotp::AndOtp,
+    &bitwarden::Bitwarden,
+    &freeotp_plus::FreeOtpPlus,
+    &google_migration::GoogleMigration,
+    &kdbx::Kdbx,
+    &onepassword::OnePassword,
+    &raivo::Raivo,
+    &twofas::TwoFas,
+];
+
+pub fn importer(name: &str) -> Option<&'static dyn Importer> {
+    IMPORTERS
+        .iter()
+        .find(|importer| importer.name() == name)
+        .copied()
+}
+
+// Fields shared by the entries of most backups
+pub struct EntryFields {
+    pub issuer: String,
+    pub name: String,
+    // `totp`, `hotp` or `steam`, in any case
+    pub otp_type: String,
+    // Base32 encoded secret
+    pub secret: String,
+    pub algorithm: String,
+    pub digits: usize,
+    pub period: Option<u64>,
+    pub counter: Option<u64>,
+    pub tags: Vec<String>,
+}
+
+impl EntryFields {
+    // Validate the fields and convert them into an account. Entries of other
+    // types than TOTP, HOTP or Steam are reported as unsupported.
+    pub fn into_account(self) -> Entry {
+        let name = entry_name(&self.issuer, &self.name);
+        let invalid = |reason: String| Error::InvalidEntry(name.clone(), reason);
+
+        let (totp, steam) = match self.otp_type.to_lowercase().as_str() {
+            "totp" => (true, false),
+            "hotp" => (false, false),
+            "steam" => (true, true),
+            _ => {
+                let reason = format!("unsupported type '{}'", self.otp_type);
+                return Err(Error::UnsupportedEntry(name, reason));
+            }
+        };
+        let key = is_base32_key(self.secret.replace(' ', "").trim_end_matches('='))
+            .map_err(|_| invalid(String::from("the secret is not a valid base32 encoding")))?;
+        let algorithm = self.algorithm.to_uppercase();
+        if let Err(err) = algorithm.parse::<HashFunction>() {
+            return Err(invalid(err.to_string()));
+        }
+        if !(MIN_DIGITS..=MAX_DIGITS).contains(&self.digits) {
+            return Err(invalid(Error::InvalidDigits(self.digits).to_string()));
+        }
+        if totp && self.period == Some(0) {
+            return Err(invalid(Error::InvalidPeriod.to_string()));
+        }
+
+        let account = Account {
+            key,
+            totp,
+            hash_function: algorithm,
+            counter: if totp {
+                None
+            } else {
+                Some(self.counter.unwrap_or(0))
+            },
+            digits: Some(self.digits),
+            period: if totp { self.period } else { None },
+            t0: None,
+            steam,
+            tags: self.tags,
+            issuer: Some(self.issuer).filter(|issuer| !issuer.is_empty()),
+            label: Some(self.name).filter(|label| !label.is_empty()),
+            notes: None,
+            created_at: None,
+            last_used_at: None,
+            external_key: None,
+        };
+        Ok((name, account))
+    }
+}
+
+// Name of an imported account, `issuer:name` unless one of them is missing
+pub fn entry_name(issuer: &str, name: &str) -> String {
+    match (name.is_empty(), issuer.is_empty()) {
+        (true, false) => issuer.to_string(),
+        (_, true) => name.to_string(),
+        _ => join_label(Some(issuer), name),
+    }
+}
+
+// Account of a password manager item whose TOTP field holds an otpauth URI, a
+// `steam://` URI or a bare base32 secret. The account is named after the item
+// rather than the label of the URI.
+pub fn totp_entry(name: String, totp: &str, tags: Vec<String>) -> Entry {
+    let totp = totp.trim();
+    if matches!(totp.get(..10), Some(scheme) if scheme.eq_ignore_ascii_case("otpauth://")) {
+        let otpauth: OtpAuth = totp
+            .parse()
+            .map_err(|err: Error| Error::InvalidEntry(name.clone(), err.to_string()))?;
+        let mut account = otpauth.into_account();
+        account.tags = tags;
+        return Ok((name, account));
+    }
+    let (otp_type, secret, digits) = match totp.strip_prefix("steam://") {
+        Some(secret) => ("steam", secret, STEAM_DIGITS),
+        None => ("totp", totp, 6),
+    };
+    EntryFields {
+        issuer: String::new(),
+        name,
+        otp_type: otp_type.to_string(),
+        secret: secret.to_string(),
+        algorithm: String::from("SHA1"),
+        digits,
+        period: Some(30),
+        counter: None,
+        tags,
+    }
+    .into_account()
+}
+
+// Read the items with a TOTP field of a CSV export. The columns are found by
+// their header, which differs between password managers and their versions.
+pub fn read_csv(content: &str) -> Result<Vec<Entry>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers: Vec<String> = reader
+        .headers()?
+        .iter()
+        .map(|header| header.trim().to_lowercase())
+        .collect();
+    let column = |names: &[&str]| {
+        headers
+            .iter()
+            .position(|header| names.contains(&header.as_str()))
+    };
+    let title = column(&["name", "title"]);
+    let username = column(&["login_username", "username"]);
+    let totp = column(&["login_totp", "otpauth", "totp", "one-time password"])
+        .ok_or_else(|| Error::InvalidBackup("CSV", String::from("no TOTP column")))?;
+    let folder = column(&["folder"]);
+    let tags = column(&["tags"]);
+    let archived = column(&["archived"]);
+
+    let mut entries = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let field = |index: Option<usize>| index.and_then(|index| record.get(index)).unwrap_or("");
+        if field(Some(totp)).trim().is_empty() || field(archived).eq_ignore_ascii_case("true") {
+            continue;
+        }
+        let item_tags = field(folder)
+            .split('/')
+            .take(1)
+            .chain(field(tags).split(&[';', ','][..]))
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(String::from)
+            .collect();
+        let name = entry_name(field(title).trim(), field(username).trim());
+        entries.push(totp_entry(name, field(Some(totp)), item_tags));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::totp_entry;
+
+    #[test]
+    fn test_totp_entry() {
+        let (name, account) = totp_entry(
+            String::from("GitHub"),
+            "OTPAUTH://totp/GitHub:evans?secret=JBSWY3DPEHPK3PXP",
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(name, "GitHub");
+        assert_eq!(account.key, "JBSWY3DPEHPK3PXP");
+        let (_, account) = totp_entry(
+            String::from("Steam"),
+            "steam://JBSWY3DPEHPK3PXP",
+            Vec::new(),
+        )
+        .unwrap();
+        assert!(account.steam);
+
+        // A multibyte character straddling the length of the scheme
+        assert!(totp_entry(String::from("GitHub"), "otpauth:/éabc", Vec::new()).is_err());
+        assert!(totp_entry(String::from("GitHub"), "ééééééééé", Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use crate::errors::{Error, Result};
+use crate::formats::{self, entry_name, totp_entry, Entry, ImportOptions, Importer};
+use serde_json::Value;
+use std::fs;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+const FORMAT: &str = "1Password";
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+// File of a `.1pux` archive holding the accounts, vaults and items
+const EXPORT_DATA: &str = "export.data";
+
+// Importer of the one-time password fields of 1Password `.1pux` or CSV exports
+pub struct OnePassword;
+
+impl Importer for OnePassword {
+    fn name(&self) -> &'static str {
+        "1password"
+    }
+
+    fn read(&self, inputs: &[&String], _options: &ImportOptions) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        for input in inputs {
+            entries.extend(read(&fs::read(input)?)?);
+        }
+        Ok(entries)
+    }
+}
+
+#[derive(Deserialize)]
+struct Export {
+    accounts: Vec<ExportAccount>,
+}
+
+#[derive(Deserialize)]
+struct ExportAccount {
+    #[serde(default)]
+    vaults: Vec<Vault>,
+}
+
+#[derive(Deserialize)]
+struct Vault {
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Item {
+    #[serde(default)]
+    state: String,
+    overview: Overview,
+    #[serde(default)]
+    details: Details,
+}
+
+#[derive(Deserialize)]
+struct Overview {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Details {
+    #[serde(default)]
+    login_fields: Vec<LoginField>,
+    #[serde(default)]
+    sections: Vec<Section>,
+}
+
+#[derive(Deserialize)]
+struct LoginField {
+    #[serde(default)]
+    value: String,
+    designation: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Section {
+    #[serde(default)]
+    fields: Vec<SectionField>,
+}
+
+// The value of a section field is an object keyed by the type of the field
+#[derive(Deserialize)]
+struct SectionField {
+    value: Value,
+}
+
+// Read the items with a one-time password field of a 1Password `.1pux`
+// archive or CSV export, archived items are ignored
+pub fn read(data: &[u8]) -> Result<Vec<Entry>> {
+    if !data.starts_with(ZIP_MAGIC) {
+        let content = String::from_utf8(data.to_vec())
+            .map_err(|_| Error::InvalidBackup(FORMAT, String::from("not a 1PUX or CSV file")))?;
+        return formats::read_csv(&content);
+    }
+
+    let mut archive = ZipArchive::new(Cursor::new(data))?;
+    let mut json = String::new();
+    archive
+        .by_name(EXPORT_DATA)
+        .map_err(|_| Error::InvalidBackup(FORMAT, format!("no {} in the archive", EXPORT_DATA)))?
+        .read_to_string(&mut json)?;
+    let export: Export = serde_json::from_str(&json)?;
+
+    Ok(export
+        .accounts
+        .iter()
+        .flat_map(|account| &account.vaults)
+        .flat_map(|vault| &vault.items)
+        .filter(|item| item.state != "archived")
+        .filter_map(|item| {
+            let totp = item
+                .details
+                .sections
+                .iter()
+                .flat_map(|section| &section.fields)
+                .find_map(|field| field.value.get("totp").and_then(Value::as_str))
+                .filter(|totp| !totp.trim().is_empty())?;
+            let username = item
+                .details
+                .login_fields
+                .iter()
+                .find(|field| field.designation.as_deref() == Some("username"))
+                .map(|field| field.value.as_str())
+                .unwrap_or_default();
+            let name = entry_name(&item.overview.title, username);
+            Some(totp_entry(name, totp, item.overview.tags.clone()))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, EXPORT_DATA};
+    use std::io::{Cursor, Write};
+    use zip::write::{FileOptions, ZipWriter};
+
+    #[test]
+    fn test_read_1pux_export() {
+        let export = r#"{"accounts": [{"attrs": {"name": "Evans"}, "vaults": [{"attrs": {"name": "Private"}, "items": [
+            {"uuid": "a", "state": "active", "categoryUuid": "001",
+             "overview": {"title": "GitHub", "tags": ["Work"]},
+             "details": {"loginFields": [{"value": "evans", "name": "username", "designation": "username"},
+                                         {"value": "x", "name": "password", "designation": "password"}],
+                         "sections": [{"title": "", "fields": [
+                             {"title": "one-time password", "id": "TOTP_1",
+                              "value": {"totp": "otpauth://totp/GitHub:evans?secret=JBSWY3DPEHPK3PXP&period=60"}}]}]}},
+            {"uuid": "b", "state": "active", "overview": {"title": "Steam"},
+             "details": {"loginFields": [{"value": "gaben", "designation": "username"}],
+                         "sections": [{"fields": [{"value": {"string": "note"}},
+                                                  {"value": {"totp": "steam://JRZCL47CMXVOQMNPZR2F7J4RGI"}}]}]}},
+            {"uuid": "c", "state": "archived", "overview": {"title": "old"},
+             "details": {"sections": [{"fields": [{"value": {"totp": "JBSWY3DPEHPK3PXP"}}]}]}},
+            {"uuid": "d", "state": "active", "overview": {"title": "email"}, "details": {}}
+        ]}]}]}"#;
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("export.attributes", FileOptions::default())
+            .unwrap();
+        zip.write_all(b"{}").unwrap();
+        zip.start_file(EXPORT_DATA, FileOptions::default()).unwrap();
+        zip.write_all(export.as_bytes()).unwrap();
+        let data = zip.finish().unwrap().into_inner();
+
+        let entries = read(&data).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let (name, account) = entries[0].as_ref().unwrap();
+        assert_eq!(name, "GitHub:evans");
+        assert_eq!(account.period, Some(60));
+        assert_eq!(account.tags, vec![String::from("Work")]);
+
+        let (name, account) = entries[1].as_ref().unwrap();
+        assert_eq!(name, "Steam:gaben");
+        assert!(account.steam);
+    }
+
+    #[test]
+    fn test_read_csv_export() {
+        let csv = "\"Title\",\"Url\",\"Username\",\"Password\",\"OTPAuth\",\"Favorite\",\"Archived\",\"Tags\",\"Notes\"\n\
+                   \"GitHub\",\"https://github.com\",\"evans\",\"x\",\"otpauth://totp/GitHub:evans?secret=JBSWY3DPEHPK3PXP\",\"false\",\"false\",\"Work;Code\",\"\"\n\
+                   \"old\",\"\",\"evans\",\"x\",\"JBSWY3DPEHPK3PXP\",\"false\",\"true\",\"\",\"\"\n";
+        let entries = read(csv.as_bytes()).unwrap();
+        assert_eq!(entries.len(), 1);
+        let (name, account) = entries[0].as_ref().unwrap();
+        assert_eq!(name, "GitHub:evans");
+        assert_eq!(
+            account.tags,
+            vec![String::from("Work"), String::from("Code")]
+        );
+    }
+}
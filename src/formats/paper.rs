@@ -0,0 +1,170 @@
+use crate::account::Account;
+use crate::errors::Result;
+use crate::otpauth::OtpAuth;
+use crate::qr;
+use data_encoding::BASE32_NOPAD;
+use ring::digest::{digest, SHA256};
+
+// Size of the QR codes, in CSS pixels
+const QR_CODE_SIZE: u32 = 192;
+// Number of characters of the checksum of a key
+const CHECKSUM_LEN: usize = 4;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 1cm; }
+h1 { font-size: 1.4em; }
+.account { display: flex; gap: 1em; align-items: center; padding: 0.5em 0;
+           border-bottom: 1px dashed #888; page-break-inside: avoid; break-inside: avoid; }
+.account svg { flex: none; width: 4.5cm; height: 4.5cm; }
+.name { font-size: 1.2em; font-weight: bold; }
+.key, .checksum { font-family: monospace; font-size: 1.2em; word-spacing: 0.3em; }
+.details, .uri { color: #444; font-size: 0.8em; }
+.uri { font-family: monospace; word-break: break-all; }
+";
+
+// Render the accounts as a printable HTML sheet, with the QR code of the
+// otpauth URI and the secret key of each account
+pub fn write(accounts: &[(&String, &Account)], created_at: &str) -> Result<String> {
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>cloak paper backup</title>\n");
+    html.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", STYLE));
+    html.push_str(&format!(
+        "<h1>cloak paper backup, {} accounts, {}</h1>\n",
+        accounts.len(),
+        created_at
+    ));
+    html.push_str(
+        "<p>Scan a QR code with <code>cloak add --qr</code> or any authenticator app, or type \
+         the key back without the spaces. The checksum helps to spot typos.</p>\n",
+    );
+    for (name, account) in accounts {
+        let uri = OtpAuth::from_account(name, account)?.to_string();
+        html.push_str("<div class=\"account\">\n");
+        html.push_str(&qr::render_svg(&uri, QR_CODE_SIZE)?);
+        html.push_str("\n<div>\n");
+        html.push_str(&format!("<div class=\"name\">{}</div>\n", escape(name)));
+        html.push_str(&format!(
+            "<div class=\"key\">{}</div>\n",
+            group_key(&account.key)
+        ));
+        html.push_str(&format!(
+            "<div class=\"checksum\">Checksum: {}</div>\n",
+            checksum(&account.key)
+        ));
+        html.push_str(&format!(
+            "<div class=\"details\">{}</div>\n",
+            escape(&details(account))
+        ));
+        html.push_str(&format!("<div class=\"uri\">{}</div>\n", escape(&uri)));
+        html.push_str("</div>\n</div>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+// Split the key in blocks of four characters, easier to read and type back
+pub fn group_key(key: &str) -> String {
+    let key = normalize(key);
+    key.as_bytes()
+        .chunks(4)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// First characters of the base32 SHA-256 digest of the key, ignoring case,
+// spaces and padding
+pub fn checksum(key: &str) -> String {
+    let digest = digest(&SHA256, normalize(key).as_bytes());
+    BASE32_NOPAD.encode(digest.as_ref())[..CHECKSUM_LEN].to_string()
+}
+
+fn normalize(key: &str) -> String {
+    key.chars()
+        .filter(|c| !c.is_whitespace() && *c != '=')
+        .collect::<String>()
+        .to_uppercase()
+}
+
+fn details(account: &Account) -> String {
+    let otp_type = if account.steam {
+        "Steam"
+    } else if account.totp {
+        "TOTP"
+    } else {
+        "HOTP"
+    };
+    let mut details = vec![
+        otp_type.to_string(),
+        account.hash_function.to_string(),
+        format!("{} digits", account.digits.unwrap_or(6)),
+    ];
+    if account.totp {
+        details.push(format!("{}s period", account.period.unwrap_or(30)));
+    } else {
+        details.push(format!("counter {}", account.counter.unwrap_or(0)));
+    }
+    details.join(" · ")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checksum, group_key, write};
+    use crate::account::Account;
+
+    #[test]
+    fn test_group_key() {
+        assert_eq!(
+            group_key("4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6"),
+            "4AZJ FQFI GYM2 KMTO O72I 6FAO Z6ZF WJR6"
+        );
+        assert_eq!(group_key("jbswy3dpehpk3pxp"), "JBSW Y3DP EHPK 3PXP");
+        assert_eq!(
+            group_key("MFZWIYLTMRQXGZCBBI======"),
+            "MFZW IYLT MRQX GZCB BI"
+        );
+    }
+
+    #[test]
+    fn test_checksum_ignores_formatting() {
+        let expected = checksum("JBSWY3DPEHPK3PXP");
+        assert_eq!(expected.len(), 4);
+        assert_eq!(checksum("jbsw y3dp ehpk 3pxp"), expected);
+        assert_ne!(checksum("JBSWY3DPEHPK3PXQ"), expected);
+    }
+
+    #[test]
+    fn test_write() {
+        let account = Account {
+            key: String::from("JBSWY3DPEHPK3PXP"),
+            totp: false,
+            hash_function: String::from("SHA1"),
+            counter: Some(3),
+            digits: None,
+            period: None,
+            t0: None,
+            steam: false,
+            tags: Vec::new(),
+            issuer: None,
+            label: None,
+            notes: None,
+            created_at: None,
+            last_used_at: None,
+            external_key: None,
+        };
+        let name = String::from("<script>");
+        let html = write(&[(&name, &account)], "2022-09-14 08:30:00 UTC").unwrap();
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("JBSW Y3DP EHPK 3PXP"));
+        assert!(html.contains("HOTP · SHA1 · 6 digits · counter 3"));
+        assert_eq!(html.matches("<svg").count(), 1);
+    }
+}
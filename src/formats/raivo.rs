@@ -0,0 +1,172 @@
+use crate::crypto;
+use crate::errors::{Error, Result};
+use crate::formats::{Entry, EntryFields, ImportOptions, Importer};
+use serde_json::Value;
+use std::fs;
+use std::io::{Cursor, Read};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+const FORMAT: &str = "Raivo";
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+// Importer of Raivo OTP exports, a password protected zip archive holding a
+// JSON file. The JSON file can also be imported on its own.
+pub struct Raivo;
+
+impl Importer for Raivo {
+    fn name(&self) -> &'static str {
+        "raivo"
+    }
+
+    fn read(&self, inputs: &[&String], _options: &ImportOptions) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        for input in inputs {
+            let data = fs::read(input)?;
+            entries.extend(read(&data, crypto::read_backup_passphrase)?);
+        }
+        Ok(entries)
+    }
+}
+
+// Raivo writes every value as a string, numbers included
+#[derive(Deserialize)]
+struct RaivoEntry {
+    #[serde(default)]
+    issuer: String,
+    #[serde(default)]
+    account: String,
+    kind: String,
+    secret: String,
+    algorithm: String,
+    digits: Value,
+    timer: Option<Value>,
+    counter: Option<Value>,
+}
+
+// Read the accounts of a Raivo export. The password is only read if the zip
+// archive is encrypted.
+pub fn read<F>(data: &[u8], read_password: F) -> Result<Vec<Entry>>
+where
+    F: FnOnce() -> Result<String>,
+{
+    let json = if data.starts_with(ZIP_MAGIC) {
+        extract_json(data, read_password)?
+    } else {
+        String::from_utf8(data.to_vec())
+            .map_err(|_| Error::InvalidBackup(FORMAT, String::from("not a JSON or zip file")))?
+    };
+    let entries: Vec<RaivoEntry> = serde_json::from_str(&json)?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            let number = |value: &Value| match value {
+                Value::String(value) => value.parse().ok(),
+                value => value.as_u64(),
+            };
+            EntryFields {
+                digits: number(&entry.digits).unwrap_or(0) as usize,
+                period: entry.timer.as_ref().and_then(number),
+                counter: entry.counter.as_ref().and_then(number),
+                issuer: entry.issuer,
+                name: entry.account,
+                otp_type: entry.kind,
+                secret: entry.secret,
+                algorithm: entry.algorithm,
+                tags: Vec::new(),
+            }
+            .into_account()
+        })
+        .collect())
+}
+
+fn extract_json<F>(data: &[u8], read_password: F) -> Result<String>
+where
+    F: FnOnce() -> Result<String>,
+{
+    let mut archive = ZipArchive::new(Cursor::new(data))?;
+    let name = archive
+        .file_names()
+        .find(|name| name.ends_with(".json"))
+        .map(str::to_string)
+        .ok_or_else(|| Error::InvalidBackup(FORMAT, String::from("no JSON file in the archive")))?;
+    let encrypted = matches!(
+        archive.by_name(&name),
+        Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED))
+    );
+
+    let mut json = String::new();
+    if encrypted {
+        let password = read_password()?;
+        archive
+            .by_name_decrypt(&name, password.as_bytes())?
+            .map_err(|_| Error::BackupDecrypt(FORMAT))?
+            .read_to_string(&mut json)
+            .map_err(|_| Error::BackupDecrypt(FORMAT))?;
+    } else {
+        archive.by_name(&name)?.read_to_string(&mut json)?;
+    }
+    Ok(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read;
+    use crate::errors::Error;
+    use std::io::{Cursor, Write};
+    use zip::write::{FileOptions, ZipWriter};
+
+    const EXPORT: &str = r#"[
+        {"pinned": "false", "issuer": "GitHub", "iconValue": "", "counter": "0", "account": "evans",
+         "iconType": "", "algorithm": "SHA1", "kind": "TOTP", "secret": "JBSWY3DPEHPK3PXP",
+         "timer": "30", "digits": "6"},
+        {"pinned": "true", "issuer": "SPDX", "counter": "12", "account": "james",
+         "algorithm": "SHA256", "kind": "HOTP", "secret": "5OM4WOOGPLQEF6UGN3CPEOOLWU",
+         "timer": "30", "digits": "8"}
+    ]"#;
+
+    #[test]
+    fn test_read_zip_export() {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("raivo-otp-export.json", FileOptions::default())
+            .unwrap();
+        zip.write_all(EXPORT.as_bytes()).unwrap();
+        let data = zip.finish().unwrap().into_inner();
+
+        let entries = read(&data, || panic!("the archive is not encrypted")).unwrap();
+        let (name, account) = entries[0].as_ref().unwrap();
+        assert_eq!(name, "GitHub:evans");
+        assert_eq!((account.digits, account.period), (Some(6), Some(30)));
+
+        let (name, account) = entries[1].as_ref().unwrap();
+        assert_eq!(name, "SPDX:james");
+        assert_eq!(account.counter, Some(12));
+        assert_eq!(account.period, None);
+    }
+
+    // Encrypted with WinZip AES as Raivo does, see tests/fixtures/raivo.py
+    #[test]
+    fn test_read_encrypted_zip_export() {
+        let data = include_bytes!("../../tests/fixtures/raivo-aes.zip");
+        let entries = read(data, || Ok(String::from("hunter2"))).unwrap();
+        assert_eq!(entries.len(), 2);
+        let (name, account) = entries[0].as_ref().unwrap();
+        assert_eq!(name, "GitHub:evans");
+        assert_eq!(account.key, "4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6");
+        assert!(account.totp);
+        let (name, account) = entries[1].as_ref().unwrap();
+        assert_eq!(name, "SPDX:james");
+        assert_eq!((account.digits, account.counter), (Some(8), Some(12)));
+
+        assert!(matches!(
+            read(data, || Ok(String::from("hunter3"))),
+            Err(Error::BackupDecrypt(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_json_export() {
+        let entries = read(EXPORT.as_bytes(), || panic!("not encrypted")).unwrap();
+        assert_eq!(entries.len(), 2);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::crypto;
+use crate::errors::{Error, Result};
+use crate::formats::{Entry, EntryFields, ImportOptions, Importer};
+use crate::otp::HashFunction;
+use data_encoding::BASE64;
+use std::collections::BTreeMap;
+use std::fs;
+
+const FORMAT: &str = "2FAS";
+// Key derivation of encrypted backups
+const PBKDF2_ITERATIONS: u32 = 10_000;
+
+// Importer of 2FAS backups (`.2fas` files), optionally encrypted with a password
+pub struct TwoFas;
+
+impl Importer for TwoFas {
+    fn name(&self) -> &'static str {
+        "2fas"
+    }
+
+    fn read(&self, inputs: &[&String], _options: &ImportOptions) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        for input in inputs {
+            let json = fs::read_to_string(input)?;
+            entries.extend(read(&json, crypto::read_backup_passphrase)?);
+        }
+        Ok(entries)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Backup {
+    #[serde(default)]
+    services: Vec<Service>,
+    // Services of encrypted backups, as `ciphertext:salt:nonce` in base64
+    services_encrypted: Option<String>,
+    #[serde(default)]
+    groups: Vec<Group>,
+}
+
+#[derive(Deserialize)]
+struct Group {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Service {
+    name: String,
+    secret: String,
+    #[serde(default)]
+    otp: Otp,
+    group_id: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Otp {
+    account: Option<String>,
+    issuer: Option<String>,
+    digits: Option<usize>,
+    period: Option<u64>,
+    algorithm: Option<String>,
+    counter: Option<u64>,
+    token_type: Option<String>,
+}
+
+// Read the accounts of a 2FAS backup. The password is only read if the
+// backup is encrypted.
+pub fn read<F>(json: &str, read_password: F) -> Result<Vec<Entry>>
+where
+    F: FnOnce() -> Result<String>,
+{
+    let backup: Backup = serde_json::from_str(json)?;
+    let services = match &backup.services_encrypted {
+        Some(encrypted) => {
+            let plaintext = decrypt(encrypted, &read_password()?)?;
+            serde_json::from_slice(&plaintext)?
+        }
+        None => backup.services,
+    };
+
+    let groups: BTreeMap<&str, &str> = backup
+        .groups
+        .iter()
+        .map(|group| (group.id.as_str(), group.name.as_str()))
+        .collect();
+    Ok(services
+        .into_iter()
+        .map(|service| {
+            let tags = service
+                .group_id
+                .as_deref()
+                .and_then(|id| groups.get(id))
+                .map(|group| vec![group.to_string()])
+                .unwrap_or_default();
+            let otp = service.otp;
+            EntryFields {
+                issuer: otp.issuer.unwrap_or(service.name),
+                name: otp.account.unwrap_or_default(),
+                otp_type: otp.token_type.unwrap_or_else(|| String::from("TOTP")),
+                secret: service.secret,
+                algorithm: otp.algorithm.unwrap_or_else(|| String::from("SHA1")),
+                digits: otp.digits.unwrap_or(6),
+                period: otp.period,
+                counter: otp.counter,
+                tags,
+            }
+            .into_account()
+        })
+        .collect())
+}
+
+fn decrypt(encrypted: &str, password: &str) -> Result<Vec<u8>> {
+    let parts = encrypted
+        .split(':')
+        .map(|part| BASE64.decode(part.as_bytes()))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|_| Error::InvalidBackup(FORMAT, String::from("invalid base64 encoding")))?;
+    let (ciphertext, salt, nonce) = match parts.as_slice() {
+        [ciphertext, salt, nonce] => (ciphertext, salt, nonce),
+        _ => {
+            let reason = String::from("malformed encrypted services");
+            return Err(Error::InvalidBackup(FORMAT, reason));
+        }
+    };
+    let key = crypto::pbkdf2_key(&HashFunction::Sha256, password, salt, PBKDF2_ITERATIONS)?;
+    crypto::aes_gcm_open(&key, nonce, ciphertext).ok_or(Error::BackupDecrypt(FORMAT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, PBKDF2_ITERATIONS};
+    use crate::crypto;
+    use crate::errors::Error;
+    use crate::otp::HashFunction;
+    use data_encoding::BASE64;
+
+    const SERVICES: &str = r#"[
+        {"name": "GitHub", "secret": "JBSWY3DPEHPK3PXP", "updatedAt": 0,
+         "otp": {"label": "GitHub:evans", "account": "evans", "issuer": "GitHub", "digits": 6,
+                 "period": 30, "algorithm": "SHA1", "tokenType": "TOTP", "source": "Link"},
+         "order": {"position": 0}, "groupId": "e3a0c8ab"},
+        {"name": "Steam", "secret": "JRZCL47CMXVOQMNPZR2F7J4RGI",
+         "otp": {"account": "gaben", "digits": 5, "period": 30, "algorithm": "SHA1",
+                 "tokenType": "STEAM"}},
+        {"name": "Counter", "secret": "5OM4WOOGPLQEF6UGN3CPEOOLWU",
+         "otp": {"digits": 8, "algorithm": "SHA512", "counter": 7, "tokenType": "HOTP"}}
+    ]"#;
+
+    #[test]
+    fn test_read_plain_backup() {
+        let json = format!(
+            r#"{{"services": {}, "groups": [{{"id": "e3a0c8ab", "name": "Work"}}], "schemaVersion": 4}}"#,
+            SERVICES
+        );
+        let entries = read(&json, || panic!("the backup is not encrypted")).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        let (name, account) = entries[0].as_ref().unwrap();
+        assert_eq!(name, "GitHub:evans");
+        assert_eq!(account.tags, vec![String::from("Work")]);
+
+        let (name, account) = entries[1].as_ref().unwrap();
+        assert_eq!(name, "Steam:gaben");
+        assert!(account.steam);
+
+        let (name, account) = entries[2].as_ref().unwrap();
+        assert_eq!(name, "Counter");
+        assert_eq!((account.totp, account.counter), (false, Some(7)));
+    }
+
+    #[test]
+    fn test_read_encrypted_backup() {
+        let salt = crypto::random_bytes(256).unwrap();
+        let key =
+            crypto::pbkdf2_key(&HashFunction::Sha256, "hunter2", &salt, PBKDF2_ITERATIONS).unwrap();
+        let (nonce, ciphertext) = crypto::aes_gcm_seal(&key, SERVICES.as_bytes()).unwrap();
+        let json = format!(
+            r#"{{"services": [], "servicesEncrypted": "{}:{}:{}", "schemaVersion": 4}}"#,
+            BASE64.encode(&ciphertext),
+            BASE64.encode(&salt),
+            BASE64.encode(&nonce)
+        );
+
+        let entries = read(&json, || Ok(String::from("hunter2"))).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert!(matches!(
+            read(&json, || Ok(String::from("hunter3"))),
+            Err(Error::BackupDecrypt(_))
+        ));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::crypto;
+use crate::errors::{Error, Result};
+use data_encoding::BASE32_NOPAD;
+use std::collections::HashMap;
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+// Prefix of the key of an account whose secret is kept in the keyring,
+// followed by the id of the secret. Secrets are never deleted from the
+// keyring, previous versions of the accounts may still refer to them.
+pub const KEY_PREFIX: &str = "keyring:";
+
+// Secret Service D-Bus API, as implemented by GNOME Keyring and KWallet
+const SERVICE: &str = "org.freedesktop.secrets";
+const SERVICE_PATH: &str = "/org/freedesktop/secrets";
+const SERVICE_INTERFACE: &str = "org.freedesktop.Secret.Service";
+const DEFAULT_COLLECTION_PATH: &str = "/org/freedesktop/secrets/aliases/default";
+const COLLECTION_INTERFACE: &str = "org.freedesktop.Secret.Collection";
+const ITEM_INTERFACE: &str = "org.freedesktop.Secret.Item";
+const PROMPT_INTERFACE: &str = "org.freedesktop.Secret.Prompt";
+// Path returned instead of a prompt when none is needed
+const NO_PROMPT: &str = "/";
+
+// Attribute identifying the secrets of cloak in the keyring
+const APPLICATION: &str = "cloak";
+
+// Secret as exchanged with the service: session, parameters, value and content type
+type Secret = (OwnedObjectPath, Vec<u8>, Vec<u8>, String);
+
+// Session with the Secret Service. Secrets are transferred as plaintext over
+// the session bus, which only the user can connect to.
+struct SecretService {
+    connection: Connection,
+    session: OwnedObjectPath,
+}
+
+impl SecretService {
+    fn connect() -> Result<SecretService> {
+        let connection = Connection::session()?;
+        let (_, session): (OwnedValue, OwnedObjectPath) =
+            proxy(&connection, SERVICE_PATH, SERVICE_INTERFACE)?
+                .call("OpenSession", &("plain", Value::from("")))?;
+        Ok(SecretService {
+            connection,
+            session,
+        })
+    }
+
+    // Unlocked items holding the secret with the id
+    fn search(&self, id: &str) -> Result<Vec<OwnedObjectPath>> {
+        let service = proxy(&self.connection, SERVICE_PATH, SERVICE_INTERFACE)?;
+        let (mut unlocked, locked): (Vec<OwnedObjectPath>, Vec<OwnedObjectPath>) =
+            service.call("SearchItems", &(attributes(id),))?;
+        if !locked.is_empty() {
+            self.unlock(&locked)?;
+            unlocked.extend(locked);
+        }
+        Ok(unlocked)
+    }
+
+    // Unlock items or collections, which may prompt for the password of the keyring
+    fn unlock(&self, objects: &[OwnedObjectPath]) -> Result<()> {
+        let service = proxy(&self.connection, SERVICE_PATH, SERVICE_INTERFACE)?;
+        let (_, prompt): (Vec<OwnedObjectPath>, OwnedObjectPath) =
+            service.call("Unlock", &(objects,))?;
+        self.prompt(&prompt)
+    }
+
+    // Show a prompt of the service and wait for the user to complete it
+    fn prompt(&self, prompt: &ObjectPath) -> Result<()> {
+        if prompt.as_str() == NO_PROMPT {
+            return Ok(());
+        }
+        let prompt = proxy(&self.connection, prompt.as_str(), PROMPT_INTERFACE)?;
+        let mut completed = prompt.receive_signal("Completed")?;
+        prompt.call::<_, _, ()>("Prompt", &("",))?;
+        let message = completed
+            .next()
+            .ok_or_else(|| Error::Keyring(String::from("the prompt was not completed")))?;
+        let (dismissed, _): (bool, OwnedValue) = message.body()?;
+        if dismissed {
+            return Err(Error::Keyring(String::from("the prompt was dismissed")));
+        }
+        Ok(())
+    }
+}
+
+// New id for the secret of an account
+pub fn new_id() -> Result<String> {
+    Ok(BASE32_NOPAD.encode(&crypto::random_bytes(10)?))
+}
+
+// Store the secret of an account in the default collection of the keyring,
+// replacing the secret with the same id
+pub fn store(id: &str, account_name: &str, secret: &str) -> Result<()> {
+    let service = SecretService::connect()?;
+    let collection = ObjectPath::from_static_str_unchecked(DEFAULT_COLLECTION_PATH);
+    service.unlock(&[collection.into()])?;
+
+    let mut properties = HashMap::new();
+    properties.insert(
+        "org.freedesktop.Secret.Item.Label",
+        Value::from(format!("cloak: {}", account_name)),
+    );
+    properties.insert(
+        "org.freedesktop.Secret.Item.Attributes",
+        Value::from(attributes(id)),
+    );
+    let secret: Secret = (
+        service.session.clone(),
+        Vec::new(),
+        secret.as_bytes().to_vec(),
+        String::from("text/plain"),
+    );
+    let (_, prompt): (OwnedObjectPath, OwnedObjectPath) = proxy(
+        &service.connection,
+        DEFAULT_COLLECTION_PATH,
+        COLLECTION_INTERFACE,
+    )?
+    .call("CreateItem", &(properties, secret, true))?;
+    service.prompt(&prompt)
+}
+
+// Secret with the id
+pub fn lookup(id: &str) -> Result<String> {
+    let service = SecretService::connect()?;
+    let item = service
+        .search(id)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::KeyringSecretNotFound(id.to_string()))?;
+    let (_, _, value, _): Secret = proxy(&service.connection, item.as_str(), ITEM_INTERFACE)?
+        .call("GetSecret", &(&service.session,))?;
+    String::from_utf8(value)
+        .map_err(|_| Error::Keyring(format!("the secret {} is not valid UTF-8", id)))
+}
+
+fn attributes(id: &str) -> HashMap<&str, &str> {
+    let mut attributes = HashMap::new();
+    attributes.insert("application", APPLICATION);
+    attributes.insert("id", id);
+    attributes
+}
+
+fn proxy<'a>(connection: &Connection, path: &'a str, interface: &'a str) -> Result<Proxy<'a>> {
+    Ok(Proxy::new(connection, SERVICE, path, interface)?)
+}
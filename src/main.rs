@@ -1,40 +1,148 @@
 extern crate clap;
+extern crate csv;
 extern crate data_encoding;
 extern crate dirs_next;
+extern crate fs2;
+extern crate image;
 extern crate ring;
+extern crate rpassword;
+extern crate rusqlite;
+extern crate scrypt;
 extern crate serde;
+extern crate serde_json;
 extern crate thiserror;
 #[macro_use]
 extern crate serde_derive;
 extern crate toml;
+extern crate zbus;
+extern crate zip;
 #[macro_use]
 extern crate lazy_static;
+extern crate percent_encoding;
+extern crate qrcode;
 
 use crate::account::AccountStore;
-use clap::command;
+use crate::files::LockMode;
+use clap::{command, value_parser, Arg, ArgMatches, Command};
+use std::process;
 
 mod account;
+mod backup;
+mod clipboard;
 mod cmd;
+mod config;
+mod crypto;
 mod dirs;
 mod errors;
+mod files;
+mod formats;
+mod keyring;
 mod otp;
+mod otpauth;
+mod qr;
+mod secret;
+mod storage;
+mod timestamp;
 
 fn main() {
     // Define list of subcommand for the `cloak` app
     let matches = command!()
+        .arg(
+            Arg::new("vault")
+                .long("vault")
+                .takes_value(true)
+                .global(true)
+                .value_name("NAME")
+                .help("Vault holding the accounts, defaults to CLOAK_VAULT or the default_vault setting"),
+        )
         .subcommand(cmd::add::subcommand())
         .subcommand(cmd::view::subcommand())
         .subcommand(cmd::list::subcommand())
         .subcommand(cmd::delete::subcommand())
+        .subcommand(cmd::rename::subcommand())
+        .subcommand(cmd::edit::subcommand())
+        .subcommand(cmd::init::subcommand())
+        .subcommand(cmd::encrypt::subcommand())
+        .subcommand(cmd::decrypt::subcommand())
+        .subcommand(cmd::passwd::subcommand())
+        .subcommand(cmd::validate::subcommand())
+        .subcommand(cmd::export::subcommand())
+        .subcommand(cmd::import::subcommand())
+        .subcommand(cmd::qr::subcommand())
+        .subcommand(cmd::backup::subcommand())
+        .subcommand(cmd::restore::subcommand())
+        .subcommand(cmd::migrate_to_keyring::subcommand())
+        .subcommand(cmd::vault::subcommand())
+        .subcommand(cmd::config::subcommand())
+        .subcommand(
+            Command::new(clipboard::CLEAR_SUBCOMMAND)
+                .hide(true)
+                .arg(Arg::new("seconds").required(true).value_parser(value_parser!(u64))),
+        )
         .get_matches();
 
-    let mut account_store = AccountStore::new().expect("Unable to initialize store");
+    if let Some((clipboard::CLEAR_SUBCOMMAND, sub_m)) = matches.subcommand() {
+        if let Err(err) = clipboard::clear(*sub_m.get_one::<u64>("seconds").unwrap()) {
+            eprintln!("{}", err);
+        }
+        return;
+    }
+
+    // The config file is managed without opening any vault
+    if let Some(("config", sub_m)) = matches.subcommand() {
+        cmd::config::run(sub_m);
+        return;
+    }
+
+    let vault = config::vault(matches.get_one::<String>("vault"));
+    if let Some(("vault", sub_m)) = matches.subcommand() {
+        cmd::vault::run(sub_m, &vault);
+        return;
+    }
+
+    let account_store = dirs::vault_dir(&vault)
+        .and_then(|vault_dir| AccountStore::new(&vault_dir, lock_mode(&matches)));
+    let mut account_store = match account_store {
+        Ok(account_store) => account_store,
+        Err(err) => {
+            eprintln!("Unable to initialize store: {}", err);
+            process::exit(1);
+        }
+    };
 
     match matches.subcommand() {
         Some(("add", sub_m)) => cmd::add::run(sub_m, &mut account_store),
         Some(("view", sub_m)) => cmd::view::run(sub_m, &mut account_store),
-        Some(("list", _)) => cmd::list::run(&mut account_store),
+        Some(("list", sub_m)) => cmd::list::run(sub_m, &mut account_store),
         Some(("delete", sub_m)) => cmd::delete::run(sub_m, &mut account_store),
+        Some(("rename", sub_m)) => cmd::rename::run(sub_m, &mut account_store),
+        Some(("edit", sub_m)) => cmd::edit::run(sub_m, &mut account_store),
+        Some(("init", sub_m)) => cmd::init::run(sub_m, &mut account_store),
+        Some(("encrypt", _)) => cmd::encrypt::run(&mut account_store),
+        Some(("decrypt", _)) => cmd::decrypt::run(&mut account_store),
+        Some(("passwd", _)) => cmd::passwd::run(&mut account_store),
+        Some(("validate", _)) => cmd::validate::run(&mut account_store),
+        Some(("export", sub_m)) => cmd::export::run(sub_m, &mut account_store),
+        Some(("import", sub_m)) => cmd::import::run(sub_m, &mut account_store),
+        Some(("qr", sub_m)) => cmd::qr::run(sub_m, &mut account_store),
+        Some(("backup", sub_m)) => cmd::backup::run(sub_m, &mut account_store),
+        Some(("restore", sub_m)) => cmd::restore::run(sub_m, &mut account_store),
+        Some(("migrate-to-keyring", sub_m)) => {
+            cmd::migrate_to_keyring::run(sub_m, &mut account_store)
+        }
         _ => eprintln!("No subcommand chosen. Add --help | -h to view the subcommands."),
     }
 }
+
+// Commands that do not change the accounts file share it with other readers
+fn lock_mode(matches: &ArgMatches) -> LockMode {
+    match matches.subcommand() {
+        Some(("validate", _)) | Some(("export", _)) | Some(("qr", _)) | Some(("backup", _)) => {
+            LockMode::Shared
+        }
+        Some(("import", sub_m)) | Some(("restore", sub_m)) if sub_m.contains_id("dry-run") => {
+            LockMode::Shared
+        }
+        _ => LockMode::Exclusive,
+    }
+}
@@ -1,8 +1,18 @@
 use crate::errors::{Error, Result};
 use data_encoding::BASE32_NOPAD;
-use ring::hmac;
+use ring::{digest, hmac};
+use std::str::FromStr;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+// Range of digits a code can have, longer codes would overflow when encoding the digest
+pub const MIN_DIGITS: usize = 1;
+pub const MAX_DIGITS: usize = 9;
+
+// Characters and length of the codes of Steam Guard, which are not decimal
+pub const STEAM_ALPHABET: &[u8; 26] = b"23456789BCDFGHJKMNPQRTVWXY";
+pub const STEAM_DIGITS: usize = 5;
+const DECIMAL_ALPHABET: &[u8; 10] = b"0123456789";
+
 // Define the types of hash functions supported
 #[derive(Debug)]
 pub enum HashFunction {
@@ -10,6 +20,25 @@ pub enum HashFunction {
     Sha256,
     Sha384,
     Sha512,
+    Sha512_256,
+}
+
+// Names of the hash functions, as stored in the accounts file
+pub const HASH_FUNCTIONS: [&str; 5] = ["SHA1", "SHA256", "SHA384", "SHA512", "SHA512_256"];
+
+impl FromStr for HashFunction {
+    type Err = Error;
+
+    fn from_str(hash_function: &str) -> Result<HashFunction> {
+        match hash_function {
+            "SHA1" => Ok(HashFunction::Sha1),
+            "SHA256" => Ok(HashFunction::Sha256),
+            "SHA384" => Ok(HashFunction::Sha384),
+            "SHA512" => Ok(HashFunction::Sha512),
+            "SHA512_256" => Ok(HashFunction::Sha512_256),
+            _ => Err(Error::UnknownHashFunction(hash_function.to_owned())),
+        }
+    }
 }
 
 // Structure representing a One Time Password
@@ -18,6 +47,8 @@ pub struct OneTimePassword {
     key: Vec<u8>,
     counter: u64,
     totp: bool,
+    period: u64,
+    t0: u64,
     output_len: usize,
     output_base: Vec<u8>,
     hash_function: HashFunction,
@@ -30,6 +61,8 @@ impl OneTimePassword {
         hash_function: &str,
         counter: Option<u64>,
         output_len: Option<usize>,
+        period: Option<u64>,
+        t0: Option<u64>,
     ) -> Result<OneTimePassword> {
         let decoded_key = BASE32_NOPAD
             .decode(key.as_bytes())
@@ -39,27 +72,46 @@ impl OneTimePassword {
             })?;
         let counter = counter.unwrap_or(0_u64);
         let output_len = output_len.unwrap_or(6);
-        let hash_function = match hash_function {
-            "SHA1" => HashFunction::Sha1,
-            "SHA256" => HashFunction::Sha256,
-            "SHA384" => HashFunction::Sha384,
-            "SHA512" => HashFunction::Sha512,
-            _ => HashFunction::Sha1,
-        };
+        if !(MIN_DIGITS..=MAX_DIGITS).contains(&output_len) {
+            return Err(Error::InvalidDigits(output_len));
+        }
+        let period = period.unwrap_or(30);
+        if period == 0 {
+            return Err(Error::InvalidPeriod);
+        }
+        let t0 = t0.unwrap_or(0);
+        let hash_function = hash_function.parse()?;
         let otp = OneTimePassword {
             key: decoded_key,
             counter,
             totp,
+            period,
+            t0,
             output_len,
-            output_base: "0123456789".to_owned().into_bytes(),
+            output_base: DECIMAL_ALPHABET.to_vec(),
             hash_function,
         };
         Ok(otp)
     }
 
+    // Generate Steam Guard codes, i.e. characters of the Steam alphabet instead of digits
+    pub fn steam(mut self) -> OneTimePassword {
+        self.output_base = STEAM_ALPHABET.to_vec();
+        self
+    }
+
     // Generate a code as defined in [RFC4226](https://tools.ietf.org/html/rfc4226)
     pub fn generate(&self) -> String {
-        let counter = self.get_counter();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        self.generate_at(timestamp)
+    }
+
+    // Generate the code for the given Unix timestamp
+    fn generate_at(&self, timestamp: u64) -> String {
+        let counter = self.get_counter(timestamp);
         let message: [u8; 8] = [
             ((counter >> 56) & 0xff) as u8,
             ((counter >> 48) & 0xff) as u8,
@@ -70,14 +122,16 @@ impl OneTimePassword {
             ((counter >> 8) & 0xff) as u8,
             (counter & 0xff) as u8,
         ];
-        let signing_key = match self.hash_function {
-            HashFunction::Sha1 => hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, &self.key),
-            HashFunction::Sha256 => hmac::Key::new(hmac::HMAC_SHA256, &self.key),
-            HashFunction::Sha384 => hmac::Key::new(hmac::HMAC_SHA384, &self.key),
-            HashFunction::Sha512 => hmac::Key::new(hmac::HMAC_SHA512, &self.key),
+        let digest = match self.hash_function {
+            HashFunction::Sha1 => {
+                hmac_sign(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, &self.key, &message)
+            }
+            HashFunction::Sha256 => hmac_sign(hmac::HMAC_SHA256, &self.key, &message),
+            HashFunction::Sha384 => hmac_sign(hmac::HMAC_SHA384, &self.key, &message),
+            HashFunction::Sha512 => hmac_sign(hmac::HMAC_SHA512, &self.key, &message),
+            HashFunction::Sha512_256 => hmac_sha512_256(&self.key, &message),
         };
-        let digest = hmac::sign(&signing_key, &message);
-        self.encode_digest(digest.as_ref())
+        self.encode_digest(&digest)
     }
 
     fn encode_digest(&self, digest: &[u8]) -> String {
@@ -86,36 +140,70 @@ impl OneTimePassword {
             | ((u32::from(digest[offset + 1]) & 0xff) << 16)
             | ((u32::from(digest[offset + 2]) & 0xff) << 8)
             | (u32::from(digest[offset + 3]) & 0xff);
+        if self.output_base == DECIMAL_ALPHABET {
+            let hotp_code = snum % 10_u32.pow(self.output_len as u32);
+            return format!("{:0width$}", hotp_code, width = self.output_len);
+        }
+        // Other alphabets start with the least significant character
         let base = self.output_base.len() as u32;
-        let hotp_code = snum % base.pow(self.output_len as u32);
-        let code = format!("{:0width$}", hotp_code, width = self.output_len);
+        let mut value = snum;
+        let mut code = String::with_capacity(self.output_len);
+        for _ in 0..self.output_len {
+            code.push(self.output_base[(value % base) as usize] as char);
+            value /= base;
+        }
         code
     }
 
-    // Calculate counter based on whether the OTP is time based or counter based
-    fn get_counter(&self) -> u64 {
+    // Calculate counter based on whether the OTP is time based or counter based.
+    // Time based counters are defined in [RFC6238](https://tools.ietf.org/html/rfc6238)
+    fn get_counter(&self, timestamp: u64) -> u64 {
         if self.totp {
-            let timestamp = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs() as u64;
-            timestamp / 30
+            timestamp.saturating_sub(self.t0) / self.period
         } else {
             self.counter
         }
     }
 }
 
+fn hmac_sign(algorithm: hmac::Algorithm, key: &[u8], message: &[u8]) -> Vec<u8> {
+    let signing_key = hmac::Key::new(algorithm, key);
+    hmac::sign(&signing_key, message).as_ref().to_vec()
+}
+
+// HMAC as defined in [RFC2104](https://tools.ietf.org/html/rfc2104), `ring` only
+// provides HMAC for SHA1, SHA256, SHA384 and SHA512
+fn hmac_sha512_256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let algorithm = &digest::SHA512_256;
+    let mut block = vec![0_u8; algorithm.block_len];
+    if key.len() > algorithm.block_len {
+        let hashed_key = digest::digest(algorithm, key);
+        block[..algorithm.output_len].copy_from_slice(hashed_key.as_ref());
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = digest::Context::new(algorithm);
+    inner.update(&block.iter().map(|b| b ^ 0x36).collect::<Vec<u8>>());
+    inner.update(message);
+    let inner = inner.finish();
+
+    let mut outer = digest::Context::new(algorithm);
+    outer.update(&block.iter().map(|b| b ^ 0x5c).collect::<Vec<u8>>());
+    outer.update(inner.as_ref());
+    outer.finish().as_ref().to_vec()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::OneTimePassword;
+    use super::{OneTimePassword, STEAM_DIGITS};
 
     macro_rules! test_hotp_hash_fn {
         ($func:ident, $hf:expr, $c:tt) => {
             #[test]
             fn $func() {
                 let key = "4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6";
-                let hotp = OneTimePassword::new(key, false, $hf, None, None).unwrap();
+                let hotp = OneTimePassword::new(key, false, $hf, None, None, None, None).unwrap();
                 assert_eq!(hotp.generate(), $c);
             }
         };
@@ -125,11 +213,31 @@ mod tests {
     test_hotp_hash_fn!(test_sha256, "SHA256", "851154");
     test_hotp_hash_fn!(test_sha384, "SHA384", "607946");
     test_hotp_hash_fn!(test_sha512, "SHA512", "377017");
+    test_hotp_hash_fn!(test_sha512_256, "SHA512_256", "171117");
+
+    #[test]
+    fn test_unknown_hash_function() {
+        let key = "4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6";
+        let result = OneTimePassword::new(key, false, "MD5", None, None, None, None);
+        assert_eq!(
+            result.err().map(|err| err.to_string()),
+            Some(String::from("Unsupported hash function 'MD5'"))
+        );
+    }
+
+    #[test]
+    fn test_sha512_256_long_key() {
+        // Keys longer than the block size are hashed first
+        let key = "AAAQEAYEAUDAOCAJBIFQYDIOB4IBCEQTCQKRMFYYDENBWHA5DYPSAIJCEMSCKJRHFAUSUKZMFUXC6MBRGIZTINJWG44DSOR3HQ6T4P2AIFBEGRCFIZDUQSKKJNGE2TSPKBIVEU2UKVLFOWCZLJNVYXK6L5QGCYTDMRSWMZ3INFVGW3DNNZXXA4LSON2HK5TXPB4XU634PV7H7AEBQKBYJBMGQ6EITCULRSGY5D4QSGJJHFEVS2LZRGM2TOOJ3HU7UCQ2FI5EUWTKPKFJVKV2ZLNOV6YLDMVTWS23NN5YXG5LXPF5X274BQOCYPCMLRWH";
+        let hotp =
+            OneTimePassword::new(key, false, "SHA512_256", Some(1), None, None, None).unwrap();
+        assert_eq!(hotp.generate(), "757599");
+    }
 
     #[test]
     fn test_hotp_default() {
         let key = "4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6";
-        let hotp = OneTimePassword::new(key, false, "SHA1", None, None).unwrap();
+        let hotp = OneTimePassword::new(key, false, "SHA1", None, None, None, None).unwrap();
         assert_eq!(hotp.counter, 0);
         let code = hotp.generate();
         assert_eq!(code.len(), 6);
@@ -139,9 +247,94 @@ mod tests {
     #[test]
     fn test_hotp_given_counter_and_length() {
         let key = "4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6";
-        let hotp = OneTimePassword::new(key, false, "SHA1", Some(1), Some(8)).unwrap();
+        let hotp = OneTimePassword::new(key, false, "SHA1", Some(1), Some(8), None, None).unwrap();
         let code = hotp.generate();
         assert_eq!(code.len(), 8);
         assert_eq!(code, "34863669");
     }
+
+    // Test vectors from [RFC6238 Appendix B](https://tools.ietf.org/html/rfc6238#appendix-B).
+    // The RFC has no vectors for SHA384 and SHA512_256, those use a seed in the same pattern.
+    macro_rules! test_totp_rfc6238 {
+        ($func:ident, $hf:expr, $key:expr, [$($codes:expr),*]) => {
+            #[test]
+            fn $func() {
+                let timestamps = [59, 1111111109, 1111111111, 1234567890, 2000000000, 20000000000];
+                let codes = [$($codes),*];
+                let totp = OneTimePassword::new($key, true, $hf, None, Some(8), None, None).unwrap();
+                for (timestamp, code) in timestamps.iter().zip(codes.iter()) {
+                    assert_eq!(totp.generate_at(*timestamp), *code);
+                }
+            }
+        };
+    }
+
+    test_totp_rfc6238!(
+        test_totp_sha1,
+        "SHA1",
+        "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+        ["94287082", "07081804", "14050471", "89005924", "69279037", "65353130"]
+    );
+    test_totp_rfc6238!(
+        test_totp_sha256,
+        "SHA256",
+        "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZA",
+        ["46119246", "68084774", "67062674", "91819424", "90698825", "77737706"]
+    );
+    test_totp_rfc6238!(
+        test_totp_sha384,
+        "SHA384",
+        "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQ",
+        ["12260385", "93607533", "30635264", "29066410", "45679189", "33578931"]
+    );
+    test_totp_rfc6238!(
+        test_totp_sha512_256,
+        "SHA512_256",
+        "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNA",
+        ["88011068", "75065193", "77091046", "24809569", "87282519", "74278334"]
+    );
+    test_totp_rfc6238!(
+        test_totp_sha512,
+        "SHA512",
+        "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNA",
+        ["90693936", "25091201", "99943326", "93441116", "38618901", "47863826"]
+    );
+
+    #[test]
+    fn test_totp_given_period_and_t0() {
+        let key = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+        let totp = OneTimePassword::new(key, true, "SHA1", None, None, Some(60), None).unwrap();
+        assert_eq!(totp.generate_at(59), "755224");
+        assert_eq!(totp.generate_at(1111111109), "360094");
+
+        let totp =
+            OneTimePassword::new(key, true, "SHA1", None, None, None, Some(1000000000)).unwrap();
+        assert_eq!(totp.generate_at(1111111109), "080717");
+        // Timestamps before T0 use the first time step
+        assert_eq!(totp.generate_at(0), totp.generate_at(1000000000));
+    }
+
+    #[test]
+    fn test_invalid_length() {
+        let key = "4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6";
+        assert!(OneTimePassword::new(key, false, "SHA1", None, Some(0), None, None).is_err());
+        assert!(OneTimePassword::new(key, false, "SHA1", None, Some(9), None, None).is_ok());
+        assert!(OneTimePassword::new(key, false, "SHA1", None, Some(10), None, None).is_err());
+    }
+
+    #[test]
+    fn test_totp_zero_period() {
+        let key = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+        assert!(OneTimePassword::new(key, true, "SHA1", None, None, Some(0), None).is_err());
+    }
+
+    #[test]
+    fn test_steam() {
+        let key = "JBSWY3DPEHPK3PXP";
+        let otp = OneTimePassword::new(key, true, "SHA1", None, Some(STEAM_DIGITS), None, None)
+            .unwrap()
+            .steam();
+        assert_eq!(otp.generate_at(0), "VH8YJ");
+        assert_eq!(otp.generate_at(1600000000), "W5GKB");
+    }
 }
@@ -0,0 +1,361 @@
+use crate::account::Account;
+use crate::cmd::add::is_base32_key;
+use crate::errors::{Error, Result};
+use crate::otp::{HashFunction, MAX_DIGITS, MIN_DIGITS, STEAM_DIGITS};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::fmt;
+use std::str::FromStr;
+
+const SCHEME: &str = "otpauth://";
+
+// Characters that are percent-encoded in labels and parameter values, i.e.
+// everything apart from the unreserved characters of RFC3986
+const ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+// An account in the [Key URI format](https://github.com/google/google-authenticator/wiki/Key-Uri-Format)
+// used by `otpauth://` provisioning URIs
+#[derive(Debug, PartialEq)]
+pub struct OtpAuth {
+    pub totp: bool,
+    pub label: String,
+    pub issuer: Option<String>,
+    pub secret: String,
+    pub algorithm: String,
+    pub digits: usize,
+    pub period: Option<u64>,
+    pub counter: Option<u64>,
+    // Steam Guard codes, set with the `encoder=steam` parameter used by KeePassXC
+    pub steam: bool,
+}
+
+impl OtpAuth {
+    // Build the URI for a stored account. Unless the account has an issuer,
+    // an issuer prefix in the account name, e.g. `GitHub:evans`, is used.
+    // URIs have no parameter for the start of the time steps, an account
+    // counting them from another time than the Unix epoch is refused rather
+    // than giving different codes once imported.
+    pub fn from_account(account_name: &str, account: &Account) -> Result<OtpAuth> {
+        match account.t0 {
+            Some(t0) if t0 != 0 && account.totp => {
+                return Err(Error::UnsupportedEpoch(account_name.to_string(), t0))
+            }
+            _ => {}
+        }
+        let (issuer, label) = account.issuer_and_label(account_name);
+        Ok(OtpAuth {
+            totp: account.totp,
+            label,
+            issuer,
+            secret: account.key.clone(),
+            algorithm: account.hash_function.clone(),
+            digits: account
+                .digits
+                .unwrap_or(if account.steam { STEAM_DIGITS } else { 6 }),
+            period: if account.totp {
+                Some(account.period.unwrap_or(30))
+            } else {
+                None
+            },
+            counter: if account.totp {
+                None
+            } else {
+                Some(account.counter.unwrap_or(0))
+            },
+            steam: account.steam,
+        })
+    }
+
+    // Name under which the account is stored, prefixed with the issuer if any
+    pub fn account_name(&self) -> String {
+        join_label(self.issuer.as_deref(), &self.label)
+    }
+
+    pub fn into_account(self) -> Account {
+        Account {
+            key: self.secret,
+            totp: self.totp,
+            hash_function: self.algorithm,
+            counter: if self.totp {
+                None
+            } else {
+                Some(self.counter.unwrap_or(0))
+            },
+            digits: Some(self.digits),
+            period: if self.totp { self.period } else { None },
+            t0: None,
+            steam: self.steam,
+            tags: Vec::new(),
+            issuer: self.issuer,
+            label: Some(self.label).filter(|label| !label.is_empty()),
+            notes: None,
+            created_at: None,
+            last_used_at: None,
+            external_key: None,
+        }
+    }
+}
+
+impl FromStr for OtpAuth {
+    type Err = Error;
+
+    fn from_str(uri: &str) -> Result<OtpAuth> {
+        let invalid = |reason: &str| Error::InvalidUri(reason.to_owned());
+
+        // Compared by bytes, a non-ASCII character may straddle the end of the scheme
+        if !matches!(uri.get(..SCHEME.len()), Some(scheme) if scheme.eq_ignore_ascii_case(SCHEME)) {
+            return Err(invalid("the URI does not start with otpauth://"));
+        }
+        let rest = &uri[SCHEME.len()..];
+        let (otp_type, rest) = rest
+            .split_once('/')
+            .ok_or_else(|| invalid("missing label"))?;
+        let totp = match otp_type.to_lowercase().as_str() {
+            "totp" => true,
+            "hotp" => false,
+            _ => return Err(invalid("the OTP type must be totp or hotp")),
+        };
+        let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let (label_issuer, label) = split_label(&decode(label)?);
+        if label.is_empty() {
+            return Err(invalid("missing label"));
+        }
+
+        let mut secret = None;
+        let mut issuer = None;
+        let mut algorithm = String::from("SHA1");
+        let mut digits = None;
+        let mut period = None;
+        let mut counter = None;
+        let mut steam = false;
+        for param in query.split('&').filter(|param| !param.is_empty()) {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = decode(&value.replace('+', " "))?;
+            match name.to_lowercase().as_str() {
+                "secret" => {
+                    let value = value.replace(' ', "");
+                    let value = is_base32_key(value.trim_end_matches('='))
+                        .map_err(|_| invalid("the secret is not a valid base32 encoding"))?;
+                    secret = Some(value);
+                }
+                "issuer" => issuer = Some(value),
+                "algorithm" => {
+                    let value = value.to_uppercase();
+                    value.parse::<HashFunction>()?;
+                    algorithm = value;
+                }
+                "digits" => {
+                    let value = value
+                        .parse()
+                        .map_err(|_| invalid("digits must be a number"))?;
+                    if !(MIN_DIGITS..=MAX_DIGITS).contains(&value) {
+                        return Err(Error::InvalidDigits(value));
+                    }
+                    digits = Some(value);
+                }
+                "period" => {
+                    let value = value
+                        .parse()
+                        .map_err(|_| invalid("period must be a number"))?;
+                    if value == 0 {
+                        return Err(Error::InvalidPeriod);
+                    }
+                    period = Some(value);
+                }
+                "counter" => {
+                    counter = Some(
+                        value
+                            .parse()
+                            .map_err(|_| invalid("counter must be a number"))?,
+                    )
+                }
+                "encoder" => steam = value.eq_ignore_ascii_case("steam"),
+                // Unknown parameters, e.g. `image`, are ignored
+                _ => (),
+            }
+        }
+
+        Ok(OtpAuth {
+            totp,
+            label,
+            // The issuer parameter takes precedence over the label prefix
+            issuer: issuer.or(label_issuer).filter(|issuer| !issuer.is_empty()),
+            secret: secret.ok_or_else(|| invalid("missing secret"))?,
+            algorithm,
+            digits: digits.unwrap_or(if steam { STEAM_DIGITS } else { 6 }),
+            period: if totp { period } else { None },
+            counter: if totp { None } else { counter },
+            steam,
+        })
+    }
+}
+
+impl fmt::Display for OtpAuth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let otp_type = if self.totp { "totp" } else { "hotp" };
+        write!(f, "{}{}/", SCHEME, otp_type)?;
+        if let Some(issuer) = &self.issuer {
+            write!(f, "{}:", encode(issuer))?;
+        }
+        write!(f, "{}?secret={}", encode(&self.label), self.secret)?;
+        if let Some(issuer) = &self.issuer {
+            write!(f, "&issuer={}", encode(issuer))?;
+        }
+        write!(f, "&algorithm={}&digits={}", self.algorithm, self.digits)?;
+        if self.steam {
+            write!(f, "&encoder=steam")?;
+        }
+        if let Some(period) = self.period {
+            write!(f, "&period={}", period)?;
+        }
+        if let Some(counter) = self.counter {
+            write!(f, "&counter={}", counter)?;
+        }
+        Ok(())
+    }
+}
+
+// Join an issuer and an account name into a label, e.g. `GitHub:evans`
+pub fn join_label(issuer: Option<&str>, name: &str) -> String {
+    match issuer {
+        Some(issuer) => format!("{}:{}", issuer, name),
+        None => name.to_owned(),
+    }
+}
+
+// Split a label into its issuer prefix and account name
+pub fn split_label(label: &str) -> (Option<String>, String) {
+    match label.split_once(':') {
+        Some((issuer, name)) => (Some(issuer.trim().to_owned()), name.trim().to_owned()),
+        None => (None, label.trim().to_owned()),
+    }
+}
+
+fn encode(value: &str) -> String {
+    utf8_percent_encode(value, ENCODE_SET).to_string()
+}
+
+fn decode(value: &str) -> Result<String> {
+    percent_decode_str(value)
+        .decode_utf8()
+        .map(|value| value.into_owned())
+        .map_err(|_| Error::InvalidUri(String::from("invalid percent-encoding")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OtpAuth;
+    use crate::account::Account;
+
+    #[test]
+    fn test_parse_totp_uri() {
+        let uri = "otpauth://totp/ACME%20Co:john.doe@email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=ACME%20Co&algorithm=SHA256&digits=8&period=60";
+        let otpauth: OtpAuth = uri.parse().unwrap();
+        assert_eq!(
+            otpauth,
+            OtpAuth {
+                totp: true,
+                label: String::from("john.doe@email.com"),
+                issuer: Some(String::from("ACME Co")),
+                secret: String::from("HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ"),
+                algorithm: String::from("SHA256"),
+                digits: 8,
+                period: Some(60),
+                counter: None,
+                steam: false,
+            }
+        );
+        assert_eq!(otpauth.account_name(), "ACME Co:john.doe@email.com");
+
+        let account = otpauth.into_account();
+        assert_eq!(account.issuer.as_deref(), Some("ACME Co"));
+        assert_eq!(account.label.as_deref(), Some("john.doe@email.com"));
+    }
+
+    #[test]
+    fn test_parse_hotp_uri_defaults() {
+        let uri = "otpauth://hotp/github?secret=4azjfqfigym2kmtoo72i6faoz6zfwjr6&counter=5";
+        let account = uri.parse::<OtpAuth>().unwrap().into_account();
+        assert_eq!(account.key, "4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6");
+        assert!(!account.totp);
+        assert_eq!(account.hash_function, "SHA1");
+        assert_eq!(account.counter, Some(5));
+        assert_eq!(account.digits, Some(6));
+        assert_eq!(account.period, None);
+    }
+
+    #[test]
+    fn test_parse_invalid_uris() {
+        let invalid_uris = [
+            "https://totp/github?secret=4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6",
+            "otpauth://motp/github?secret=4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6",
+            "otpauth://totp/github",
+            "otpauth://totp/?secret=4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6",
+            "otpauth://totp/github?secret=12123EQ",
+            "otpauth://totp/github?secret=4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6&algorithm=MD5",
+            "otpauth://totp/github?secret=4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6&period=0",
+            "otpauth://totp/github?secret=4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6&digits=six",
+            "otpauth://totp/github?secret=4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6&digits=10",
+            "otpauth:/éabc",
+            "otpauth",
+        ];
+        for uri in invalid_uris.iter() {
+            assert!(uri.parse::<OtpAuth>().is_err(), "{}", uri);
+        }
+    }
+
+    #[test]
+    fn test_render_uri_encodes_label() {
+        let account = Account {
+            key: String::from("4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6"),
+            totp: true,
+            hash_function: String::from("SHA1"),
+            counter: None,
+            digits: None,
+            period: None,
+            t0: None,
+            steam: false,
+            tags: Vec::new(),
+            issuer: None,
+            label: None,
+            notes: None,
+            created_at: None,
+            last_used_at: None,
+            external_key: None,
+        };
+        let otpauth = OtpAuth::from_account("ACME Co:john doe/work", &account).unwrap();
+        assert_eq!(
+            otpauth.to_string(),
+            "otpauth://totp/ACME%20Co:john%20doe%2Fwork?secret=4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6&issuer=ACME%20Co&algorithm=SHA1&digits=6&period=30"
+        );
+
+        // The start of the time steps can't be given in the URI
+        let account = Account {
+            t0: Some(0),
+            ..account
+        };
+        assert!(OtpAuth::from_account("github", &account).is_ok());
+        let account = Account {
+            t0: Some(1_000_000),
+            ..account
+        };
+        assert!(OtpAuth::from_account("github", &account).is_err());
+    }
+
+    #[test]
+    fn test_uri_roundtrip() {
+        let uris = [
+            "otpauth://totp/ACME%20Co:john.doe%40email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=ACME%20Co&algorithm=SHA512&digits=8&period=60",
+            "otpauth://hotp/github?secret=4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6&algorithm=SHA1&digits=6&counter=42",
+            "otpauth://totp/Steam:gaben?secret=JBSWY3DPEHPK3PXP&issuer=Steam&algorithm=SHA1&digits=5&encoder=steam&period=30",
+        ];
+        for uri in uris.iter() {
+            let otpauth: OtpAuth = uri.parse().unwrap();
+            assert_eq!(otpauth.to_string(), *uri);
+            assert_eq!(otpauth.to_string().parse::<OtpAuth>().unwrap(), otpauth);
+        }
+    }
+}
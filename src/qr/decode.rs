@@ -0,0 +1,327 @@
+use crate::qr::reed_solomon;
+use crate::qr::version::{self, BlockLayout, EcLevel, MAX_VERSION};
+use crate::qr::BitMatrix;
+
+// Mask applied to the format information so that it's never all zeros
+const FORMAT_MASK: u16 = 0x5412;
+const FORMAT_GENERATOR: u32 = 0x537;
+const VERSION_GENERATOR: u32 = 0x1f25;
+// Codewords at a Hamming distance of 3 or less can be corrected
+const MAX_BIT_ERRORS: u32 = 3;
+
+const ALPHANUMERIC_CHARS: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+// Remainder of the polynomial division used by the BCH codes of the format
+// and version information
+fn bch_remainder(value: u32, generator: u32) -> u32 {
+    let generator_degree = 31 - generator.leading_zeros();
+    let mut remainder = value << generator_degree;
+    while remainder != 0 && 31 - remainder.leading_zeros() >= generator_degree {
+        remainder ^= generator << (31 - remainder.leading_zeros() - generator_degree);
+    }
+    remainder
+}
+
+fn format_codeword(data: u16) -> u16 {
+    let data = data as u32;
+    ((data << 10 | bch_remainder(data, FORMAT_GENERATOR)) as u16) ^ FORMAT_MASK
+}
+
+fn version_codeword(version: usize) -> u32 {
+    let version = version as u32;
+    version << 12 | bch_remainder(version, VERSION_GENERATOR)
+}
+
+// Read the bits at the given (x, y) positions, the first being the most significant
+fn read_bits(grid: &BitMatrix, positions: impl Iterator<Item = (usize, usize)>) -> u32 {
+    positions.fold(0, |bits, (x, y)| bits << 1 | grid.get(x, y) as u32)
+}
+
+// Read the error correction level and the data mask from either copy of the
+// format information
+fn read_format(grid: &BitMatrix) -> Option<(EcLevel, u8)> {
+    let dimension = grid.width;
+    let first_copy = (0..6)
+        .map(|x| (x, 8))
+        .chain([(7, 8), (8, 8), (8, 7)].iter().cloned())
+        .chain((0..6).rev().map(|y| (8, y)));
+    let second_copy = (dimension - 7..dimension)
+        .rev()
+        .map(|y| (8, y))
+        .chain((dimension - 8..dimension).map(|x| (x, 8)));
+    let copies = [read_bits(grid, first_copy), read_bits(grid, second_copy)];
+
+    let (distance, data) = (0..32_u16)
+        .map(|data| {
+            let codeword = format_codeword(data) as u32;
+            let distance = copies
+                .iter()
+                .map(|bits| (bits ^ codeword).count_ones())
+                .min()
+                .unwrap();
+            (distance, data)
+        })
+        .min()?;
+    if distance > MAX_BIT_ERRORS {
+        return None;
+    }
+    Some((EcLevel::from_bits(data >> 3), (data & 0b111) as u8))
+}
+
+// Read the version from the version information of codes of version 7 and above
+pub fn read_version(grid: &BitMatrix) -> Option<usize> {
+    let dimension = grid.width;
+    if dimension < version::dimension(7) {
+        return None;
+    }
+    let first_copy = (0..6)
+        .rev()
+        .flat_map(|y| (dimension - 11..dimension - 8).rev().map(move |x| (x, y)));
+    let second_copy = (0..6)
+        .rev()
+        .flat_map(|x| (dimension - 11..dimension - 8).rev().map(move |y| (x, y)));
+    let copies = [read_bits(grid, first_copy), read_bits(grid, second_copy)];
+
+    let (distance, version) = (7..=MAX_VERSION)
+        .map(|version| {
+            let codeword = version_codeword(version);
+            let distance = copies
+                .iter()
+                .map(|bits| (bits ^ codeword).count_ones())
+                .min()
+                .unwrap();
+            (distance, version)
+        })
+        .min()?;
+    if distance > MAX_BIT_ERRORS {
+        return None;
+    }
+    Some(version)
+}
+
+// Whether the module at the given row and column is inverted by the data mask
+fn is_masked(mask: u8, row: usize, column: usize) -> bool {
+    let (i, j) = (row, column);
+    match mask {
+        0 => (i + j) % 2 == 0,
+        1 => i % 2 == 0,
+        2 => j % 3 == 0,
+        3 => (i + j) % 3 == 0,
+        4 => (i / 2 + j / 3) % 2 == 0,
+        5 => (i * j) % 2 + (i * j) % 3 == 0,
+        6 => ((i * j) % 2 + (i * j) % 3) % 2 == 0,
+        _ => ((i + j) % 2 + (i * j) % 3) % 2 == 0,
+    }
+}
+
+// Read the codewords in the zigzag order starting from the bottom right corner
+fn read_codewords(grid: &BitMatrix, version: usize, mask: u8) -> Vec<u8> {
+    let dimension = grid.width;
+    let function_pattern = version::function_pattern(version);
+    let num_codewords = version::raw_data_modules(version) / 8;
+
+    let mut codewords = Vec::with_capacity(num_codewords);
+    let mut byte = 0_u8;
+    let mut bits_read = 0;
+    let mut reading_up = true;
+    let mut right = dimension - 1;
+    while right > 0 {
+        // Skip the vertical timing pattern
+        if right == 6 {
+            right -= 1;
+        }
+        for count in 0..dimension {
+            let y = if reading_up {
+                dimension - 1 - count
+            } else {
+                count
+            };
+            for x in [right, right - 1].iter().cloned() {
+                if function_pattern.get(x, y) {
+                    continue;
+                }
+                byte = byte << 1 | (grid.get(x, y) ^ is_masked(mask, y, x)) as u8;
+                bits_read += 1;
+                if bits_read == 8 {
+                    codewords.push(byte);
+                    byte = 0;
+                    bits_read = 0;
+                }
+            }
+        }
+        reading_up = !reading_up;
+        right = right.saturating_sub(2);
+    }
+    codewords.truncate(num_codewords);
+    codewords
+}
+
+// Split the interleaved codewords into blocks, correct the errors of each
+// block and return the data codewords
+fn correct_codewords(codewords: &[u8], layout: &BlockLayout) -> Option<Vec<u8>> {
+    let mut blocks: Vec<Vec<u8>> = (0..layout.num_blocks)
+        .map(|block| Vec::with_capacity(layout.data_len(block) + layout.ecc_len))
+        .collect();
+    let mut codewords = codewords.iter();
+    for i in 0..=layout.short_data_len {
+        for (index, block) in blocks.iter_mut().enumerate() {
+            if i < layout.data_len(index) {
+                block.push(*codewords.next()?);
+            }
+        }
+    }
+    for _ in 0..layout.ecc_len {
+        for block in blocks.iter_mut() {
+            block.push(*codewords.next()?);
+        }
+    }
+
+    let mut data = Vec::new();
+    for (index, block) in blocks.iter_mut().enumerate() {
+        if !reed_solomon::correct(block, layout.ecc_len) {
+            return None;
+        }
+        data.extend_from_slice(&block[..layout.data_len(index)]);
+    }
+    Some(data)
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn available(&self) -> usize {
+        self.data.len() * 8 - self.position
+    }
+
+    fn read(&mut self, count: usize) -> Option<u32> {
+        if count > self.available() {
+            return None;
+        }
+        let mut value = 0;
+        for _ in 0..count {
+            let bit = self.data[self.position / 8] >> (7 - self.position % 8) & 1;
+            value = value << 1 | bit as u32;
+            self.position += 1;
+        }
+        Some(value)
+    }
+}
+
+// Number of bits of the character count indicator of a segment
+fn char_count_bits(mode: u32, version: usize) -> usize {
+    let index = match version {
+        1..=9 => 0,
+        10..=26 => 1,
+        _ => 2,
+    };
+    match mode {
+        0b0001 => [10, 12, 14][index],
+        0b0010 => [9, 11, 13][index],
+        _ => [8, 16, 16][index],
+    }
+}
+
+// Decode the segments of the data bit stream
+fn decode_segments(data: &[u8], version: usize) -> Option<Vec<u8>> {
+    let mut reader = BitReader { data, position: 0 };
+    let mut content = Vec::new();
+    while reader.available() >= 4 {
+        let mode = reader.read(4)?;
+        match mode {
+            // Terminator
+            0b0000 => break,
+            // Numeric
+            0b0001 => {
+                let mut count = reader.read(char_count_bits(mode, version))?;
+                while count > 0 {
+                    let (bits, digits) = match count {
+                        1 => (4, 1),
+                        2 => (7, 2),
+                        _ => (10, 3),
+                    };
+                    let value = reader.read(bits)?;
+                    if value >= 10_u32.pow(digits) {
+                        return None;
+                    }
+                    content.extend(format!("{:0width$}", value, width = digits as usize).bytes());
+                    count -= digits;
+                }
+            }
+            // Alphanumeric
+            0b0010 => {
+                let mut count = reader.read(char_count_bits(mode, version))?;
+                while count >= 2 {
+                    let value = reader.read(11)? as usize;
+                    content.push(*ALPHANUMERIC_CHARS.get(value / 45)?);
+                    content.push(*ALPHANUMERIC_CHARS.get(value % 45)?);
+                    count -= 2;
+                }
+                if count == 1 {
+                    content.push(*ALPHANUMERIC_CHARS.get(reader.read(6)? as usize)?);
+                }
+            }
+            // Byte
+            0b0100 => {
+                let count = reader.read(char_count_bits(mode, version))?;
+                for _ in 0..count {
+                    content.push(reader.read(8)? as u8);
+                }
+            }
+            // Extended Channel Interpretation, the content is assumed to be UTF-8
+            0b0111 => {
+                let first = reader.read(8)?;
+                if first & 0x80 == 0x80 {
+                    let extra = if first & 0xc0 == 0x80 { 8 } else { 16 };
+                    reader.read(extra)?;
+                }
+            }
+            // Structured append, the parts of the content are concatenated
+            0b0011 => {
+                reader.read(16)?;
+            }
+            // FNC1 in first or second position
+            0b0101 => (),
+            0b1001 => {
+                reader.read(8)?;
+            }
+            // Kanji and unknown modes aren't supported
+            _ => return None,
+        }
+    }
+    Some(content)
+}
+
+// Decode the content of a QR code from its modules
+pub fn decode_grid(grid: &BitMatrix) -> Option<Vec<u8>> {
+    let version = (grid.width - 17) / 4;
+    let (ec_level, mask) = read_format(grid)?;
+    let codewords = read_codewords(grid, version, mask);
+    let data = correct_codewords(&codewords, &BlockLayout::new(version, ec_level))?;
+    decode_segments(&data, version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_segments, format_codeword, version_codeword};
+
+    #[test]
+    fn test_format_and_version_codewords() {
+        // Values from ISO/IEC 18004 Annex C and D
+        assert_eq!(format_codeword(0b00101), 0b100000011001110);
+        assert_eq!(version_codeword(7), 0x07c94);
+        assert_eq!(version_codeword(40), 0x28c69);
+    }
+
+    #[test]
+    fn test_decode_segments() {
+        // Numeric "01234567" followed by the terminator
+        let data = [0x10, 0x20, 0x0c, 0x56, 0x61, 0x80, 0xec, 0x11];
+        assert_eq!(decode_segments(&data, 1), Some(b"01234567".to_vec()));
+        // Alphanumeric "AC-42"
+        let data = [0x20, 0x29, 0xce, 0xe7, 0x21, 0x00, 0x00];
+        assert_eq!(decode_segments(&data, 1), Some(b"AC-42".to_vec()));
+    }
+}
@@ -0,0 +1,335 @@
+use crate::qr::version::{dimension, MAX_VERSION, MIN_VERSION};
+use crate::qr::BitMatrix;
+use image::GrayImage;
+
+// Center of a finder pattern, one of the three squares in the corners of a QR code
+#[derive(Clone, Debug)]
+pub struct FinderPattern {
+    pub x: f32,
+    pub y: f32,
+    pub module_size: f32,
+    count: usize,
+}
+
+impl FinderPattern {
+    fn distance(&self, other: &FinderPattern) -> f32 {
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
+    }
+}
+
+// Convert an image to dark and light pixels, using Otsu's method to choose the threshold
+pub fn binarize(image: &GrayImage) -> BitMatrix {
+    let mut histogram = [0_u64; 256];
+    for pixel in image.pixels() {
+        histogram[pixel.0[0] as usize] += 1;
+    }
+    let total = image.pixels().len() as f64;
+    let sum: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(value, &count)| value as f64 * count as f64)
+        .sum();
+
+    let mut threshold = 127;
+    let mut best_variance = 0.0;
+    let mut background = 0.0;
+    let mut background_sum = 0.0;
+    for (value, &count) in histogram.iter().enumerate() {
+        background += count as f64;
+        if background == 0.0 {
+            continue;
+        }
+        let foreground = total - background;
+        if foreground == 0.0 {
+            break;
+        }
+        background_sum += value as f64 * count as f64;
+        let background_mean = background_sum / background;
+        let foreground_mean = (sum - background_sum) / foreground;
+        let variance = background * foreground * (background_mean - foreground_mean).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            threshold = value;
+        }
+    }
+
+    let (width, height) = image.dimensions();
+    let mut matrix = BitMatrix::new(width as usize, height as usize);
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if (pixel.0[0] as usize) <= threshold {
+            matrix.set(x as usize, y as usize);
+        }
+    }
+    matrix
+}
+
+// Check whether the runs of dark and light pixels have the 1:1:3:1:1 ratio of a finder pattern
+fn is_finder_ratio(counts: &[usize; 5]) -> bool {
+    let total: usize = counts.iter().sum();
+    if total < 7 || counts.contains(&0) {
+        return false;
+    }
+    let module_size = total as f32 / 7.0;
+    let max_variance = module_size / 2.0;
+    let ratios = [1.0, 1.0, 3.0, 1.0, 1.0];
+    counts
+        .iter()
+        .zip(ratios.iter())
+        .all(|(&count, &ratio)| (ratio * module_size - count as f32).abs() < ratio * max_variance)
+}
+
+// Center of the run of pixels ending just before `end`
+fn center_from_end(counts: &[usize; 5], end: usize) -> f32 {
+    end as f32 - counts[4] as f32 - counts[3] as f32 - counts[2] as f32 / 2.0
+}
+
+// Look for a finder pattern along a line of pixels, starting from its center.
+// Returns the refined center position along the line.
+fn cross_check<F: Fn(usize) -> bool>(
+    is_dark: F,
+    len: usize,
+    start: usize,
+    max_count: usize,
+    original_total: usize,
+) -> Option<f32> {
+    let mut counts = [0_usize; 5];
+
+    let mut i = start as isize;
+    while i >= 0 && is_dark(i as usize) {
+        counts[2] += 1;
+        i -= 1;
+    }
+    while i >= 0 && !is_dark(i as usize) && counts[1] <= max_count {
+        counts[1] += 1;
+        i -= 1;
+    }
+    if i < 0 || counts[1] > max_count {
+        return None;
+    }
+    while i >= 0 && is_dark(i as usize) && counts[0] <= max_count {
+        counts[0] += 1;
+        i -= 1;
+    }
+    if counts[0] > max_count {
+        return None;
+    }
+
+    let mut i = start + 1;
+    while i < len && is_dark(i) {
+        counts[2] += 1;
+        i += 1;
+    }
+    while i < len && !is_dark(i) && counts[3] <= max_count {
+        counts[3] += 1;
+        i += 1;
+    }
+    if i == len || counts[3] > max_count {
+        return None;
+    }
+    while i < len && is_dark(i) && counts[4] <= max_count {
+        counts[4] += 1;
+        i += 1;
+    }
+    if counts[4] > max_count {
+        return None;
+    }
+
+    // The pattern should have about the same size in both directions
+    let total: usize = counts.iter().sum();
+    if 5 * (total as isize - original_total as isize).unsigned_abs() >= 2 * original_total {
+        return None;
+    }
+    if is_finder_ratio(&counts) {
+        Some(center_from_end(&counts, i))
+    } else {
+        None
+    }
+}
+
+// Confirm a possible finder pattern found on a row and record its center
+fn handle_possible_center(
+    image: &BitMatrix,
+    counts: &[usize; 5],
+    end: usize,
+    y: usize,
+    finders: &mut Vec<FinderPattern>,
+) -> bool {
+    let total: usize = counts.iter().sum();
+    let x = center_from_end(counts, end) as usize;
+    let y = match cross_check(|i| image.get(x, i), image.height, y, counts[2] * 2, total) {
+        Some(y) => y,
+        None => return false,
+    };
+    let row = y as usize;
+    let x = match cross_check(|i| image.get(i, row), image.width, x, counts[2] * 2, total) {
+        Some(x) => x,
+        None => return false,
+    };
+    let module_size = total as f32 / 7.0;
+
+    for finder in finders.iter_mut() {
+        if (finder.x - x).abs() <= module_size
+            && (finder.y - y).abs() <= module_size
+            && (finder.module_size - module_size).abs() <= 1.0_f32.max(finder.module_size / 4.0)
+        {
+            let count = finder.count as f32;
+            finder.x = (finder.x * count + x) / (count + 1.0);
+            finder.y = (finder.y * count + y) / (count + 1.0);
+            finder.module_size = (finder.module_size * count + module_size) / (count + 1.0);
+            finder.count += 1;
+            return true;
+        }
+    }
+    finders.push(FinderPattern {
+        x,
+        y,
+        module_size,
+        count: 1,
+    });
+    true
+}
+
+// Find the finder patterns by scanning every row for runs of dark and light
+// pixels in a 1:1:3:1:1 ratio
+pub fn find_finder_patterns(image: &BitMatrix) -> Vec<FinderPattern> {
+    let mut finders = Vec::new();
+    for y in 0..image.height {
+        let mut counts = [0_usize; 5];
+        let mut state = 0;
+        for x in 0..image.width {
+            if image.get(x, y) {
+                if state % 2 == 1 {
+                    state += 1;
+                }
+                counts[state] += 1;
+            } else if state % 2 == 1 {
+                counts[state] += 1;
+            } else if state == 0 && counts[0] == 0 {
+                // Skip the light pixels before the first dark pixel
+            } else if state == 4 {
+                if is_finder_ratio(&counts)
+                    && handle_possible_center(image, &counts, x, y, &mut finders)
+                {
+                    counts = [0; 5];
+                    state = 0;
+                } else {
+                    counts = [counts[2], counts[3], counts[4], 1, 0];
+                    state = 3;
+                }
+            } else {
+                state += 1;
+                counts[state] += 1;
+            }
+        }
+        if state == 4 && is_finder_ratio(&counts) {
+            handle_possible_center(image, &counts, image.width, y, &mut finders);
+        }
+    }
+    // Finder patterns are seen on several rows, discard one-off matches
+    finders.retain(|finder| finder.count >= 2);
+    finders
+}
+
+// Triples of finder patterns that may belong to the same QR code, as indexes
+// of the top left, top right and bottom left patterns, most likely first
+pub fn candidate_triples(finders: &[FinderPattern]) -> Vec<[usize; 3]> {
+    let mut triples = Vec::new();
+    for i in 0..finders.len() {
+        for j in i + 1..finders.len() {
+            for k in j + 1..finders.len() {
+                if let Some(triple) = score_triple(finders, [i, j, k]) {
+                    triples.push(triple);
+                }
+            }
+        }
+    }
+    triples.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap());
+    triples.into_iter().map(|(_, triple)| triple).collect()
+}
+
+// Check the finder patterns form a right isosceles triangle, returning a score
+// (lower is better) and the patterns ordered as top left, top right and bottom left
+fn score_triple(finders: &[FinderPattern], triple: [usize; 3]) -> Option<(f32, [usize; 3])> {
+    let [a, b, c] = triple.map(|i| &finders[i]);
+    let module_sizes = [a.module_size, b.module_size, c.module_size];
+    let min_module = module_sizes.iter().cloned().fold(f32::MAX, f32::min);
+    let max_module = module_sizes.iter().cloned().fold(0.0, f32::max);
+    if max_module > min_module * 1.5 {
+        return None;
+    }
+
+    // The top left pattern is the one opposite the longest side
+    let sides = [b.distance(c), a.distance(c), a.distance(b)];
+    let corner = (0..3)
+        .max_by(|&i, &j| sides[i].partial_cmp(&sides[j]).unwrap())
+        .unwrap();
+    let hypotenuse = sides[corner];
+    let (leg1, leg2) = (sides[(corner + 1) % 3], sides[(corner + 2) % 3]);
+    let legs_ratio = leg1.max(leg2) / leg1.min(leg2);
+    let pythagoras = (hypotenuse.powi(2) / (leg1.powi(2) + leg2.powi(2)) - 1.0).abs();
+    if legs_ratio > 1.25 || pythagoras > 0.2 {
+        return None;
+    }
+    let module_size = (a.module_size + b.module_size + c.module_size) / 3.0;
+    let modules = (leg1 + leg2) / 2.0 / module_size;
+    if modules < (dimension(MIN_VERSION) - 7) as f32 * 0.8
+        || modules > (dimension(MAX_VERSION) - 7) as f32 * 1.2
+    {
+        return None;
+    }
+
+    let top_left = triple[corner];
+    let mut top_right = triple[(corner + 1) % 3];
+    let mut bottom_left = triple[(corner + 2) % 3];
+    // With the y axis pointing down, top right is clockwise from bottom left
+    let (tl, tr, bl) = (
+        &finders[top_left],
+        &finders[top_right],
+        &finders[bottom_left],
+    );
+    let cross = (tr.x - tl.x) * (bl.y - tl.y) - (tr.y - tl.y) * (bl.x - tl.x);
+    if cross < 0.0 {
+        std::mem::swap(&mut top_right, &mut bottom_left);
+    }
+    let score = (legs_ratio - 1.0) + pythagoras + (max_module / min_module - 1.0);
+    Some((score, [top_left, top_right, bottom_left]))
+}
+
+// Estimate the version of a QR code from the distance between its finder patterns
+pub fn estimate_version(finders: &[FinderPattern], triple: [usize; 3]) -> usize {
+    let [tl, tr, bl] = triple.map(|i| &finders[i]);
+    let module_size = (tl.module_size + tr.module_size + bl.module_size) / 3.0;
+    let modules = (tl.distance(tr) + tl.distance(bl)) / 2.0 / module_size + 7.0;
+    let version = ((modules - 17.0) / 4.0).round();
+    (version.max(MIN_VERSION as f32) as usize).min(MAX_VERSION)
+}
+
+// Sample the modules of a QR code of the given version, mapping module
+// coordinates to pixels with the affine transform defined by the finder patterns
+pub fn sample_grid(
+    image: &BitMatrix,
+    finders: &[FinderPattern],
+    triple: [usize; 3],
+    version: usize,
+) -> BitMatrix {
+    let [tl, tr, bl] = triple.map(|i| &finders[i]);
+    let dimension = dimension(version);
+    // Finder pattern centers are 3.5 modules from the edges of the code
+    let span = (dimension - 7) as f32;
+    let (ux, uy) = ((tr.x - tl.x) / span, (tr.y - tl.y) / span);
+    let (vx, vy) = ((bl.x - tl.x) / span, (bl.y - tl.y) / span);
+
+    let mut grid = BitMatrix::new(dimension, dimension);
+    for row in 0..dimension {
+        for column in 0..dimension {
+            let u = column as f32 + 0.5 - 3.5;
+            let v = row as f32 + 0.5 - 3.5;
+            let x = tl.x + u * ux + v * vx;
+            let y = tl.y + u * uy + v * vy;
+            if x >= 0.0 && y >= 0.0 && image.get_checked(x as usize, y as usize).unwrap_or(false) {
+                grid.set(column, row);
+            }
+        }
+    }
+    grid
+}
@@ -0,0 +1,235 @@
+use crate::errors::{Error, Result};
+use image::GrayImage;
+use std::path::Path;
+
+mod decode;
+mod detect;
+mod reed_solomon;
+mod render;
+mod version;
+
+pub use self::render::{render_svg, render_terminal, write_image};
+
+// Grid of dark (`true`) and light (`false`) pixels or modules
+pub struct BitMatrix {
+    width: usize,
+    height: usize,
+    bits: Vec<bool>,
+}
+
+impl BitMatrix {
+    fn new(width: usize, height: usize) -> BitMatrix {
+        BitMatrix {
+            width,
+            height,
+            bits: vec![false; width * height],
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.bits[y * self.width + x]
+    }
+
+    fn get_checked(&self, x: usize, y: usize) -> Option<bool> {
+        if x < self.width && y < self.height {
+            Some(self.get(x, y))
+        } else {
+            None
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize) {
+        self.bits[y * self.width + x] = true;
+    }
+
+    fn set_region(&mut self, left: usize, top: usize, width: usize, height: usize) {
+        for y in top..top + height {
+            for x in left..left + width {
+                self.set(x, y);
+            }
+        }
+    }
+}
+
+// Decode the content of the QR code in an image file, which must contain exactly one code
+pub fn decode_file(path: &Path) -> Result<String> {
+    let image = image::open(path)?.into_luma_alpha8();
+    // Flatten transparent pixels onto a white background
+    let image = GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        let [luma, alpha] = image.get_pixel(x, y).0;
+        let luma = (luma as u32 * alpha as u32 + 255 * (255 - alpha as u32)) / 255;
+        image::Luma([luma as u8])
+    });
+
+    let mut contents = decode_image(&image)?;
+    match contents.len() {
+        0 => Err(Error::QrCodeNotFound),
+        1 => {
+            let content = contents.pop().unwrap();
+            String::from_utf8(content).map_err(|_| Error::QrCodeDecode)
+        }
+        count => Err(Error::MultipleQrCodes(count)),
+    }
+}
+
+// Decode every QR code in an image. Fails if a QR code was found but
+// none could be decoded.
+pub fn decode_image(image: &GrayImage) -> Result<Vec<Vec<u8>>> {
+    let pixels = detect::binarize(image);
+    let finders = detect::find_finder_patterns(&pixels);
+    let mut used = vec![false; finders.len()];
+    let mut contents = Vec::new();
+
+    for triple in detect::candidate_triples(&finders) {
+        if triple.iter().any(|&i| used[i]) {
+            continue;
+        }
+        if let Some(content) = decode_triple(&pixels, &finders, triple) {
+            for &i in triple.iter() {
+                used[i] = true;
+            }
+            contents.push(content);
+        }
+    }
+
+    if contents.is_empty() && finders.len() >= 3 {
+        return Err(Error::QrCodeDecode);
+    }
+    Ok(contents)
+}
+
+// Decode the QR code delimited by three finder patterns. The version estimated
+// from the finder patterns may be off by one or two, so neighbouring versions
+// are tried as well.
+fn decode_triple(
+    pixels: &BitMatrix,
+    finders: &[detect::FinderPattern],
+    triple: [usize; 3],
+) -> Option<Vec<u8>> {
+    let estimate = detect::estimate_version(finders, triple);
+    let grid = detect::sample_grid(pixels, finders, triple, estimate);
+    let mut versions = vec![estimate];
+    if let Some(version) = decode::read_version(&grid) {
+        versions.insert(0, version);
+    }
+    for offset in 1..=2 {
+        versions.push(estimate + offset);
+        if estimate > offset {
+            versions.push(estimate - offset);
+        }
+    }
+    versions.dedup();
+
+    versions
+        .into_iter()
+        .filter(|version| (version::MIN_VERSION..=version::MAX_VERSION).contains(version))
+        .find_map(|version| {
+            let grid = detect::sample_grid(pixels, finders, triple, version);
+            decode::decode_grid(&grid)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode_image;
+    use image::{imageops, GrayImage, Luma};
+    use qrcode::{Color, EcLevel, QrCode, Version};
+
+    const URI: &str = "otpauth://totp/ACME%20Co:john.doe@email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=ACME%20Co";
+
+    // Render a QR code with the given module size in pixels and a quiet zone of 4 modules
+    fn render(code: &QrCode, module_size: u32) -> GrayImage {
+        let width = code.width() as u32;
+        let colors = code.to_colors();
+        let size = (width + 8) * module_size;
+        GrayImage::from_fn(size, size, |x, y| {
+            let (column, row) = (x / module_size, y / module_size);
+            let dark = (4..width + 4).contains(&column)
+                && (4..width + 4).contains(&row)
+                && colors[((row - 4) * width + column - 4) as usize] == Color::Dark;
+            Luma([if dark { 0 } else { 255 }])
+        })
+    }
+
+    #[test]
+    fn test_decode_every_version() {
+        let ec_levels = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H];
+        for version in 1..=40 {
+            // Cycle through the error correction levels to keep the test fast
+            let ec_level = ec_levels[version as usize % ec_levels.len()];
+            let data = format!("v{}-{:?}", version, ec_level);
+            let code = QrCode::with_version(&data, Version::Normal(version), ec_level).unwrap();
+            let contents = decode_image(&render(&code, 2)).unwrap();
+            assert_eq!(contents, vec![data.into_bytes()]);
+        }
+    }
+
+    #[test]
+    fn test_decode_numeric_and_alphanumeric() {
+        for data in ["0123456789012", "HTTPS://EXAMPLE.COM/ABC"].iter() {
+            let code = QrCode::new(data).unwrap();
+            let contents = decode_image(&render(&code, 3)).unwrap();
+            assert_eq!(contents, vec![data.as_bytes().to_vec()]);
+        }
+    }
+
+    #[test]
+    fn test_decode_rotated_and_scaled() {
+        let code = QrCode::new(URI).unwrap();
+        let image = render(&code, 4);
+        let rotated = imageops::rotate90(&image);
+        assert_eq!(
+            decode_image(&rotated).unwrap(),
+            vec![URI.as_bytes().to_vec()]
+        );
+        let rotated = imageops::rotate180(&image);
+        assert_eq!(
+            decode_image(&rotated).unwrap(),
+            vec![URI.as_bytes().to_vec()]
+        );
+
+        let (width, height) = image.dimensions();
+        let scaled = imageops::resize(
+            &image,
+            width * 5 / 3,
+            height * 5 / 3,
+            imageops::FilterType::Triangle,
+        );
+        assert_eq!(
+            decode_image(&scaled).unwrap(),
+            vec![URI.as_bytes().to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_decode_with_damaged_modules() {
+        let code = QrCode::with_error_correction_level(URI, EcLevel::M).unwrap();
+        let mut image = render(&code, 3);
+        // Scribble over a few modules in the middle of the code
+        let center = image.width() / 2;
+        for x in center - 6..center + 6 {
+            for y in center - 3..center + 3 {
+                image.put_pixel(x, y, Luma([0]));
+            }
+        }
+        assert_eq!(decode_image(&image).unwrap(), vec![URI.as_bytes().to_vec()]);
+    }
+
+    #[test]
+    fn test_decode_multiple_codes() {
+        let first = render(&QrCode::new("first").unwrap(), 3);
+        let second = render(&QrCode::new("second").unwrap(), 3);
+        let mut image = GrayImage::from_pixel(first.width() * 2 + 10, first.height(), Luma([255]));
+        imageops::replace(&mut image, &first, 0, 0);
+        imageops::replace(&mut image, &second, first.width() as i64 + 10, 0);
+        let mut contents = decode_image(&image).unwrap();
+        contents.sort();
+        assert_eq!(contents, vec![b"first".to_vec(), b"second".to_vec()]);
+    }
+
+    #[test]
+    fn test_decode_without_code() {
+        let image = GrayImage::from_fn(100, 100, |x, y| Luma([((x * y) % 256) as u8]));
+        assert!(decode_image(&image).unwrap_or_default().is_empty());
+    }
+}
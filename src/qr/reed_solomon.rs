@@ -0,0 +1,191 @@
+// Reed-Solomon error correction over GF(256) with the primitive polynomial
+// x^8 + x^4 + x^3 + x^2 + 1 used by QR codes
+struct Galois {
+    exp: [u8; 512],
+    log: [u8; 256],
+}
+
+impl Galois {
+    fn new() -> Galois {
+        let mut exp = [0_u8; 512];
+        let mut log = [0_u8; 256];
+        let mut value: u16 = 1;
+        for (power, item) in exp.iter_mut().enumerate().take(255) {
+            *item = value as u8;
+            log[value as usize] = power as u8;
+            value <<= 1;
+            if value & 0x100 != 0 {
+                value ^= 0x11d;
+            }
+        }
+        for power in 255..512 {
+            exp[power] = exp[power - 255];
+        }
+        Galois { exp, log }
+    }
+
+    fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            0
+        } else {
+            self.exp[self.log[a as usize] as usize + self.log[b as usize] as usize]
+        }
+    }
+
+    fn div(&self, a: u8, b: u8) -> u8 {
+        if a == 0 {
+            0
+        } else {
+            self.exp[self.log[a as usize] as usize + 255 - self.log[b as usize] as usize]
+        }
+    }
+
+    // alpha^power, for any (possibly negative) power
+    fn alpha_pow(&self, power: i64) -> u8 {
+        self.exp[power.rem_euclid(255) as usize]
+    }
+
+    // Evaluate a polynomial whose coefficients are ordered from the lowest degree
+    fn eval(&self, poly: &[u8], x: u8) -> u8 {
+        poly.iter()
+            .rev()
+            .fold(0, |acc, &coef| self.mul(acc, x) ^ coef)
+    }
+}
+
+// Correct the errors of a block of data codewords followed by `ecc_len` error
+// correction codewords in place. Returns `false` if the block has more errors
+// than can be corrected.
+pub fn correct(block: &mut [u8], ecc_len: usize) -> bool {
+    let gf = Galois::new();
+    let syndromes = compute_syndromes(&gf, block, ecc_len);
+    if syndromes.iter().all(|&s| s == 0) {
+        return true;
+    }
+
+    // Find the error locator polynomial with the Berlekamp-Massey algorithm
+    let mut locator = vec![1_u8];
+    let mut previous = vec![1_u8];
+    let mut num_errors = 0;
+    let mut shift = 1;
+    let mut previous_discrepancy = 1_u8;
+    for k in 0..ecc_len {
+        let mut discrepancy = syndromes[k];
+        for i in 1..=num_errors.min(locator.len() - 1) {
+            discrepancy ^= gf.mul(locator[i], syndromes[k - i]);
+        }
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+        let coef = gf.div(discrepancy, previous_discrepancy);
+        let mut updated = locator.clone();
+        if updated.len() < previous.len() + shift {
+            updated.resize(previous.len() + shift, 0);
+        }
+        for (i, &p) in previous.iter().enumerate() {
+            updated[i + shift] ^= gf.mul(coef, p);
+        }
+        if 2 * num_errors <= k {
+            num_errors = k + 1 - num_errors;
+            previous = locator;
+            previous_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            shift += 1;
+        }
+        locator = updated;
+    }
+    while locator.len() > 1 && *locator.last().unwrap() == 0 {
+        locator.pop();
+    }
+    if locator.len() - 1 != num_errors || 2 * num_errors > ecc_len {
+        return false;
+    }
+
+    // Find the error positions with a Chien search, codeword `i` is the
+    // coefficient of x^(n - 1 - i)
+    let n = block.len();
+    let positions: Vec<usize> = (0..n)
+        .filter(|&i| gf.eval(&locator, gf.alpha_pow(-((n - 1 - i) as i64))) == 0)
+        .collect();
+    if positions.len() != num_errors {
+        return false;
+    }
+
+    // Compute the error values with the Forney algorithm
+    let mut evaluator = vec![0_u8; ecc_len];
+    for (i, &s) in syndromes.iter().enumerate() {
+        for (j, &l) in locator.iter().enumerate() {
+            if i + j < ecc_len {
+                evaluator[i + j] ^= gf.mul(s, l);
+            }
+        }
+    }
+    let derivative: Vec<u8> = locator
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, &l)| if i % 2 == 1 { l } else { 0 })
+        .collect();
+    for &i in positions.iter() {
+        let x = gf.alpha_pow((n - 1 - i) as i64);
+        let x_inv = gf.div(1, x);
+        let denominator = gf.eval(&derivative, x_inv);
+        if denominator == 0 {
+            return false;
+        }
+        block[i] ^= gf.mul(x, gf.div(gf.eval(&evaluator, x_inv), denominator));
+    }
+
+    compute_syndromes(&gf, block, ecc_len)
+        .iter()
+        .all(|&s| s == 0)
+}
+
+// Evaluate the received polynomial at alpha^0 .. alpha^(ecc_len - 1)
+fn compute_syndromes(gf: &Galois, block: &[u8], ecc_len: usize) -> Vec<u8> {
+    (0..ecc_len)
+        .map(|i| {
+            let x = gf.alpha_pow(i as i64);
+            block.iter().fold(0, |acc, &coef| gf.mul(acc, x) ^ coef)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::correct;
+
+    // Version 1-M code words for "01234567", from ISO/IEC 18004 Annex I
+    const BLOCK: [u8; 26] = [
+        0x10, 0x20, 0x0c, 0x56, 0x61, 0x80, 0xec, 0x11, 0xec, 0x11, 0xec, 0x11, 0xec, 0x11, 0xec,
+        0x11, 0xa5, 0x24, 0xd4, 0xc1, 0xed, 0x36, 0xc7, 0x87, 0x2c, 0x55,
+    ];
+
+    #[test]
+    fn test_correct_without_errors() {
+        let mut block = BLOCK;
+        assert!(correct(&mut block, 10));
+        assert_eq!(block, BLOCK);
+    }
+
+    #[test]
+    fn test_correct_errors() {
+        let mut block = BLOCK;
+        for (i, position) in [0, 3, 7, 18, 25].iter().enumerate() {
+            block[*position] ^= 0x5a + i as u8;
+        }
+        assert!(correct(&mut block, 10));
+        assert_eq!(block, BLOCK);
+    }
+
+    #[test]
+    fn test_correct_too_many_errors() {
+        let mut block = BLOCK;
+        for codeword in block.iter_mut().take(6) {
+            *codeword ^= 0xff;
+        }
+        assert!(!correct(&mut block, 10));
+    }
+}
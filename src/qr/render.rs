@@ -0,0 +1,115 @@
+use crate::errors::{Error, Result};
+use crate::files;
+use image::{GrayImage, ImageFormat, Luma};
+use qrcode::render::svg;
+use qrcode::render::unicode::Dense1x2;
+use qrcode::{Color, QrCode};
+use std::io::Cursor;
+use std::path::Path;
+
+// Width of the light border around a QR code, in modules
+const QUIET_ZONE: u32 = 4;
+// Size of a module in PNG images, in pixels
+const PNG_MODULE_SIZE: u32 = 8;
+
+// Render a QR code with Unicode half blocks, each character holding two
+// modules stacked vertically. Terminals usually draw light text on a dark
+// background so light modules are drawn as blocks, unless `invert` is set.
+pub fn render_terminal(data: &str, invert: bool) -> Result<String> {
+    let code = QrCode::new(data)?;
+    let (dark, light) = if invert {
+        (Dense1x2::Dark, Dense1x2::Light)
+    } else {
+        (Dense1x2::Light, Dense1x2::Dark)
+    };
+    Ok(code
+        .render::<Dense1x2>()
+        .dark_color(dark)
+        .light_color(light)
+        .build())
+}
+
+// Write a QR code to a PNG or SVG file, depending on the file extension. The
+// code holds the secret key, the file is only readable by the user.
+pub fn write_image(data: &str, path: &Path) -> Result<()> {
+    let code = QrCode::new(data)?;
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let image = match extension.as_str() {
+        "png" => {
+            let mut png = Cursor::new(Vec::new());
+            to_image(&code).write_to(&mut png, ImageFormat::Png)?;
+            png.into_inner()
+        }
+        "svg" => to_svg(&code, 256).into_bytes(),
+        _ => return Err(Error::UnsupportedImageFormat(extension)),
+    };
+    files::write_private(path, &image)
+}
+
+// Render a QR code as an `<svg>` element of at least `size` pixels, to embed
+// in HTML documents
+pub fn render_svg(data: &str, size: u32) -> Result<String> {
+    Ok(to_svg(&QrCode::new(data)?, size))
+}
+
+fn to_svg(code: &QrCode, size: u32) -> String {
+    let image = code
+        .render::<svg::Color>()
+        .min_dimensions(size, size)
+        .build();
+    // Strip the XML declaration, which is optional for SVG files
+    match image.find("<svg") {
+        Some(start) => image[start..].to_string(),
+        None => image,
+    }
+}
+
+fn to_image(code: &QrCode) -> GrayImage {
+    let width = code.width() as u32;
+    let colors = code.to_colors();
+    let size = (width + 2 * QUIET_ZONE) * PNG_MODULE_SIZE;
+    GrayImage::from_fn(size, size, |x, y| {
+        let (column, row) = (x / PNG_MODULE_SIZE, y / PNG_MODULE_SIZE);
+        let modules = QUIET_ZONE..width + QUIET_ZONE;
+        let dark = modules.contains(&column)
+            && modules.contains(&row)
+            && colors[((row - QUIET_ZONE) * width + column - QUIET_ZONE) as usize] == Color::Dark;
+        Luma([if dark { 0 } else { 255 }])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_terminal, to_image};
+    use crate::qr::decode_image;
+    use qrcode::QrCode;
+
+    const URI: &str =
+        "otpauth://totp/GitHub:evans?secret=4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6&issuer=GitHub";
+
+    #[test]
+    fn test_image_roundtrip() {
+        let image = to_image(&QrCode::new(URI).unwrap());
+        assert_eq!(decode_image(&image).unwrap(), vec![URI.as_bytes().to_vec()]);
+    }
+
+    #[test]
+    fn test_render_terminal() {
+        let code = QrCode::new(URI).unwrap();
+        let rendered = render_terminal(URI, false).unwrap();
+        let lines: Vec<_> = rendered.lines().collect();
+        // Two rows of modules per line, including the quiet zone, the last
+        // line only holds one row since the size is odd
+        let size = code.width() + 8;
+        assert_eq!(lines.len() * 2, size + 1);
+        assert!(lines.iter().all(|line| line.chars().count() == size));
+        // The quiet zone is light, drawn as full blocks unless inverted
+        assert!(lines[0].chars().all(|c| c == '\u{2588}'));
+        let inverted = render_terminal(URI, true).unwrap();
+        assert!(inverted.lines().next().unwrap().chars().all(|c| c == ' '));
+    }
+}
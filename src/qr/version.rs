@@ -0,0 +1,200 @@
+use crate::qr::BitMatrix;
+
+pub const MIN_VERSION: usize = 1;
+pub const MAX_VERSION: usize = 40;
+
+// Error correction level of a QR code
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EcLevel {
+    L,
+    M,
+    Q,
+    H,
+}
+
+impl EcLevel {
+    // Level encoded in the two most significant bits of the format information
+    pub fn from_bits(bits: u16) -> EcLevel {
+        match bits & 0b11 {
+            0b01 => EcLevel::L,
+            0b00 => EcLevel::M,
+            0b11 => EcLevel::Q,
+            _ => EcLevel::H,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            EcLevel::L => 0,
+            EcLevel::M => 1,
+            EcLevel::Q => 2,
+            EcLevel::H => 3,
+        }
+    }
+}
+
+// Number of error correction codewords per block, indexed by level and version.
+// From ISO/IEC 18004:2015, Table 9.
+const ECC_CODEWORDS_PER_BLOCK: [[u8; 41]; 4] = [
+    [
+        0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28,
+        30, 30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+    [
+        0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28,
+        28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+    ],
+    [
+        0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30, 24, 28, 28, 26, 30, 28, 30,
+        30, 30, 30, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+    [
+        0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24, 30, 28, 28, 26, 28, 30, 24,
+        30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+];
+
+// Number of error correction blocks, indexed by level and version
+const NUM_ERROR_CORRECTION_BLOCKS: [[u8; 41]; 4] = [
+    [
+        0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13,
+        14, 15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25,
+    ],
+    [
+        0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21,
+        23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49,
+    ],
+    [
+        0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12, 17, 16, 18, 21, 20, 23, 23, 25, 27, 29,
+        34, 34, 35, 38, 40, 43, 45, 48, 51, 53, 56, 59, 62, 65, 68,
+    ],
+    [
+        0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18, 16, 19, 21, 25, 25, 25, 34, 30, 32,
+        35, 37, 40, 42, 45, 48, 51, 54, 57, 60, 63, 66, 70, 74, 77, 81,
+    ],
+];
+
+// Layout of the error correction blocks of a QR code
+pub struct BlockLayout {
+    pub ecc_len: usize,
+    pub num_blocks: usize,
+    // Blocks are either short or one data codeword longer, short blocks come first
+    pub num_short_blocks: usize,
+    pub short_data_len: usize,
+}
+
+impl BlockLayout {
+    pub fn new(version: usize, ec_level: EcLevel) -> BlockLayout {
+        let ecc_len = ECC_CODEWORDS_PER_BLOCK[ec_level.index()][version] as usize;
+        let num_blocks = NUM_ERROR_CORRECTION_BLOCKS[ec_level.index()][version] as usize;
+        let raw_codewords = raw_data_modules(version) / 8;
+        BlockLayout {
+            ecc_len,
+            num_blocks,
+            num_short_blocks: num_blocks - raw_codewords % num_blocks,
+            short_data_len: raw_codewords / num_blocks - ecc_len,
+        }
+    }
+
+    pub fn data_len(&self, block: usize) -> usize {
+        if block < self.num_short_blocks {
+            self.short_data_len
+        } else {
+            self.short_data_len + 1
+        }
+    }
+}
+
+pub fn dimension(version: usize) -> usize {
+    17 + 4 * version
+}
+
+// Number of modules available for data and error correction codewords, i.e.
+// the modules not used by function patterns
+pub fn raw_data_modules(version: usize) -> usize {
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let num_align = version / 7 + 2;
+        modules -= (25 * num_align - 10) * num_align - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules
+}
+
+// Row and column coordinates of the centers of the alignment patterns
+pub fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let num_align = version / 7 + 2;
+    let step = (version * 8 + num_align * 3 + 5) / (num_align * 4 - 4) * 2;
+    // The last position is 7 modules from the edge, the others are spaced evenly
+    // going backwards, apart from the first one which is always 6
+    let last = dimension(version) - 7;
+    let mut positions = vec![6];
+    positions.extend((1..num_align).map(|i| last - (num_align - 1 - i) * step));
+    positions
+}
+
+// Modules used by function patterns, which don't hold any data
+pub fn function_pattern(version: usize) -> BitMatrix {
+    let dimension = dimension(version);
+    let mut pattern = BitMatrix::new(dimension, dimension);
+    // Finder patterns, separators and format information
+    pattern.set_region(0, 0, 9, 9);
+    pattern.set_region(dimension - 8, 0, 8, 9);
+    pattern.set_region(0, dimension - 8, 9, 8);
+
+    let positions = alignment_positions(version);
+    let last = positions.len().saturating_sub(1);
+    for (i, &x) in positions.iter().enumerate() {
+        for (j, &y) in positions.iter().enumerate() {
+            // Skip the positions overlapping the finder patterns
+            if (i == 0 && (j == 0 || j == last)) || (i == last && j == 0) {
+                continue;
+            }
+            pattern.set_region(x - 2, y - 2, 5, 5);
+        }
+    }
+
+    // Timing patterns
+    pattern.set_region(6, 9, 1, dimension - 17);
+    pattern.set_region(9, 6, dimension - 17, 1);
+
+    // Version information
+    if version >= 7 {
+        pattern.set_region(dimension - 11, 0, 3, 6);
+        pattern.set_region(0, dimension - 11, 6, 3);
+    }
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{alignment_positions, raw_data_modules, BlockLayout, EcLevel};
+
+    #[test]
+    fn test_alignment_positions() {
+        assert!(alignment_positions(1).is_empty());
+        assert_eq!(alignment_positions(2), vec![6, 18]);
+        assert_eq!(alignment_positions(7), vec![6, 22, 38]);
+        assert_eq!(alignment_positions(32), vec![6, 34, 60, 86, 112, 138]);
+        assert_eq!(alignment_positions(40), vec![6, 30, 58, 86, 114, 142, 170]);
+    }
+
+    #[test]
+    fn test_block_layout() {
+        assert_eq!(raw_data_modules(1), 208);
+        let layout = BlockLayout::new(1, EcLevel::M);
+        assert_eq!((layout.ecc_len, layout.num_blocks), (10, 1));
+        assert_eq!(layout.data_len(0), 16);
+
+        // 5-Q has two blocks of 15 and two blocks of 16 data codewords
+        let layout = BlockLayout::new(5, EcLevel::Q);
+        assert_eq!((layout.ecc_len, layout.num_blocks), (18, 4));
+        assert_eq!(layout.num_short_blocks, 2);
+        assert_eq!((layout.data_len(0), layout.data_len(3)), (15, 16));
+    }
+}
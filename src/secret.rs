@@ -0,0 +1,207 @@
+use crate::config;
+use crate::errors::{Error, Result};
+use crate::keyring;
+use crate::otpauth::OtpAuth;
+use data_encoding::HEXLOWER;
+use ring::digest;
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+// File of the config directory listing the SHA-256 of the commands the user
+// confirmed, kept out of the vaults as they may be shared
+const TRUSTED_COMMANDS_FILE_NAME: &str = "trusted_commands";
+
+// Program holding the secret key of an account, stored in a field of its own
+// so that no key, e.g. of a shared or restored account, turns into a command
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase", tag = "kind", content = "source")]
+pub enum ExternalKey {
+    // Shell command printing the key, run once the user confirmed it
+    Command(String),
+    // Path of a file encrypted with gpg holding the key
+    Gpg(String),
+    // Name of a `pass` entry holding the key
+    Pass(String),
+}
+
+impl ExternalKey {
+    // Read the secret key. Commands run with the terminal so that gpg can ask
+    // for the passphrase of the key.
+    pub fn read(&self) -> Result<String> {
+        match self {
+            ExternalKey::Command(command) => {
+                confirm_command(command)?;
+                read_output(command, shell(command))
+            }
+            ExternalKey::Gpg(path) => {
+                let mut gpg = Command::new("gpg");
+                gpg.args(["--quiet", "--decrypt", "--", path]);
+                read_output(path, gpg)
+            }
+            ExternalKey::Pass(name) => {
+                let mut pass = Command::new("pass");
+                pass.args(["show", name]);
+                read_output(name, pass)
+            }
+        }
+    }
+}
+
+// Where the secret key of an account comes from
+#[derive(Debug, PartialEq)]
+pub enum KeySource<'a> {
+    // The key itself, stored in the accounts file
+    Plain(&'a str),
+    // Id of the secret in the keyring
+    Keyring(&'a str),
+    // Program reading the key
+    External(&'a ExternalKey),
+}
+
+impl<'a> KeySource<'a> {
+    pub fn parse(key: &'a str) -> KeySource<'a> {
+        match key.strip_prefix(keyring::KEY_PREFIX) {
+            Some(id) => KeySource::Keyring(id),
+            None => KeySource::Plain(key),
+        }
+    }
+
+    pub fn read(&self) -> Result<String> {
+        match self {
+            KeySource::Plain(key) => Ok(key.to_string()),
+            KeySource::Keyring(id) => keyring::lookup(id),
+            KeySource::External(external_key) => external_key.read(),
+        }
+    }
+}
+
+// Record a command as confirmed, e.g. when the user adds it
+pub fn trust_command(command: &str) -> Result<()> {
+    let path = config::config_file_path()?.with_file_name(TRUSTED_COMMANDS_FILE_NAME);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", command_digest(command))?;
+    Ok(())
+}
+
+fn is_trusted(command: &str) -> Result<bool> {
+    let path = config::config_file_path()?.with_file_name(TRUSTED_COMMANDS_FILE_NAME);
+    let trusted = match fs::read_to_string(path) {
+        Ok(trusted) => trusted,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err.into()),
+    };
+    let digest = command_digest(command);
+    Ok(trusted.lines().any(|line| line == digest))
+}
+
+// Ask the user before running a command for the first time, as it may come
+// from a vault someone else can change
+fn confirm_command(command: &str) -> Result<()> {
+    if is_trusted(command)? {
+        return Ok(());
+    }
+    eprint!("Run `{}` to read the secret key [N/y]? ", command);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if answer.trim().to_lowercase() != "y" {
+        return Err(Error::KeySource(
+            command.to_string(),
+            String::from("the command was not confirmed"),
+        ));
+    }
+    trust_command(command)
+}
+
+fn command_digest(command: &str) -> String {
+    HEXLOWER.encode(digest::digest(&digest::SHA256, command.as_bytes()).as_ref())
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.args(["-c", command]);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.args(["/C", command]);
+    shell
+}
+
+fn read_output(source: &str, mut command: Command) -> Result<String> {
+    let output = command
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|err| Error::KeySource(source.to_string(), err.to_string()))?;
+    if !output.status.success() {
+        return Err(Error::KeySource(
+            source.to_string(),
+            output.status.to_string(),
+        ));
+    }
+    let output = String::from_utf8_lossy(&output.stdout);
+    parse_key(&output).ok_or_else(|| Error::KeySource(source.to_string(), String::from("no key")))
+}
+
+// The key is the first line of the output, e.g. of a `pass` entry, either the
+// key itself or an otpauth:// URI as stored by pass-otp
+fn parse_key(output: &str) -> Option<String> {
+    let line = output
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())?;
+    match line.parse::<OtpAuth>() {
+        Ok(otpauth) => Some(otpauth.secret),
+        Err(_) => Some(line.replace(' ', "").to_uppercase()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_key, read_output, shell, KeySource};
+
+    #[test]
+    fn test_parse_key_source() {
+        let sources = [
+            ("JBSWY3DPEHPK3PXP", KeySource::Plain("JBSWY3DPEHPK3PXP")),
+            ("keyring:MFZWIYLT", KeySource::Keyring("MFZWIYLT")),
+            // Keys never refer to a command
+            ("command:echo key", KeySource::Plain("command:echo key")),
+        ];
+        for (key, source) in sources {
+            assert_eq!(KeySource::parse(key), source);
+        }
+    }
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(
+            parse_key("\njbsw y3dp ehpk 3pxp\nlogin: evans\n"),
+            Some(String::from("JBSWY3DPEHPK3PXP"))
+        );
+        assert_eq!(
+            parse_key("otpauth://totp/GitHub:evans?secret=JBSWY3DPEHPK3PXP&issuer=GitHub\n"),
+            Some(String::from("JBSWY3DPEHPK3PXP"))
+        );
+        assert_eq!(parse_key("\n\n"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_command_output() {
+        let command = "printf 'jbswy3dpehpk3pxp\\n'";
+        assert_eq!(
+            read_output(command, shell(command)).unwrap(),
+            "JBSWY3DPEHPK3PXP"
+        );
+        assert!(read_output("exit 3", shell("exit 3")).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use crate::account::SCHEMA_VERSION;
+use crate::crypto::{self, VaultKey};
+use crate::errors::{Error, Result};
+use crate::files;
+use crate::storage::{self, Accounts, Storage};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const DIR_NAME: &str = "accounts.d";
+const VERSION_FILE_NAME: &str = "version";
+const VAULT_FILE_NAME: &str = "vault";
+const EXTENSION: &str = "toml";
+
+// Characters of account names kept as is in file names, the others are
+// percent-encoded
+const FILE_NAME: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'@');
+
+// A directory holding a TOML file per account, which diffs well when the
+// directory is kept in git. The accounts are encrypted one by one.
+pub struct Directory {
+    path: PathBuf,
+}
+
+impl Directory {
+    pub fn new(vault_dir: &Path) -> Result<Directory> {
+        let path = vault_dir.join(DIR_NAME);
+        fs::create_dir_all(&path)?;
+        Ok(Directory { path })
+    }
+
+    fn account_path(&self, account_name: &str) -> PathBuf {
+        let file_name = utf8_percent_encode(account_name, FILE_NAME).to_string();
+        self.path.join(format!("{}.{}", file_name, EXTENSION))
+    }
+
+    // Names of the accounts along with the paths of their files
+    fn account_files(&self) -> Result<BTreeMap<String, PathBuf>> {
+        let mut account_files = BTreeMap::new();
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(EXTENSION) {
+                continue;
+            }
+            if let Some(file_stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                let account_name = percent_decode_str(file_stem).decode_utf8_lossy();
+                account_files.insert(account_name.into_owned(), path);
+            }
+        }
+        Ok(account_files)
+    }
+}
+
+impl Storage for Directory {
+    // The directory is always written with the current schema
+    fn read(&self, _upgrade: bool) -> Result<(Accounts, Option<VaultKey>)> {
+        let version = match read_optional(&self.path.join(VERSION_FILE_NAME))? {
+            Some(version) => String::from_utf8_lossy(&version)
+                .trim()
+                .parse()
+                .unwrap_or(0),
+            None => SCHEMA_VERSION,
+        };
+        if version != SCHEMA_VERSION {
+            return Err(Error::UnsupportedSchemaVersion(version, SCHEMA_VERSION));
+        }
+        let vault_key = match read_optional(&self.path.join(VAULT_FILE_NAME))? {
+            Some(marker) => Some(storage::open_vault_marker(&marker)?),
+            None => None,
+        };
+
+        let mut accounts = BTreeMap::new();
+        for (account_name, path) in self.account_files()? {
+            let account = storage::decode(&fs::read(path)?, vault_key.as_ref())?;
+            accounts.insert(account_name, account);
+        }
+        Ok((accounts, vault_key))
+    }
+
+    // Only the files of the accounts that changed are written
+    fn write(
+        &self,
+        accounts: &Accounts,
+        vault_key: Option<&VaultKey>,
+        _keep_previous: bool,
+    ) -> Result<()> {
+        let version_path = self.path.join(VERSION_FILE_NAME);
+        let version = format!("{}\n", SCHEMA_VERSION);
+        if read_optional(&version_path)?.as_deref() != Some(version.as_bytes()) {
+            files::write_atomic(&version_path, version.as_bytes(), 0)?;
+        }
+
+        let vault_path = self.path.join(VAULT_FILE_NAME);
+        let marker = read_optional(&vault_path)?;
+        match vault_key {
+            Some(vault_key) => {
+                let sealed = matches!(
+                    marker,
+                    Some(marker) if crypto::decrypt_with_key(&marker, vault_key).is_ok()
+                );
+                if !sealed {
+                    files::write_atomic(&vault_path, &storage::seal_vault_marker(vault_key)?, 0)?;
+                }
+            }
+            None if marker.is_some() => fs::remove_file(&vault_path)?,
+            None => {}
+        }
+
+        for (account_name, account) in accounts {
+            let path = self.account_path(account_name);
+            let unchanged = matches!(
+                read_optional(&path)?,
+                Some(data) if crypto::is_encrypted(&data) == vault_key.is_some()
+                    && storage::decode(&data, vault_key).ok().as_ref() == Some(account)
+            );
+            if !unchanged {
+                files::write_atomic(&path, &storage::encode(account, vault_key)?, 0)?;
+            }
+        }
+        for (account_name, path) in self.account_files()? {
+            if !accounts.contains_key(&account_name) {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Account files are replaced, the directory keeps no other copy
+    fn rekey(&self, accounts: &Accounts, vault_key: Option<&VaultKey>) -> Result<()> {
+        self.write(accounts, vault_key, false)
+    }
+
+    fn previous(&self, _version: usize, _vault_key: Option<&VaultKey>) -> Result<(Accounts, u64)> {
+        Err(Error::PreviousVersionsUnsupported("directory"))
+    }
+}
+
+fn read_optional(path: &Path) -> Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
@@ -0,0 +1,153 @@
+use crate::account::{self, Account, SCHEMA_VERSION};
+use crate::crypto::{self, VaultKey};
+use crate::dirs;
+use crate::errors::{Error, Result};
+use crate::files;
+use crate::storage::{Accounts, Storage};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use toml::value::Table;
+
+// Number of previous versions of the accounts file that are kept
+const PREVIOUS_VERSIONS: usize = 5;
+
+// Contents of the accounts file
+#[derive(Serialize)]
+struct AccountsFile<'a> {
+    version: u32,
+    accounts: &'a BTreeMap<String, Account>,
+}
+
+// The accounts file, a single TOML file encrypted as a whole
+pub struct File {
+    path: PathBuf,
+}
+
+impl File {
+    pub fn new(vault_dir: &Path) -> Result<File> {
+        Ok(File {
+            path: dirs::accounts_file_path(vault_dir)?,
+        })
+    }
+}
+
+impl Storage for File {
+    fn read(&self, upgrade: bool) -> Result<(Accounts, Option<VaultKey>)> {
+        let path = self.path.as_path();
+        let (table, vault_key) = read_file(&fs::read(path)?, None)?;
+        let version = account::schema_version(&table);
+        // A new accounts file is empty, there is nothing to upgrade
+        let upgrade = upgrade && version < SCHEMA_VERSION && !table.is_empty();
+        let accounts = read_accounts(table)?;
+
+        // Upgrade the file in place, keeping a copy of the previous version
+        // in case the upgrade goes wrong or an older cloak is needed
+        if upgrade {
+            fs::copy(path, path.with_extension(format!("v{}.bak", version)))?;
+            self.write(&accounts, vault_key.as_ref(), true)?;
+        }
+        Ok((accounts, vault_key))
+    }
+
+    fn write(
+        &self,
+        accounts: &Accounts,
+        vault_key: Option<&VaultKey>,
+        keep_previous: bool,
+    ) -> Result<()> {
+        let accounts_str = toml::to_string(&AccountsFile {
+            version: SCHEMA_VERSION,
+            accounts,
+        })?;
+        let data = match vault_key {
+            Some(vault_key) => crypto::encrypt(accounts_str.as_bytes(), vault_key)?,
+            None => accounts_str.into_bytes(),
+        };
+        let keep = if keep_previous { PREVIOUS_VERSIONS } else { 0 };
+        files::write_atomic(&self.path, &data, keep)
+    }
+
+    // Previous versions and upgrade backups are encrypted with the previous key
+    // or not at all
+    fn rekey(&self, accounts: &Accounts, vault_key: Option<&VaultKey>) -> Result<()> {
+        self.write(accounts, vault_key, false)?;
+        let dir = self.path.parent().unwrap_or_else(|| Path::new("."));
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if is_copy(&path) {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    // The passphrase is only asked for if it changed since that version
+    fn previous(&self, version: usize, vault_key: Option<&VaultKey>) -> Result<(Accounts, u64)> {
+        let path = files::previous_path(&self.path, version);
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Err(Error::NoPreviousVersion(version, PREVIOUS_VERSIONS))
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let saved_at = fs::metadata(&path)?
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let (table, _) = read_file(&data, vault_key)?;
+        Ok((read_accounts(table)?, saved_at))
+    }
+}
+
+// Previous version `accounts.<version>` or upgrade backup `accounts.v<version>.bak`
+fn is_copy(path: &Path) -> bool {
+    let file_name = path.file_name().and_then(|name| name.to_str());
+    let extension =
+        match file_name.and_then(|name| name.strip_prefix(dirs::CLOAK_ACCOUNTS_FILE_NAME)) {
+            Some(extension) => extension,
+            None => return false,
+        };
+    let is_number = |value: &str| !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit());
+    match extension.strip_prefix('.') {
+        Some(version) if is_number(version) => true,
+        Some(backup) => matches!(
+            backup.strip_prefix('v').and_then(|backup| backup.strip_suffix(".bak")),
+            Some(version) if is_number(version)
+        ),
+        None => false,
+    }
+}
+
+// Read the contents of an accounts file, decrypting it with the given key or a
+// passphrase. Returns the contents along with the key of an encrypted file.
+fn read_file(data: &[u8], vault_key: Option<&VaultKey>) -> Result<(Table, Option<VaultKey>)> {
+    let (data, vault_key) = if !crypto::is_encrypted(data) {
+        (data.to_vec(), None)
+    } else if let Some(plaintext) =
+        vault_key.and_then(|vault_key| crypto::decrypt_with_key(data, vault_key).ok())
+    {
+        (plaintext, None)
+    } else {
+        let passphrase = crypto::read_passphrase()?;
+        let (plaintext, vault_key) = crypto::decrypt(data, &passphrase)?;
+        (plaintext, Some(vault_key))
+    };
+    let accounts_str =
+        String::from_utf8(data).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Ok((toml::from_str(&accounts_str)?, vault_key))
+}
+
+// Accounts of the contents of an accounts file, upgraded to the current version
+fn read_accounts(table: Table) -> Result<Accounts> {
+    let version = account::schema_version(&table);
+    let mut table = account::migrate(table, version)?;
+    match table.remove("accounts") {
+        Some(accounts) => Ok(accounts.try_into()?),
+        None => Ok(BTreeMap::new()),
+    }
+}
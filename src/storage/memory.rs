@@ -0,0 +1,48 @@
+use crate::crypto::VaultKey;
+use crate::errors::{Error, Result};
+use crate::storage::{Accounts, Storage};
+use std::cell::RefCell;
+
+// Accounts kept in memory, unencrypted, along with every previous version
+#[derive(Default)]
+pub struct Memory {
+    versions: RefCell<Vec<Accounts>>,
+}
+
+impl Storage for Memory {
+    fn read(&self, _upgrade: bool) -> Result<(Accounts, Option<VaultKey>)> {
+        let accounts = self.versions.borrow().last().cloned();
+        Ok((accounts.unwrap_or_default(), None))
+    }
+
+    fn write(
+        &self,
+        accounts: &Accounts,
+        _vault_key: Option<&VaultKey>,
+        keep_previous: bool,
+    ) -> Result<()> {
+        let mut versions = self.versions.borrow_mut();
+        if !keep_previous {
+            versions.pop();
+        }
+        versions.push(accounts.clone());
+        Ok(())
+    }
+
+    fn rekey(&self, accounts: &Accounts, _vault_key: Option<&VaultKey>) -> Result<()> {
+        self.versions.replace(vec![accounts.clone()]);
+        Ok(())
+    }
+
+    fn previous(&self, version: usize, _vault_key: Option<&VaultKey>) -> Result<(Accounts, u64)> {
+        let versions = self.versions.borrow();
+        versions
+            .len()
+            .checked_sub(version + 1)
+            .map(|index| (versions[index].clone(), 0))
+            .ok_or(Error::NoPreviousVersion(
+                version,
+                versions.len().saturating_sub(1),
+            ))
+    }
+}
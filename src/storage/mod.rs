@@ -0,0 +1,166 @@
+use crate::account::Account;
+use crate::config::CONFIG;
+use crate::crypto::{self, VaultKey};
+use crate::dirs;
+use crate::errors::{Error, Result};
+use std::collections::BTreeMap;
+use std::env;
+use std::io;
+use std::path::Path;
+
+pub mod directory;
+pub mod file;
+#[cfg(test)]
+pub mod memory;
+pub mod sqlite;
+
+// Accounts by name, as kept by a storage backend
+pub type Accounts = BTreeMap<String, Account>;
+
+// Where the accounts are kept. Backends encrypt what they store with the vault
+// key when there is one.
+pub trait Storage {
+    // Read the accounts, along with the key they are encrypted with. Accounts
+    // stored with an older schema are upgraded in place if `upgrade` is set.
+    fn read(&self, upgrade: bool) -> Result<(Accounts, Option<VaultKey>)>;
+
+    // Replace the stored accounts, keeping the stored ones as the latest
+    // previous version if `keep_previous` is set and the backend keeps them
+    fn write(
+        &self,
+        accounts: &Accounts,
+        vault_key: Option<&VaultKey>,
+        keep_previous: bool,
+    ) -> Result<()>;
+
+    // Replace the stored accounts after the vault key changed, dropping the
+    // previous versions and any other copy left readable without the new key
+    fn rekey(&self, accounts: &Accounts, vault_key: Option<&VaultKey>) -> Result<()>;
+
+    // Accounts of a previous version, 1 being the latest, and the time it was saved
+    fn previous(&self, version: usize, vault_key: Option<&VaultKey>) -> Result<(Accounts, u64)>;
+}
+
+// Names of the storage backends
+pub const STORAGES: [&str; 3] = ["file", "directory", "sqlite"];
+
+// Storage backend of a vault, found from its files so that a vault is never
+// read with another backend. New vaults use the backend selected by
+// `CLOAK_STORAGE` or the `storage` setting, the accounts file by default.
+pub fn open(vault_dir: &Path) -> Result<Box<dyn Storage>> {
+    let selected = match env::var("CLOAK_STORAGE") {
+        Ok(name) if !name.is_empty() => Some(parse_storage(&name)?),
+        _ => None,
+    };
+    let dir = vault_dir.display().to_string();
+    let name = match (stored(vault_dir).as_slice(), selected) {
+        ([], selected) => selected
+            .or_else(|| CONFIG.storage.clone())
+            .unwrap_or_else(|| String::from("file")),
+        ([stored], Some(selected)) if *stored != selected => {
+            return Err(Error::StorageMismatch(dir, stored.to_string(), selected))
+        }
+        ([stored], _) => stored.to_string(),
+        (stored, Some(selected)) if stored.contains(&selected.as_str()) => selected,
+        (stored, _) => return Err(Error::AmbiguousStorage(dir, stored.join(" and "))),
+    };
+    match name.as_str() {
+        "directory" => Ok(Box::new(directory::Directory::new(vault_dir)?)),
+        "sqlite" => Ok(Box::new(sqlite::Sqlite::new(vault_dir)?)),
+        _ => Ok(Box::new(file::File::new(vault_dir)?)),
+    }
+}
+
+pub fn parse_storage(name: &str) -> Result<String> {
+    if !STORAGES.contains(&name) {
+        return Err(Error::UnknownStorage(name.to_string()));
+    }
+    Ok(name.to_string())
+}
+
+// Backends whose files are in the vault directory
+fn stored(vault_dir: &Path) -> Vec<&'static str> {
+    let mut stored = Vec::new();
+    if vault_dir.join(dirs::CLOAK_ACCOUNTS_FILE_NAME).is_file() {
+        stored.push("file");
+    }
+    if vault_dir.join(directory::DIR_NAME).is_dir() {
+        stored.push("directory");
+    }
+    if vault_dir.join(sqlite::FILE_NAME).is_file() {
+        stored.push("sqlite");
+    }
+    stored
+}
+
+// Content of the vault marker, stored by backends that encrypt each account
+// on its own so that the passphrase is asked for even without any account
+const VAULT_MARKER: &[u8] = b"cloak";
+
+fn seal_vault_marker(vault_key: &VaultKey) -> Result<Vec<u8>> {
+    crypto::encrypt(VAULT_MARKER, vault_key)
+}
+
+// Key of the accounts, derived from the passphrase that opens the vault marker
+fn open_vault_marker(data: &[u8]) -> Result<VaultKey> {
+    let (_, vault_key) = crypto::decrypt(data, &crypto::read_passphrase()?)?;
+    Ok(vault_key)
+}
+
+// Serialize an account, encrypted with the vault key if there is one
+fn encode(account: &Account, vault_key: Option<&VaultKey>) -> Result<Vec<u8>> {
+    let account_str = toml::to_string(account)?;
+    match vault_key {
+        Some(vault_key) => crypto::encrypt(account_str.as_bytes(), vault_key),
+        None => Ok(account_str.into_bytes()),
+    }
+}
+
+// Deserialize an account, decrypted with the vault key if it is encrypted
+fn decode(data: &[u8], vault_key: Option<&VaultKey>) -> Result<Account> {
+    let data = match vault_key {
+        Some(vault_key) if crypto::is_encrypted(data) => crypto::decrypt_with_key(data, vault_key)?,
+        None if crypto::is_encrypted(data) => return Err(Error::Decrypt),
+        _ => data.to_vec(),
+    };
+    let account_str =
+        String::from_utf8(data).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Ok(toml::from_str(&account_str)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode};
+    use crate::account::Account;
+    use crate::crypto::VaultKey;
+    use crate::errors::Error;
+
+    #[test]
+    fn test_encode_decode() {
+        let account = Account {
+            key: String::from("JBSWY3DPEHPK3PXP"),
+            totp: true,
+            hash_function: String::from("SHA1"),
+            counter: None,
+            digits: Some(6),
+            period: Some(30),
+            t0: None,
+            steam: false,
+            tags: vec![String::from("Work")],
+            issuer: Some(String::from("GitHub")),
+            label: None,
+            notes: None,
+            created_at: Some(1_663_144_200),
+            last_used_at: None,
+            external_key: None,
+        };
+        let data = encode(&account, None).unwrap();
+        assert_eq!(decode(&data, None).unwrap(), account);
+
+        let vault_key = VaultKey::generate("hunter2").unwrap();
+        let data = encode(&account, Some(&vault_key)).unwrap();
+        assert!(!data.starts_with(b"key"));
+        assert_eq!(decode(&data, Some(&vault_key)).unwrap(), account);
+        assert!(matches!(decode(&data, None), Err(Error::Decrypt)));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::account::SCHEMA_VERSION;
+use crate::crypto::VaultKey;
+use crate::dirs;
+use crate::errors::{Error, Result};
+use crate::storage::{self, Accounts, Storage};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+pub const FILE_NAME: &str = "accounts.sqlite";
+
+const CREATE_TABLES: &str = "
+CREATE TABLE IF NOT EXISTS accounts (name TEXT PRIMARY KEY, account BLOB NOT NULL);
+CREATE TABLE IF NOT EXISTS metadata (key TEXT PRIMARY KEY, value BLOB NOT NULL);
+";
+
+// An SQLite database holding a row per account, encrypted one by one. The
+// schema version is kept as the `user_version` of the database.
+pub struct Sqlite {
+    connection: Connection,
+}
+
+impl Sqlite {
+    pub fn new(vault_dir: &Path) -> Result<Sqlite> {
+        let path = vault_dir.join(FILE_NAME);
+        // Created beforehand to restrict its permissions
+        if !path.is_file() {
+            dirs::create_file(&path)?;
+        }
+        let connection = Connection::open(path)?;
+        connection.execute_batch(CREATE_TABLES)?;
+        Ok(Sqlite { connection })
+    }
+}
+
+impl Storage for Sqlite {
+    // The database is always written with the current schema
+    fn read(&self, _upgrade: bool) -> Result<(Accounts, Option<VaultKey>)> {
+        let version: u32 = self
+            .connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            return Err(Error::UnsupportedSchemaVersion(version, SCHEMA_VERSION));
+        }
+        let marker: Option<Vec<u8>> = self
+            .connection
+            .query_row(
+                "SELECT value FROM metadata WHERE key = 'vault'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let vault_key = match marker {
+            Some(marker) => Some(storage::open_vault_marker(&marker)?),
+            None => None,
+        };
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT name, account FROM accounts")?;
+        let mut rows = statement.query([])?;
+        let mut accounts = BTreeMap::new();
+        while let Some(row) = rows.next()? {
+            let data: Vec<u8> = row.get(1)?;
+            accounts.insert(row.get(0)?, storage::decode(&data, vault_key.as_ref())?);
+        }
+        Ok((accounts, vault_key))
+    }
+
+    fn write(
+        &self,
+        accounts: &Accounts,
+        vault_key: Option<&VaultKey>,
+        _keep_previous: bool,
+    ) -> Result<()> {
+        let transaction = self.connection.unchecked_transaction()?;
+        transaction.execute("DELETE FROM accounts", [])?;
+        for (account_name, account) in accounts {
+            transaction.execute(
+                "INSERT INTO accounts (name, account) VALUES (?1, ?2)",
+                params![account_name, storage::encode(account, vault_key)?],
+            )?;
+        }
+        match vault_key {
+            Some(vault_key) => transaction.execute(
+                "INSERT OR REPLACE INTO metadata (key, value) VALUES ('vault', ?1)",
+                params![storage::seal_vault_marker(vault_key)?],
+            )?,
+            None => transaction.execute("DELETE FROM metadata WHERE key = 'vault'", [])?,
+        };
+        transaction.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    // Rows replaced by the write are left in free pages of the database until
+    // it is rebuilt
+    fn rekey(&self, accounts: &Accounts, vault_key: Option<&VaultKey>) -> Result<()> {
+        self.write(accounts, vault_key, false)?;
+        self.connection.execute_batch("VACUUM")?;
+        Ok(())
+    }
+
+    fn previous(&self, _version: usize, _vault_key: Option<&VaultKey>) -> Result<(Accounts, u64)> {
+        Err(Error::PreviousVersionsUnsupported("sqlite"))
+    }
+}
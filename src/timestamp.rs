@@ -0,0 +1,60 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+// Current time as seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+// Format seconds since the Unix epoch as a UTC date and time, e.g.
+// `2022-09-14 08:30:00 UTC`
+pub fn format(timestamp: u64) -> String {
+    let (days, seconds) = (timestamp / SECONDS_PER_DAY, timestamp % SECONDS_PER_DAY);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+// Date of the given number of days since the Unix epoch in the proleptic
+// Gregorian calendar, see http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format;
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format(951_782_400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(format(1_663_144_200), "2022-09-14 08:30:00 UTC");
+        assert_eq!(format(4_107_542_399), "2100-02-28 23:59:59 UTC");
+    }
+}
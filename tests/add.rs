@@ -1,8 +1,10 @@
 mod common;
 
-use crate::common::cloak;
+use crate::common::{cloak, write_qr_code};
 use assert_fs::fixture::TempDir;
+use image::{GrayImage, Luma};
 use predicates::prelude::*;
+use std::fs;
 
 #[test]
 fn no_subcommands() {
@@ -27,3 +29,263 @@ fn add_account() {
     assert!(predicates::path::is_file().eval(&temp_dir.path().join("accounts")));
     temp_dir.close().unwrap();
 }
+
+#[test]
+fn add_account_with_digits_period_and_epoch() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .arg("add")
+        .arg("test_app")
+        .arg("MFZWIYLTMRQXGZDRO5YWK4LXMVYXOZLRO4FA")
+        .arg("--digits=8")
+        .arg("--period=60")
+        .arg("--epoch=1000")
+        .assert()
+        .success()
+        .stdout("Account successfully created\n");
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("digits = 8"));
+    assert!(accounts.contains("period = 60"));
+    assert!(accounts.contains("t0 = 1000"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn add_account_with_zero_period() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .arg("add")
+        .arg("test_app")
+        .arg("MFZWIYLTMRQXGZDRO5YWK4LXMVYXOZLRO4FA")
+        .arg("--period=0")
+        .assert()
+        .failure();
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn add_account_from_uri() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .arg("add")
+        .arg("--uri")
+        .arg("otpauth://totp/ACME%20Co:john.doe%40email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=ACME%20Co&algorithm=SHA256&period=60")
+        .assert()
+        .success()
+        .stdout("Account successfully created\n");
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("[accounts.\"ACME Co:john.doe@email.com\"]"));
+    assert!(accounts.contains("hash_function = \"SHA256\""));
+    assert!(accounts.contains("period = 60"));
+    assert!(accounts.contains("issuer = \"ACME Co\""));
+    assert!(accounts.contains("label = \"john.doe@email.com\""));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn add_account_with_metadata() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .arg("add")
+        .arg("github-work")
+        .arg("4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6")
+        .arg("--issuer=GitHub")
+        .arg("--label=evans")
+        .arg("--tag=Work")
+        .arg("--tag=Code")
+        .arg("--notes=Recovery codes in the safe")
+        .assert()
+        .success()
+        .stdout("Account successfully created\n");
+    cloak(&temp_dir)
+        .arg("view")
+        .arg("github-work")
+        .arg("--verbose")
+        .assert()
+        .success()
+        .stdout(
+            predicates::str::is_match(
+                r"^\d{6}
+Issuer: GitHub
+Label: evans
+Tags: Work, Code
+Notes: Recovery codes in the safe
+Created: \d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2} UTC
+Last used: never
+$",
+            )
+            .unwrap(),
+        );
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("tags = [\"Work\", \"Code\"]"));
+    assert!(accounts.contains("created_at = "));
+    assert!(accounts.contains("last_used_at = "));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn add_account_from_uri_with_name() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .arg("add")
+        .arg("acme")
+        .arg("--uri")
+        .arg("otpauth://hotp/ACME?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&counter=3")
+        .assert()
+        .success()
+        .stdout("Account successfully created\n");
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("[accounts.acme]"));
+    assert!(accounts.contains("counter = 3"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn add_account_from_invalid_uri() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .arg("add")
+        .arg("--uri")
+        .arg("otpauth://totp/ACME")
+        .assert()
+        .success()
+        .stderr("Invalid otpauth URI: missing secret\n");
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn add_account_from_qr_code() {
+    let temp_dir = TempDir::new().unwrap();
+    let image = temp_dir.path().join("qr.png");
+    write_qr_code(
+        &image,
+        "otpauth://totp/ACME%20Co:john.doe%40email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=ACME%20Co&digits=8",
+    );
+    cloak(&temp_dir)
+        .arg("add")
+        .arg("--qr")
+        .arg(&image)
+        .assert()
+        .success()
+        .stdout("Account successfully created\n");
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("[accounts.\"ACME Co:john.doe@email.com\"]"));
+    assert!(accounts.contains("key = \"HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ\""));
+    assert!(accounts.contains("digits = 8"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn add_account_from_image_without_qr_code() {
+    let temp_dir = TempDir::new().unwrap();
+    let image = temp_dir.path().join("blank.png");
+    GrayImage::from_pixel(64, 64, Luma([255]))
+        .save(&image)
+        .unwrap();
+    cloak(&temp_dir)
+        .arg("add")
+        .arg("--qr")
+        .arg(&image)
+        .assert()
+        .success()
+        .stderr("No QR code found in the image\n");
+    temp_dir.close().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn add_account_with_key_command() {
+    let temp_dir = TempDir::new().unwrap();
+    let key_file = temp_dir.path().join("key");
+    fs::write(&key_file, "gezdgnbvgy3tqojqgezdgnbvgy3tqojq\n").unwrap();
+    let command = format!("cat {}", key_file.display());
+    cloak(&temp_dir)
+        .args(["add", "--hotp", "--key-command", &command, "rfc4226"])
+        .assert()
+        .success()
+        .stdout("Account successfully created\n");
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains(&format!(
+        "[accounts.rfc4226.external_key]\nkind = \"command\"\nsource = \"{}\"\n",
+        command
+    )));
+
+    cloak(&temp_dir)
+        .args(["view", "rfc4226"])
+        .assert()
+        .success()
+        .stdout("755224\n");
+    fs::remove_file(&key_file).unwrap();
+    cloak(&temp_dir)
+        .args(["view", "rfc4226"])
+        .assert()
+        .stderr(predicate::str::contains(format!(
+            "Could not read the key from '{}': exit status: 1",
+            command
+        )));
+    temp_dir.close().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn confirm_key_command_of_shared_account() {
+    let temp_dir = TempDir::new().unwrap();
+    let ran = temp_dir.path().join("ran");
+    let command = format!("touch {} && echo GEZDGNBVGY3TQOJQ", ran.display());
+    // Added to a shared vault by someone else
+    fs::write(
+        temp_dir.path().join("accounts"),
+        format!(
+            "version = 2\n[accounts.shared]\nkey = \"\"\ntotp = false\nhash_function = \"SHA1\"\n\
+             counter = 0\n[accounts.shared.external_key]\nkind = \"command\"\nsource = \"{}\"\n",
+            command
+        ),
+    )
+    .unwrap();
+    cloak(&temp_dir)
+        .args(["view", "shared"])
+        .write_stdin("n\n")
+        .assert()
+        .stderr(predicate::str::ends_with(format!(
+            "Run `{}` to read the secret key [N/y]? Could not read the key from '{}': \
+             the command was not confirmed\n",
+            command, command
+        )));
+    assert!(!ran.exists());
+
+    cloak(&temp_dir)
+        .args(["view", "shared"])
+        .write_stdin("y\n")
+        .assert()
+        .success();
+    assert!(ran.exists());
+    // Confirmed commands run without asking again
+    cloak(&temp_dir)
+        .args(["view", "shared"])
+        .assert()
+        .success()
+        .stderr("");
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn key_is_never_a_command() {
+    let temp_dir = TempDir::new().unwrap();
+    let ran = temp_dir.path().join("ran");
+    // Keys were once prefixed with `command:` to read them from a command
+    fs::write(
+        temp_dir.path().join("accounts"),
+        format!(
+            "version = 2\n[accounts.imported]\nkey = \"command:touch {}\"\ntotp = true\n\
+             hash_function = \"SHA1\"\n",
+            ran.display()
+        ),
+    )
+    .unwrap();
+    cloak(&temp_dir)
+        .args(["view", "imported"])
+        .write_stdin("y\n")
+        .assert();
+    assert!(!ran.exists());
+    temp_dir.close().unwrap();
+}
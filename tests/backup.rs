@@ -0,0 +1,197 @@
+mod common;
+
+use crate::common::{cloak, load_accounts_file};
+use assert_fs::fixture::TempDir;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+use std::fs;
+
+fn backup(temp_dir: &TempDir) {
+    cloak(temp_dir)
+        .arg("backup")
+        .arg(temp_dir.path().join("cloak.backup"))
+        .env("CLOAK_NEW_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout(format!(
+            "Backed up 1 accounts to {}\n",
+            temp_dir.path().join("cloak.backup").display()
+        ));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let path = temp_dir.path().join("cloak.backup");
+        let mode = fs::metadata(path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
+
+#[test]
+fn backup_and_restore_merge() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    backup(&temp_dir);
+    temp_dir
+        .child("accounts")
+        .write_str(
+            "
+[gitlab]
+key = \"JBSWY3DPEHPK3PXP\"
+totp = true
+hash_function = \"SHA1\"
+",
+        )
+        .unwrap();
+
+    cloak(&temp_dir)
+        .arg("restore")
+        .arg("--dry-run")
+        .arg(temp_dir.path().join("cloak.backup"))
+        .env("CLOAK_BACKUP_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with("+ test_app\n"));
+    cloak(&temp_dir)
+        .arg("restore")
+        .arg(temp_dir.path().join("cloak.backup"))
+        .env("CLOAK_BACKUP_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with(
+            "+ test_app\nRestored the backup, 1 accounts changed\n",
+        ));
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("[accounts.gitlab]"));
+    assert!(accounts.contains("[accounts.test_app]"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn restore_replace_asks_for_confirmation() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    backup(&temp_dir);
+    temp_dir
+        .child("accounts")
+        .write_str(
+            "
+[gitlab]
+key = \"JBSWY3DPEHPK3PXP\"
+totp = true
+hash_function = \"SHA1\"
+
+[test_app]
+key = \"MFZWIYLTMRQXGZCBBI\"
+totp = true
+hash_function = \"SHA256\"
+",
+        )
+        .unwrap();
+
+    let restore = |answer: &str| {
+        cloak(&temp_dir)
+            .arg("restore")
+            .arg("--mode=replace")
+            .arg(temp_dir.path().join("cloak.backup"))
+            .env("CLOAK_BACKUP_PASSPHRASE", "hunter2")
+            .write_stdin(answer)
+            .assert()
+            .success()
+    };
+    restore("n\n").stdout(predicate::str::contains("- gitlab\n~ test_app\n"));
+    assert!(fs::read_to_string(temp_dir.path().join("accounts"))
+        .unwrap()
+        .contains("[accounts.gitlab]"));
+
+    restore("y\n").stdout(predicate::str::ends_with(
+        "Restored the backup, 2 accounts changed\n",
+    ));
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(!accounts.contains("[accounts.gitlab]"));
+    assert!(accounts.contains("hash_function = \"SHA1\""));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn restore_with_wrong_passphrase() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    backup(&temp_dir);
+    cloak(&temp_dir)
+        .arg("restore")
+        .arg(temp_dir.path().join("cloak.backup"))
+        .env("CLOAK_BACKUP_PASSPHRASE", "hunter3")
+        .assert()
+        .success()
+        .stderr(
+            "Could not decrypt the cloak backup: wrong password or the file has been tampered with\n",
+        );
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn restore_previous_version() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("delete")
+        .arg("test_app")
+        .write_stdin("y\n")
+        .assert()
+        .success();
+    assert!(temp_dir.path().join("accounts.1").exists());
+
+    cloak(&temp_dir)
+        .arg("restore")
+        .arg("--previous")
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with(
+            "+ test_app\nRestored version 1 of the accounts file, 1 accounts changed\n",
+        ));
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("[accounts.test_app]"));
+
+    cloak(&temp_dir)
+        .arg("restore")
+        .arg("--previous=9")
+        .assert()
+        .success()
+        .stderr("There is no version 9 of the accounts file, up to 5 previous versions are kept\n");
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn restore_skips_key_commands() {
+    let temp_dir = TempDir::new().unwrap();
+    temp_dir
+        .child("accounts")
+        .write_str(
+            "version = 2\n[accounts.test_app]\nkey = \"MFZWIYLTMRQXGZCBBI\"\ntotp = true\n\
+             hash_function = \"SHA1\"\n[accounts.shared]\nkey = \"\"\ntotp = true\n\
+             hash_function = \"SHA1\"\n[accounts.shared.external_key]\nkind = \"command\"\n\
+             source = \"echo MFZWIYLTMRQXGZCBBI\"\n",
+        )
+        .unwrap();
+    for account in ["test_app", "shared"] {
+        cloak(&temp_dir)
+            .arg("delete")
+            .arg(account)
+            .write_stdin("y\n")
+            .assert()
+            .success();
+    }
+
+    cloak(&temp_dir)
+        .arg("restore")
+        .arg("--previous=2")
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with(
+            "+ test_app\nRestored version 2 of the accounts file, 1 accounts changed\n",
+        ))
+        .stderr("Skipping 'shared', its key is read from a command which is never restored\n");
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(!accounts.contains("[accounts.shared]"));
+    temp_dir.close().unwrap();
+}
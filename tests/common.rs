@@ -1,14 +1,19 @@
 extern crate assert_cmd;
 extern crate assert_fs;
 extern crate escargot;
+extern crate image;
 extern crate lazy_static;
 extern crate predicates;
+extern crate qrcode;
 
 use assert_cmd::Command;
 use assert_fs::fixture::TempDir;
 use assert_fs::prelude::*;
 use escargot::CargoRun;
+use image::{GrayImage, Luma};
 use lazy_static::lazy_static;
+use qrcode::{Color, QrCode};
+use std::path::Path;
 
 lazy_static! {
     static ref CARGO_RUN: CargoRun = escargot::CargoBuild::new()
@@ -22,6 +27,8 @@ lazy_static! {
 pub fn cloak(temp_dir: &TempDir) -> Command {
     let mut cmd = Command::from(CARGO_RUN.command());
     cmd.env("CLOAK_ACCOUNTS_DIR", temp_dir.path().to_str().unwrap());
+    // Keep the config file of the user out of the tests
+    cmd.env("XDG_CONFIG_HOME", temp_dir.path().join("config"));
     cmd
 }
 
@@ -39,3 +46,68 @@ hash_function = \"SHA1\"
         )
         .unwrap();
 }
+
+#[allow(dead_code)]
+pub fn load_hotp_accounts_file(temp_dir: &TempDir) {
+    temp_dir
+        .child("accounts")
+        .write_str(
+            "
+[hotp_app]
+key = \"4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6\"
+totp = false
+hash_function = \"SHA1\"
+counter = 0
+",
+        )
+        .unwrap();
+}
+
+#[allow(dead_code)]
+pub fn load_eight_digit_accounts_file(temp_dir: &TempDir) {
+    temp_dir
+        .child("accounts")
+        .write_str(
+            "
+[test_app]
+key = \"MFZWIYLTMRQXGZCBBI\"
+totp = true
+hash_function = \"SHA1\"
+digits = 8
+",
+        )
+        .unwrap();
+}
+
+#[allow(dead_code)]
+pub fn load_steam_accounts_file(temp_dir: &TempDir) {
+    temp_dir
+        .child("accounts")
+        .write_str(
+            "
+[\"Steam:gaben\"]
+key = \"JBSWY3DPEHPK3PXP\"
+totp = true
+hash_function = \"SHA1\"
+digits = 5
+steam = true
+",
+        )
+        .unwrap();
+}
+
+// Save a QR code of the given data as a PNG image, with 4 pixels per module
+#[allow(dead_code)]
+pub fn write_qr_code(path: &Path, data: &str) {
+    let code = QrCode::new(data).unwrap();
+    let width = code.width() as u32;
+    let colors = code.to_colors();
+    let image = GrayImage::from_fn((width + 8) * 4, (width + 8) * 4, |x, y| {
+        let (column, row) = (x / 4, y / 4);
+        let dark = (4..width + 4).contains(&column)
+            && (4..width + 4).contains(&row)
+            && colors[((row - 4) * width + column - 4) as usize] == Color::Dark;
+        Luma([if dark { 0 } else { 255 }])
+    });
+    image.save(path).unwrap();
+}
@@ -0,0 +1,199 @@
+mod common;
+
+use crate::common::{cloak, load_accounts_file};
+use assert_fs::fixture::TempDir;
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+use std::fs;
+
+#[test]
+fn set_and_get_settings() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .args(["config", "set", "algorithm", "sha256"])
+        .assert()
+        .success();
+    cloak(&temp_dir)
+        .args(["config", "set", "digits", "8"])
+        .assert()
+        .success();
+    cloak(&temp_dir)
+        .args(["config", "set", "digits", "12"])
+        .assert()
+        .stderr("Invalid number of digits 12, codes can have 1 to 9 digits\n");
+    let config = fs::read_to_string(temp_dir.path().join("config/cloak/config.toml")).unwrap();
+    assert_eq!(config, "algorithm = \"SHA256\"\ndigits = 8\n");
+
+    cloak(&temp_dir)
+        .args(["config", "get", "algorithm"])
+        .assert()
+        .success()
+        .stdout("SHA256\n");
+    cloak(&temp_dir)
+        .args(["config", "unset", "algorithm"])
+        .assert()
+        .success();
+    cloak(&temp_dir)
+        .args(["config", "list"])
+        .assert()
+        .success()
+        .stdout("digits = 8\n");
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn use_defaults_of_config() {
+    let temp_dir = TempDir::new().unwrap();
+    for (key, value) in [("algorithm", "SHA512"), ("digits", "8"), ("period", "60")] {
+        cloak(&temp_dir)
+            .args(["config", "set", key, value])
+            .assert()
+            .success();
+    }
+    cloak(&temp_dir)
+        .args(["add", "github", "JBSWY3DPEHPK3PXP"])
+        .assert()
+        .success();
+    cloak(&temp_dir)
+        .args(["add", "gitlab", "JBSWY3DPEHPK3PXP", "--digits", "6"])
+        .assert()
+        .success();
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("hash_function = \"SHA512\"\ndigits = 8\nperiod = 60\n"));
+    assert!(accounts.contains("digits = 6\n"));
+
+    cloak(&temp_dir)
+        .args(["config", "set", "export_format", "paper"])
+        .assert()
+        .success();
+    cloak(&temp_dir)
+        .arg("export")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("<!DOCTYPE html>"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn use_default_vault_of_config() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .args(["vault", "create", "work"])
+        .assert()
+        .success();
+    cloak(&temp_dir)
+        .args(["config", "set", "default_vault", "work"])
+        .assert()
+        .success();
+    cloak(&temp_dir)
+        .args(["vault", "list"])
+        .assert()
+        .success()
+        .stdout("  default\n* work\n");
+    cloak(&temp_dir)
+        .args(["--vault", "default", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("test_app"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn keep_unknown_settings() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    temp_dir
+        .child("config/cloak/config.toml")
+        .write_str("clipboard_history = false\ndigits = 8\n")
+        .unwrap();
+    cloak(&temp_dir)
+        .arg("list")
+        .assert()
+        .success()
+        .stderr(predicate::str::starts_with(
+            "Ignoring the unknown setting 'clipboard_history'",
+        ));
+    cloak(&temp_dir)
+        .args(["config", "set", "period", "60"])
+        .assert()
+        .success();
+    let config = fs::read_to_string(temp_dir.path().join("config/cloak/config.toml")).unwrap();
+    assert_eq!(
+        config,
+        "digits = 8\nperiod = 60\nclipboard_history = false\n"
+    );
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn fix_invalid_config_file() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    temp_dir
+        .child("config/cloak/config.toml")
+        .write_str("digits = 12\nperiod = 60\n")
+        .unwrap();
+    cloak(&temp_dir)
+        .arg("list")
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with(
+            "Could not read the config file",
+        ));
+    cloak(&temp_dir)
+        .args(["config", "unset", "digits"])
+        .assert()
+        .success()
+        .stderr(predicate::str::starts_with("Ignoring an invalid setting"));
+    cloak(&temp_dir).arg("list").assert().success().stderr("");
+    cloak(&temp_dir)
+        .args(["config", "list"])
+        .assert()
+        .success()
+        .stdout("period = 60\n");
+
+    // A file that can't be parsed is replaced, and kept next to the new one
+    temp_dir
+        .child("config/cloak/config.toml")
+        .write_str("digits = [\n")
+        .unwrap();
+    cloak(&temp_dir)
+        .args(["config", "set", "digits", "8"])
+        .assert()
+        .success();
+    cloak(&temp_dir)
+        .args(["config", "list"])
+        .assert()
+        .success()
+        .stdout("digits = 8\n");
+    let previous = fs::read_to_string(temp_dir.path().join("config/cloak/config.toml.1")).unwrap();
+    assert_eq!(previous, "digits = [\n");
+    temp_dir.close().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn move_accounts_to_data_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    temp_dir
+        .child("home/.cloak/accounts")
+        .write_str(
+            "[test_app]\nkey = \"MFZWIYLTMRQXGZCBBI\"\ntotp = true\nhash_function = \"SHA1\"\n",
+        )
+        .unwrap();
+    let data_dir = temp_dir.path().join("data/cloak");
+    for _ in 0..2 {
+        cloak(&temp_dir)
+            .env_remove("CLOAK_ACCOUNTS_DIR")
+            .env("HOME", temp_dir.path().join("home"))
+            .env("XDG_DATA_HOME", temp_dir.path().join("data"))
+            .arg("list")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("test_app"));
+    }
+    assert!(!temp_dir.path().join("home/.cloak").exists());
+    assert!(data_dir.join("accounts").is_file());
+    temp_dir.close().unwrap();
+}
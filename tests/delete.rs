@@ -15,9 +15,10 @@ fn delete_existent_account() {
         .assert()
         .success()
         .stdout("Are you sure you want to delete test_app [N/y]? Account successfully deleted\n");
-    assert!(fs::read_to_string(&temp_dir.path().join("accounts"))
-        .unwrap()
-        .is_empty());
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("accounts")).unwrap(),
+        "version = 2\n\n[accounts]\n"
+    );
     temp_dir.close().unwrap();
 }
 
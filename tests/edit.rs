@@ -0,0 +1,73 @@
+mod common;
+
+use crate::common::{cloak, load_accounts_file, load_hotp_accounts_file};
+use assert_fs::fixture::TempDir;
+use std::fs;
+
+#[test]
+fn edit_totp_account() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("edit")
+        .arg("test_app")
+        .arg("--algorithm=SHA256")
+        .arg("--digits=8")
+        .arg("--key=jbswy3dpehpk3pxp")
+        .assert()
+        .success()
+        .stdout(
+            "key: changed
+algorithm: SHA1 -> SHA256
+digits: 6 -> 8
+Account successfully updated
+",
+        );
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("key = \"JBSWY3DPEHPK3PXP\""));
+    assert!(accounts.contains("hash_function = \"SHA256\""));
+    assert!(accounts.contains("digits = 8"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn edit_hotp_counter() {
+    let temp_dir = TempDir::new().unwrap();
+    load_hotp_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("edit")
+        .arg("hotp_app")
+        .arg("--period=60")
+        .assert()
+        .success()
+        .stdout("The period only applies to time based accounts\n");
+    cloak(&temp_dir)
+        .arg("edit")
+        .arg("hotp_app")
+        .arg("--counter=42")
+        .assert()
+        .success()
+        .stdout("counter: 0 -> 42\nAccount successfully updated\n");
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("counter = 42"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn edit_without_changes() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("edit")
+        .arg("test_app")
+        .assert()
+        .failure();
+    cloak(&temp_dir)
+        .arg("edit")
+        .arg("test_app")
+        .arg("--algorithm=SHA1")
+        .assert()
+        .success()
+        .stdout("Nothing to change\n");
+    temp_dir.close().unwrap();
+}
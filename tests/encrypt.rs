@@ -0,0 +1,190 @@
+mod common;
+
+use crate::common::{cloak, load_accounts_file};
+use assert_fs::fixture::TempDir;
+use predicates::prelude::*;
+use std::fs;
+
+fn encrypt_accounts_file(temp_dir: &TempDir, passphrase: &str) {
+    cloak(temp_dir)
+        .arg("encrypt")
+        .env("CLOAK_NEW_PASSPHRASE", passphrase)
+        .assert()
+        .success()
+        .stdout("Accounts file successfully encrypted\n");
+}
+
+#[test]
+fn init_encrypted_accounts_file() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .arg("init")
+        .arg("--encrypt")
+        .env("CLOAK_NEW_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout("Accounts file successfully initialized\n");
+    cloak(&temp_dir)
+        .arg("add")
+        .arg("test_app")
+        .arg("MFZWIYLTMRQXGZDRO5YWK4LXMVYXOZLRO4FA")
+        .env("CLOAK_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout("Account successfully created\n");
+    let data = fs::read(temp_dir.path().join("accounts")).unwrap();
+    assert!(data.starts_with(b"CLOAKENC"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn init_already_initialized_accounts_file() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("init")
+        .arg("--encrypt")
+        .env("CLOAK_NEW_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout("Accounts file is already initialized\n");
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn encrypt_accounts_file_and_view() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    encrypt_accounts_file(&temp_dir, "hunter2");
+    let data = fs::read(temp_dir.path().join("accounts")).unwrap();
+    assert!(!String::from_utf8_lossy(&data).contains("MFZWIYLTMRQXGZCBBI"));
+    cloak(&temp_dir)
+        .arg("view")
+        .arg("test_app")
+        .env("CLOAK_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout(predicates::str::is_match(r"^\d{6}\n$").unwrap());
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn view_encrypted_accounts_file_with_wrong_passphrase() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    encrypt_accounts_file(&temp_dir, "hunter2");
+    cloak(&temp_dir)
+        .arg("view")
+        .arg("test_app")
+        .env("CLOAK_PASSPHRASE", "hunter3")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("wrong passphrase"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn decrypt_accounts_file() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    encrypt_accounts_file(&temp_dir, "hunter2");
+    cloak(&temp_dir)
+        .arg("decrypt")
+        .env("CLOAK_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout("Accounts file successfully decrypted\n");
+    assert!(fs::read_to_string(temp_dir.path().join("accounts"))
+        .unwrap()
+        .contains("MFZWIYLTMRQXGZCBBI"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn change_passphrase() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    encrypt_accounts_file(&temp_dir, "hunter2");
+    cloak(&temp_dir)
+        .arg("passwd")
+        .env("CLOAK_PASSPHRASE", "hunter2")
+        .env("CLOAK_NEW_PASSPHRASE", "correct horse")
+        .assert()
+        .success()
+        .stdout("Passphrase successfully changed\n");
+    cloak(&temp_dir)
+        .arg("list")
+        .env("CLOAK_PASSPHRASE", "hunter2")
+        .assert()
+        .failure();
+    cloak(&temp_dir)
+        .arg("list")
+        .env("CLOAK_PASSPHRASE", "correct horse")
+        .assert()
+        .success();
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn passwd_plaintext_accounts_file() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("passwd")
+        .assert()
+        .success()
+        .stdout("Accounts file is not encrypted. Use `cloak encrypt` to encrypt it.\n");
+    temp_dir.close().unwrap();
+}
+
+// Names of the files of the vault holding the seed of `load_accounts_file`
+fn files_with_seed(temp_dir: &TempDir) -> Vec<String> {
+    fs::read_dir(temp_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            String::from_utf8_lossy(&fs::read(path).unwrap()).contains("MFZWIYLTMRQXGZCBBI")
+        })
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect()
+}
+
+#[test]
+fn no_plaintext_copy_left_after_encrypt() {
+    let temp_dir = TempDir::new().unwrap();
+    // The file of version 1 is upgraded, keeping a backup, and a change keeps a
+    // previous version
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .args(["add", "gitlab", "JBSWY3DPEHPK3PXP"])
+        .assert()
+        .success();
+    assert!(temp_dir.path().join("accounts.v1.bak").is_file());
+    assert!(temp_dir.path().join("accounts.1").is_file());
+
+    encrypt_accounts_file(&temp_dir, "hunter2");
+    assert_eq!(files_with_seed(&temp_dir), Vec::<String>::new());
+    assert!(!temp_dir.path().join("accounts.1").exists());
+    assert!(!temp_dir.path().join("accounts.v1.bak").exists());
+
+    // Versions encrypted with the previous passphrase are dropped as well
+    cloak(&temp_dir)
+        .args(["add", "bitbucket", "JBSWY3DPEHPK3PXP"])
+        .env("CLOAK_PASSPHRASE", "hunter2")
+        .assert()
+        .success();
+    cloak(&temp_dir)
+        .arg("passwd")
+        .env("CLOAK_PASSPHRASE", "hunter2")
+        .env("CLOAK_NEW_PASSPHRASE", "correct horse")
+        .assert()
+        .success();
+    assert!(!temp_dir.path().join("accounts.1").exists());
+    cloak(&temp_dir)
+        .args(["view", "test_app"])
+        .env("CLOAK_PASSPHRASE", "correct horse")
+        .assert()
+        .success();
+    temp_dir.close().unwrap();
+}
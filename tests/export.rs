@@ -0,0 +1,290 @@
+mod common;
+
+use crate::common::{cloak, load_accounts_file, load_hotp_accounts_file, load_steam_accounts_file};
+use assert_fs::fixture::TempDir;
+use predicates::prelude::*;
+use std::fs;
+
+#[test]
+fn export_account_as_uri() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("export")
+        .arg("--uri")
+        .arg("test_app")
+        .assert()
+        .success()
+        .stdout(
+            "otpauth://totp/test_app?secret=MFZWIYLTMRQXGZCBBI&algorithm=SHA1&digits=6&period=30\n",
+        );
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn export_non_existent_account() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("export")
+        .arg("404app")
+        .assert()
+        .success()
+        .stdout("Account with the name '404app' does not exist. Consider adding it.\n");
+    temp_dir.close().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn export_to_private_file() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    let path = temp_dir.path().join("accounts.txt");
+    fs::write(&path, "").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+    cloak(&temp_dir)
+        .args(["export", "--output", path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(format!("Exported 1 accounts to {}\n", path.display()));
+    assert!(fs::read_to_string(&path).unwrap().starts_with("otpauth://"));
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn export_account_with_epoch() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .args(["add", "github", "JBSWY3DPEHPK3PXP", "--epoch=1000"])
+        .assert()
+        .success();
+    for format in ["uri", "paper", "aegis"] {
+        cloak(&temp_dir)
+            .args(["export", "--format", format])
+            .assert()
+            .stdout("")
+            .stderr(predicate::str::starts_with(
+                "Account 'github' counts time steps from 1000",
+            ));
+    }
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn export_aegis_unsupported_hash_function() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .args([
+            "add",
+            "github",
+            "JBSWY3DPEHPK3PXP",
+            "--algorithm=SHA512_256",
+        ])
+        .assert()
+        .success();
+    cloak(&temp_dir)
+        .args(["export", "--format=aegis"])
+        .assert()
+        .stdout("")
+        .stderr("Account 'github' uses SHA512_256, which Aegis doesn't support\n");
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn export_and_import_uri_roundtrip() {
+    let temp_dir = TempDir::new().unwrap();
+    load_hotp_accounts_file(&temp_dir);
+    let output = cloak(&temp_dir)
+        .arg("export")
+        .arg("--format=uri")
+        .output()
+        .unwrap();
+    let uri = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        uri,
+        "otpauth://hotp/hotp_app?secret=4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6&algorithm=SHA1&digits=6&counter=0\n"
+    );
+
+    let other_dir = TempDir::new().unwrap();
+    cloak(&other_dir)
+        .arg("add")
+        .arg("--uri")
+        .arg(uri.trim())
+        .assert()
+        .success()
+        .stdout("Account successfully created\n");
+    cloak(&other_dir)
+        .arg("view")
+        .arg("hotp_app")
+        .assert()
+        .success()
+        .stdout("852241\n");
+    temp_dir.close().unwrap();
+    other_dir.close().unwrap();
+}
+
+#[test]
+fn export_and_import_aegis_roundtrip() {
+    let temp_dir = TempDir::new().unwrap();
+    load_steam_accounts_file(&temp_dir);
+    let vault = temp_dir.path().join("aegis.json");
+    let output = cloak(&temp_dir)
+        .arg("export")
+        .arg("--format=aegis")
+        .output()
+        .unwrap();
+    fs::write(&vault, output.stdout).unwrap();
+
+    let other_dir = TempDir::new().unwrap();
+    cloak(&other_dir)
+        .arg("import")
+        .arg("--format=aegis")
+        .arg(&vault)
+        .assert()
+        .success()
+        .stdout("Imported 'Steam:gaben'\nImported 1 of 1 accounts\n");
+    cloak(&other_dir)
+        .arg("view")
+        .arg("Steam:gaben")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match("^[23456789BCDFGHJKMNPQRTVWXY]{5}\n$").unwrap());
+    temp_dir.close().unwrap();
+    other_dir.close().unwrap();
+}
+
+#[test]
+fn export_and_import_encrypted_aegis() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    let vault = temp_dir.path().join("aegis.json");
+    let output = cloak(&temp_dir)
+        .arg("export")
+        .arg("--format=aegis")
+        .arg("--encrypt")
+        .env("CLOAK_NEW_PASSPHRASE", "hunter2")
+        .output()
+        .unwrap();
+    fs::write(&vault, output.stdout).unwrap();
+    assert!(!fs::read_to_string(&vault)
+        .unwrap()
+        .contains("MFZWIYLTMRQXGZCBBI"));
+
+    let other_dir = TempDir::new().unwrap();
+    cloak(&other_dir)
+        .arg("import")
+        .arg("--format=aegis")
+        .arg(&vault)
+        .env("CLOAK_BACKUP_PASSPHRASE", "hunter3")
+        .assert()
+        .success()
+        .stderr(
+            "Could not decrypt the Aegis backup: wrong password or the file has been tampered with\n",
+        );
+    cloak(&other_dir)
+        .arg("import")
+        .arg("--format=aegis")
+        .arg(&vault)
+        .env("CLOAK_BACKUP_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout("Imported 'test_app'\nImported 1 of 1 accounts\n");
+    temp_dir.close().unwrap();
+    other_dir.close().unwrap();
+}
+
+#[test]
+fn export_encrypted_uri() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("export")
+        .arg("--uri")
+        .arg("--encrypt")
+        .assert()
+        .success()
+        .stderr("Encryption is not supported by the uri format\n");
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn export_and_import_kdbx() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    let database = temp_dir.path().join("cloak.kdbx");
+    let keyfile = temp_dir.path().join("cloak.keyx");
+    fs::write(&keyfile, "secret key file").unwrap();
+    cloak(&temp_dir)
+        .arg("export")
+        .arg("--format=kdbx")
+        .arg("--output")
+        .arg(&database)
+        .arg("--keyfile")
+        .arg(&keyfile)
+        .env("CLOAK_NEW_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout(format!("Exported 1 accounts to {}\n", database.display()));
+
+    let other_dir = TempDir::new().unwrap();
+    cloak(&other_dir)
+        .arg("import")
+        .arg("--format=kdbx")
+        .arg(&database)
+        .env("CLOAK_BACKUP_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stderr(
+            "Could not decrypt the KeePass backup: wrong password or the file has been tampered with\n",
+        );
+    cloak(&other_dir)
+        .arg("import")
+        .arg("--format=kdbx")
+        .arg("--keyfile")
+        .arg(&keyfile)
+        .arg(&database)
+        .env("CLOAK_BACKUP_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout("Imported 'test_app'\nImported 1 of 1 accounts\n");
+    temp_dir.close().unwrap();
+    other_dir.close().unwrap();
+}
+
+#[test]
+fn export_kdbx_to_stdout() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("export")
+        .arg("--format=kdbx")
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("The kdbx format is binary, use --output to write it to a file\n");
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn export_paper() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    let output = temp_dir.path().join("paper.html");
+    cloak(&temp_dir)
+        .arg("export")
+        .arg("--format=paper")
+        .arg("--output")
+        .arg(&output)
+        .assert()
+        .success()
+        .stdout(format!("Exported 1 accounts to {}\n", output.display()));
+    let html = fs::read_to_string(&output).unwrap();
+    assert!(html.contains("<div class=\"name\">test_app</div>"));
+    assert!(html.contains("MFZW IYLT MRQX GZCB BI"));
+    assert!(html.contains("otpauth://totp/test_app?secret=MFZWIYLTMRQXGZCBBI"));
+    temp_dir.close().unwrap();
+}
@@ -0,0 +1,252 @@
+#!/usr/bin/env python3
+# Writes the KeePass databases used by tests/import.rs, without any code of
+# cloak: the KDBX 4 format is implemented here from its specification, with
+# the Argon2 and ciphers of the `cryptography` package (OpenSSL). The XML
+# document is laid out as KeePassXC 2.7 saves it. Both databases are
+# unlocked with the password `hunter2`.
+#
+#     python3 tests/fixtures/kdbx.py tests/fixtures
+
+import base64
+import gzip
+import hashlib
+import hmac
+import os
+import struct
+import sys
+import uuid
+
+from cryptography.hazmat.primitives import padding
+from cryptography.hazmat.primitives.ciphers import Cipher, algorithms, modes
+from cryptography.hazmat.primitives.kdf.argon2 import Argon2d, Argon2id
+
+PASSWORD = b"hunter2"
+
+AES256_CBC = uuid.UUID("31c1f2e6-bf71-4350-be58-05216afc5aff").bytes
+CHACHA20 = uuid.UUID("d6038a2b-8b6f-4cb5-a524-339a31dbb59a").bytes
+ARGON2D = uuid.UUID("ef636ddf-8c29-444b-91f7-a9a403e30a0c").bytes
+ARGON2ID = uuid.UUID("9e298b19-56db-4773-b23d-fc3ec6f0a1e6").bytes
+
+# Smaller than the defaults of KeePassXC to keep the tests fast
+ARGON2_MEMORY = 8 * 1024 * 1024
+ARGON2_ITERATIONS = 2
+ARGON2_PARALLELISM = 2
+
+ENTRIES = [
+    # group, title, username, otp, tags
+    (None, "GitHub", "evans",
+     "otpauth://totp/GitHub:evans?secret=4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6&period=30&digits=6&issuer=GitHub",
+     ""),
+    ("Work", "GitLab", "evans@example.com",
+     "otpauth://totp/GitLab:evans%40example.com?secret=JBSWY3DPEHPK3PXP&period=60&digits=8&issuer=GitLab&algorithm=SHA256",
+     "2fa;admin"),
+    ("Games", "Steam", "gaben",
+     "otpauth://totp/Steam:gaben?secret=JRZCL47CMXVOQMNPZR2F7J4RGI&period=30&digits=5&issuer=Steam&encoder=steam",
+     ""),
+    (None, "Email", "evans", None, ""),
+    ("Recycle Bin", "Deleted", "evans",
+     "otpauth://totp/Deleted:evans?secret=MFZWIYLTMRQXGZCBBI&period=30&digits=6&issuer=Deleted",
+     ""),
+]
+
+TIMES = """<Times>
+<CreationTime>Bz/b2g4AAAA=</CreationTime>
+<LastModificationTime>Bz/b2g4AAAA=</LastModificationTime>
+<LastAccessTime>Bz/b2g4AAAA=</LastAccessTime>
+<ExpiryTime>Bz/b2g4AAAA=</ExpiryTime>
+<Expires>False</Expires>
+<UsageCount>0</UsageCount>
+<LocationChanged>Bz/b2g4AAAA=</LocationChanged>
+</Times>"""
+
+
+def b64(data):
+    return base64.b64encode(data).decode()
+
+
+def escape(text):
+    return text.replace("&", "&amp;").replace("<", "&lt;").replace(">", "&gt;")
+
+
+class InnerStream:
+    """ChaCha20 protecting the values marked as protected, in document order"""
+
+    def __init__(self, key):
+        digest = hashlib.sha512(key).digest()
+        nonce = b"\0" * 4 + digest[32:44]
+        self.cipher = Cipher(algorithms.ChaCha20(digest[:32], nonce), None).encryptor()
+
+    def protect(self, value):
+        return b64(self.cipher.update(value.encode()))
+
+
+def string(stream, key, value, protected=False):
+    if protected:
+        return '<String><Key>%s</Key><Value Protected="True">%s</Value></String>' % (
+            key, stream.protect(value))
+    if not value:
+        return "<String><Key>%s</Key><Value/></String>" % key
+    return "<String><Key>%s</Key><Value>%s</Value></String>" % (key, escape(value))
+
+
+def entry(stream, title, username, otp, tags):
+    # KeePassXC writes the attributes sorted by key
+    strings = [string(stream, "Notes", ""), string(stream, "Password", "correct horse", True),
+               string(stream, "Title", title), string(stream, "URL", ""),
+               string(stream, "UserName", username)]
+    if otp:
+        strings.insert(0, string(stream, "otp", otp, True))
+        strings.sort(key=lambda s: s.split("</Key>")[0])
+    return """<Entry>
+<UUID>%s</UUID>
+<IconID>0</IconID>
+<ForegroundColor/>
+<BackgroundColor/>
+<OverrideURL/>
+<Tags>%s</Tags>
+%s
+%s
+<AutoType><Enabled>True</Enabled><DataTransferObfuscation>0</DataTransferObfuscation></AutoType>
+<History/>
+</Entry>""" % (b64(os.urandom(16)), escape(tags), TIMES, "\n".join(strings))
+
+
+def group(name, group_uuid, children):
+    return """<Group>
+<UUID>%s</UUID>
+<Name>%s</Name>
+<Notes/>
+<IconID>48</IconID>
+%s
+<IsExpanded>True</IsExpanded>
+<DefaultAutoTypeSequence/>
+<EnableAutoType>null</EnableAutoType>
+<EnableSearching>null</EnableSearching>
+<LastTopVisibleEntry>AAAAAAAAAAAAAAAAAAAAAA==</LastTopVisibleEntry>
+%s
+</Group>""" % (b64(group_uuid), escape(name), TIMES, "\n".join(children))
+
+
+def document(stream):
+    recycle_bin = os.urandom(16)
+    # Values are protected in document order, entries come before the groups
+    root_entries = [entry(stream, *fields[1:]) for fields in ENTRIES if fields[0] is None]
+    groups = {}
+    for group_name, *fields in ENTRIES:
+        if group_name is not None:
+            groups.setdefault(group_name, []).append(entry(stream, *fields))
+    subgroups = [group(name, recycle_bin if name == "Recycle Bin" else os.urandom(16), children)
+                 for name, children in groups.items()]
+    return """<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<KeePassFile>
+<Meta>
+<Generator>KeePassXC</Generator>
+<DatabaseName>Passwords</DatabaseName>
+<DatabaseNameChanged>Bz/b2g4AAAA=</DatabaseNameChanged>
+<DatabaseDescription/>
+<DefaultUserName/>
+<MaintenanceHistoryDays>365</MaintenanceHistoryDays>
+<Color/>
+<MasterKeyChanged>Bz/b2g4AAAA=</MasterKeyChanged>
+<MasterKeyChangeRec>-1</MasterKeyChangeRec>
+<MasterKeyChangeForce>-1</MasterKeyChangeForce>
+<MemoryProtection>
+<ProtectTitle>False</ProtectTitle>
+<ProtectUserName>False</ProtectUserName>
+<ProtectPassword>True</ProtectPassword>
+<ProtectURL>False</ProtectURL>
+<ProtectNotes>False</ProtectNotes>
+</MemoryProtection>
+<CustomIcons/>
+<RecycleBinEnabled>True</RecycleBinEnabled>
+<RecycleBinUUID>%s</RecycleBinUUID>
+<RecycleBinChanged>Bz/b2g4AAAA=</RecycleBinChanged>
+<EntryTemplatesGroup>AAAAAAAAAAAAAAAAAAAAAA==</EntryTemplatesGroup>
+<EntryTemplatesGroupChanged>Bz/b2g4AAAA=</EntryTemplatesGroupChanged>
+<LastSelectedGroup>AAAAAAAAAAAAAAAAAAAAAA==</LastSelectedGroup>
+<LastTopVisibleGroup>AAAAAAAAAAAAAAAAAAAAAA==</LastTopVisibleGroup>
+<HistoryMaxItems>10</HistoryMaxItems>
+<HistoryMaxSize>6291456</HistoryMaxSize>
+<SettingsChanged>Bz/b2g4AAAA=</SettingsChanged>
+<CustomData/>
+</Meta>
+<Root>
+%s
+<DeletedObjects/>
+</Root>
+</KeePassFile>
+""" % (b64(recycle_bin), group("Root", os.urandom(16), root_entries + subgroups))
+
+
+def variant_dictionary(items):
+    data = struct.pack("<H", 0x0100)
+    for kind, key, value in items:
+        key = key.encode()
+        data += struct.pack("<BI", kind, len(key)) + key
+        if kind == 0x05:
+            value = struct.pack("<Q", value)
+        elif kind == 0x04:
+            value = struct.pack("<I", value)
+        data += struct.pack("<I", len(value)) + value
+    return data + b"\0"
+
+
+def field(field_id, value):
+    return struct.pack("<BI", field_id, len(value)) + value
+
+
+def database(cipher_id, kdf_id):
+    master_seed = os.urandom(32)
+    iv = os.urandom(16 if cipher_id == AES256_CBC else 12)
+    salt = os.urandom(32)
+    kdf = variant_dictionary([
+        (0x42, "$UUID", kdf_id),
+        (0x05, "I", ARGON2_ITERATIONS),
+        (0x05, "M", ARGON2_MEMORY),
+        (0x04, "P", ARGON2_PARALLELISM),
+        (0x42, "S", salt),
+        (0x04, "V", 0x13),
+    ])
+    header = (bytes.fromhex("03d9a29a67fb4bb5") + struct.pack("<HH", 0, 4)
+              + field(2, cipher_id) + field(3, struct.pack("<I", 1)) + field(4, master_seed)
+              + field(7, iv) + field(11, kdf) + field(0, b"\r\n\r\n"))
+
+    composite = hashlib.sha256(hashlib.sha256(PASSWORD).digest()).digest()
+    argon2 = Argon2d if kdf_id == ARGON2D else Argon2id
+    transformed = argon2(salt=salt, length=32, iterations=ARGON2_ITERATIONS,
+                         lanes=ARGON2_PARALLELISM,
+                         memory_cost=ARGON2_MEMORY // 1024).derive(composite)
+    hmac_base = hashlib.sha512(master_seed + transformed + b"\x01").digest()
+
+    def block_key(index):
+        return hashlib.sha512(struct.pack("<Q", index) + hmac_base).digest()
+
+    stream_key = os.urandom(64)
+    inner = (field(1, struct.pack("<I", 3)) + field(2, stream_key) + field(0, b""))
+    plaintext = gzip.compress(inner + document(InnerStream(stream_key)).encode())
+
+    cipher_key = hashlib.sha256(master_seed + transformed).digest()
+    if cipher_id == AES256_CBC:
+        padder = padding.PKCS7(128).padder()
+        padded = padder.update(plaintext) + padder.finalize()
+        encryptor = Cipher(algorithms.AES(cipher_key), modes.CBC(iv)).encryptor()
+    else:
+        padded = plaintext
+        encryptor = Cipher(algorithms.ChaCha20(cipher_key, b"\0" * 4 + iv), None).encryptor()
+    ciphertext = encryptor.update(padded) + encryptor.finalize()
+
+    data = header + hashlib.sha256(header).digest()
+    data += hmac.new(block_key(2**64 - 1), header, hashlib.sha256).digest()
+    for index, block in enumerate([ciphertext, b""]):
+        length = struct.pack("<I", len(block))
+        message = struct.pack("<Q", index) + length + block
+        data += hmac.new(block_key(index), message, hashlib.sha256).digest() + length + block
+    return data
+
+
+if __name__ == "__main__":
+    directory = sys.argv[1]
+    for name, cipher_id, kdf_id in [("argon2d-aes.kdbx", AES256_CBC, ARGON2D),
+                                     ("argon2id-chacha20.kdbx", CHACHA20, ARGON2ID)]:
+        with open(os.path.join(directory, name), "wb") as file:
+            file.write(database(cipher_id, kdf_id))
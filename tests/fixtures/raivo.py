@@ -0,0 +1,75 @@
+#!/usr/bin/env python3
+# Writes the Raivo OTP export used by src/formats/raivo.rs, without any code
+# of cloak or of the zip crate. Raivo exports through SSZipArchive, which
+# encrypts the archive with WinZip AES-256 (AE-2); the format is implemented
+# here from its specification, with the AES of the `cryptography` package
+# (OpenSSL). The archive is encrypted with the password `hunter2`.
+#
+#     python3 tests/fixtures/raivo.py tests/fixtures
+
+import hashlib
+import hmac
+import json
+import os
+import struct
+import sys
+import zlib
+
+from cryptography.hazmat.primitives.ciphers import Cipher, algorithms, modes
+
+PASSWORD = b"hunter2"
+NAME = b"raivo-otp-export.json"
+
+# Raivo writes every value as a string, numbers included
+ENTRIES = [
+    {"pinned": "false", "issuer": "GitHub", "iconValue": "", "counter": "0", "account": "evans",
+     "iconType": "", "algorithm": "SHA1", "kind": "TOTP", "secret": "4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6",
+     "timer": "30", "digits": "6"},
+    {"pinned": "true", "issuer": "SPDX", "iconValue": "", "counter": "12", "account": "james",
+     "iconType": "", "algorithm": "SHA256", "kind": "HOTP", "secret": "5OM4WOOGPLQEF6UGN3CPEOOLWU",
+     "timer": "30", "digits": "8"},
+]
+
+# 1980-01-01 00:00 in MS-DOS format
+DOS_TIME = 0
+DOS_DATE = (0 << 9) | (1 << 5) | 1
+
+
+def aes_ctr(key, data):
+    # WinZip AES counts blocks in little endian, starting at 1
+    encryptor = Cipher(algorithms.AES(key), modes.ECB()).encryptor()
+    output = bytearray()
+    for index in range(0, len(data), 16):
+        counter = (index // 16 + 1).to_bytes(16, "little")
+        keystream = encryptor.update(counter)
+        output += bytes(a ^ b for a, b in zip(data[index:index + 16], keystream))
+    return bytes(output)
+
+
+def archive():
+    compressor = zlib.compressobj(9, zlib.DEFLATED, -15)
+    plaintext = json.dumps(ENTRIES).encode()
+    compressed = compressor.compress(plaintext) + compressor.flush()
+
+    salt = os.urandom(16)
+    derived = hashlib.pbkdf2_hmac("sha1", PASSWORD, salt, 1000, 66)
+    cipher_key, mac_key, verifier = derived[:32], derived[32:64], derived[64:]
+    ciphertext = aes_ctr(cipher_key, compressed)
+    mac = hmac.new(mac_key, ciphertext, hashlib.sha1).digest()[:10]
+    data = salt + verifier + ciphertext + mac
+
+    # AE-2, AES-256, deflated. AE-2 leaves the CRC out, the MAC covers it.
+    extra = struct.pack("<HHH2sBH", 0x9901, 7, 2, b"AE", 3, 8)
+    fields = struct.pack("<HHHHHIIIHH", 51, 1, 99, DOS_TIME, DOS_DATE, 0, len(data),
+                         len(plaintext), len(NAME), len(extra))
+    local = b"PK\x03\x04" + fields + NAME + extra
+    central = (b"PK\x01\x02" + struct.pack("<H", 51) + fields
+               + struct.pack("<HHHII", 0, 0, 0, 0o100644 << 16, 0) + NAME + extra)
+    end = b"PK\x05\x06" + struct.pack("<HHHHIIH", 0, 0, 1, 1, len(central),
+                                      len(local) + len(data), 0)
+    return local + data + central + end
+
+
+if __name__ == "__main__":
+    with open(os.path.join(sys.argv[1], "raivo-aes.zip"), "wb") as file:
+        file.write(archive())
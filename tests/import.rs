@@ -0,0 +1,379 @@
+mod common;
+
+use crate::common::{cloak, write_qr_code};
+use assert_fs::fixture::TempDir;
+use assert_fs::prelude::*;
+use data_encoding::BASE64;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Export of three accounts "Test1:test1@example1.com" to "Test3:test3@example3.com"
+const MIGRATION_URI: &str = "otpauth-migration://offline?data=CjEKCkhlbGxvId6tvu8SGFRlc3QxOnRlc3QxQGV4YW1wbGUxLmNvbRoFVGVzdDEgASgBMAIKMQoKSGVsbG8h3q2%2B7xIYVGVzdDI6dGVzdDJAZXhhbXBsZTIuY29tGgVUZXN0MiABKAEwAgoxCgpIZWxsbyHerb7vEhhUZXN0Mzp0ZXN0M0BleGFtcGxlMy5jb20aBVRlc3QzIAEoATACEAEYASAAKICRs%2BQH";
+
+// Build the URI of one batch of an export, holding a single TOTP account
+fn batch_uri(account_name: &str, batch_index: u8, batch_size: u8) -> String {
+    let mut account = vec![0x0a, 0x05];
+    account.extend_from_slice(b"hello");
+    account.extend_from_slice(&[0x12, account_name.len() as u8]);
+    account.extend_from_slice(account_name.as_bytes());
+    let mut data = vec![0x0a, account.len() as u8];
+    data.extend_from_slice(&account);
+    data.extend_from_slice(&[0x18, batch_size, 0x20, batch_index, 0x28, 0x2a]);
+    format!(
+        "otpauth-migration://offline?data={}",
+        BASE64.encode(&data).replace('+', "%2B").replace('/', "%2F")
+    )
+}
+
+#[test]
+fn import_google_migration_uri() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .arg("import")
+        .arg("--format=google-migration")
+        .arg(MIGRATION_URI)
+        .assert()
+        .success()
+        .stdout(
+            "Imported 'Test1:test1@example1.com'
+Imported 'Test2:test2@example2.com'
+Imported 'Test3:test3@example3.com'
+Imported 3 of 3 accounts
+",
+        );
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("[accounts.\"Test2:test2@example2.com\"]"));
+    assert!(accounts.contains("key = \"JBSWY3DPEHPK3PXP\""));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn import_google_migration_unsupported_account() {
+    // Accounts "first" and "last" around an MD5 account "md5"
+    let uri = "otpauth-migration://offline?data=ChIKBWhlbGxvEgVmaXJzdCABMAIKEAoFaGVsbG8SA21kNSAEMAIKEQoFaGVsbG8SBGxhc3QgATAC";
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .args(["import", "--format=google-migration", "--dry-run", uri])
+        .assert()
+        .success()
+        .stdout(
+            "NAME   TYPE  ALGORITHM  DIGITS  STATUS
+first  TOTP  SHA1       6       import
+md5    -     -          -       skip: Unsupported hash function 'MD5'
+last   TOTP  SHA1       6       import
+2 of 3 accounts would be imported (1 skipped)
+",
+        );
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn import_google_migration_qr_code() {
+    let temp_dir = TempDir::new().unwrap();
+    let image = temp_dir.path().join("export.png");
+    write_qr_code(&image, MIGRATION_URI);
+    cloak(&temp_dir)
+        .arg("import")
+        .arg("-f")
+        .arg("google-migration")
+        .arg(&image)
+        .assert()
+        .success()
+        .stdout(predicates::str::ends_with("Imported 3 of 3 accounts\n"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn import_google_migration_name_collision() {
+    let temp_dir = TempDir::new().unwrap();
+    temp_dir
+        .child("accounts")
+        .write_str(
+            "
+[\"Test2:test2@example2.com\"]
+key = \"MFZWIYLTMRQXGZCBBI\"
+totp = true
+hash_function = \"SHA1\"
+",
+        )
+        .unwrap();
+    cloak(&temp_dir)
+        .arg("import")
+        .arg("--format=google-migration")
+        .arg(MIGRATION_URI)
+        .assert()
+        .success()
+        .stdout(
+            "Imported 'Test1:test1@example1.com'
+Skipped 'Test2:test2@example2.com': an account with this name already exists
+Imported 'Test3:test3@example3.com'
+Imported 2 of 3 accounts (1 conflicting)
+",
+        );
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("key = \"MFZWIYLTMRQXGZCBBI\""));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn import_google_migration_batches() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .arg("import")
+        .arg("--format=google-migration")
+        .arg(batch_uri("second", 1, 3))
+        .arg(batch_uri("first", 0, 3))
+        .arg(batch_uri("first", 0, 3))
+        .assert()
+        .success()
+        .stdout("Imported 'first'\nImported 'second'\nImported 2 of 2 accounts\n")
+        .stderr("Missing QR code 3 of 3 of the export, the accounts it holds are not imported\n");
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn import_invalid_google_migration_uri() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .arg("import")
+        .arg("--format=google-migration")
+        .arg("otpauth-migration://offline?secret=ABC")
+        .assert()
+        .success()
+        .stderr("Invalid Google Authenticator export: missing data\n");
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn import_aegis_vault() {
+    let temp_dir = TempDir::new().unwrap();
+    temp_dir
+        .child("aegis.json")
+        .write_str(
+            r#"{
+    "version": 1,
+    "header": {"slots": null, "params": null},
+    "db": {
+        "version": 2,
+        "entries": [
+            {
+                "type": "totp",
+                "uuid": "3ae6f1ad-2e65-4ed2-a953-1ec0dff2386d",
+                "name": "evans",
+                "issuer": "GitHub",
+                "icon": null,
+                "info": {"secret": "4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6", "algo": "SHA1", "digits": 6, "period": 30},
+                "group": "Work"
+            },
+            {
+                "type": "yandex",
+                "uuid": "1c3a0e6f-9b2d-4f7a-8e5c-6d4b2a1f0e9d",
+                "name": "evans",
+                "issuer": "Yandex",
+                "info": {"secret": "JBSWY3DPEHPK3PXP", "algo": "SHA256", "digits": 8, "period": 30, "pin": "1234"}
+            }
+        ]
+    }
+}"#,
+        )
+        .unwrap();
+    cloak(&temp_dir)
+        .arg("import")
+        .arg("--format=aegis")
+        .arg(temp_dir.path().join("aegis.json"))
+        .assert()
+        .success()
+        .stdout("Imported 'GitHub:evans'\nImported 1 of 2 accounts (1 skipped)\n")
+        .stderr("Skipped 'Yandex:evans': unsupported type 'yandex'\n");
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("tags = [\"Work\"]"));
+    temp_dir.close().unwrap();
+}
+
+const ANDOTP_BACKUP: &str = r#"[
+    {"secret": "4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6", "issuer": "GitHub", "label": "evans",
+     "digits": 6, "type": "TOTP", "algorithm": "SHA1", "period": 30, "tags": ["Work"]},
+    {"secret": "JBSWY3DPEHPK3PXP", "issuer": "", "label": "hotp_app", "digits": 8,
+     "type": "HOTP", "algorithm": "SHA256", "counter": 2, "tags": []},
+    {"secret": "e3152afee62599c8", "issuer": "", "label": "motp_app", "digits": 6,
+     "type": "MOTP", "algorithm": "MD5", "period": 10, "tags": []}
+]"#;
+
+#[test]
+fn import_andotp_backup() {
+    let temp_dir = TempDir::new().unwrap();
+    temp_dir
+        .child("andotp.json")
+        .write_str(ANDOTP_BACKUP)
+        .unwrap();
+    cloak(&temp_dir)
+        .arg("import")
+        .arg("--format=andotp")
+        .arg(temp_dir.path().join("andotp.json"))
+        .assert()
+        .success()
+        .stdout(
+            "Imported 'GitHub:evans'\nImported 'hotp_app'\nImported 2 of 3 accounts (1 skipped)\n",
+        )
+        .stderr("Skipped 'motp_app': unsupported type 'MOTP'\n");
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("[accounts.hotp_app]"));
+    assert!(accounts.contains("counter = 2"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn import_dry_run() {
+    let temp_dir = TempDir::new().unwrap();
+    temp_dir
+        .child("andotp.json")
+        .write_str(ANDOTP_BACKUP)
+        .unwrap();
+    cloak(&temp_dir)
+        .arg("import")
+        .arg("--format=andotp")
+        .arg("--dry-run")
+        .arg(temp_dir.path().join("andotp.json"))
+        .assert()
+        .success()
+        .stdout(
+            "NAME          TYPE  ALGORITHM  DIGITS  STATUS
+GitHub:evans  TOTP  SHA1       6       import
+hotp_app      HOTP  SHA256     8       import
+motp_app      -     -          -       skip: unsupported type 'MOTP'
+2 of 3 accounts would be imported (1 skipped)
+",
+        );
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(!accounts.contains("GitHub:evans"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn import_nothing_if_an_entry_is_invalid() {
+    let temp_dir = TempDir::new().unwrap();
+    temp_dir
+        .child("2fas.2fas")
+        .write_str(
+            r#"{
+    "services": [
+        {"name": "GitHub", "secret": "4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6",
+         "otp": {"account": "evans", "issuer": "GitHub", "digits": 6, "period": 30,
+                 "algorithm": "SHA1", "tokenType": "TOTP"}},
+        {"name": "Broken", "secret": "not base32!",
+         "otp": {"digits": 6, "period": 30, "algorithm": "SHA1", "tokenType": "TOTP"}}
+    ],
+    "groups": [],
+    "schemaVersion": 4
+}"#,
+        )
+        .unwrap();
+    cloak(&temp_dir)
+        .arg("import")
+        .arg("--format=2fas")
+        .arg(temp_dir.path().join("2fas.2fas"))
+        .assert()
+        .success()
+        .stdout("")
+        .stderr(
+            "Invalid entry 'Broken': the secret is not a valid base32 encoding
+Nothing was imported, 1 of 2 entries could not be read
+",
+        );
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(!accounts.contains("GitHub:evans"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn import_bitwarden_export() {
+    let temp_dir = TempDir::new().unwrap();
+    temp_dir
+        .child("accounts")
+        .write_str(
+            "[\"GitLab:james\"]
+key = \"JBSWY3DPEHPK3PXP\"
+totp = true
+hash_function = \"SHA1\"
+",
+        )
+        .unwrap();
+    temp_dir
+        .child("bitwarden.csv")
+        .write_str(
+            "folder,favorite,type,name,notes,fields,reprompt,login_uri,login_username,login_password,login_totp
+Work,,login,GitHub,,,0,https://github.com,evans,x,4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6
+,,login,GitLab,,,0,https://gitlab.com,james,x,otpauth://totp/GitLab:james?secret=JBSWY3DPEHPK3PXP
+,,login,Steam,,,0,,gaben,x,steam://JRZCL47CMXVOQMNPZR2F7J4RGI
+,,login,email,,,0,,evans,x,
+",
+        )
+        .unwrap();
+    cloak(&temp_dir)
+        .arg("import")
+        .arg("--format=bitwarden")
+        .arg(temp_dir.path().join("bitwarden.csv"))
+        .assert()
+        .success()
+        .stdout(
+            "Imported 'GitHub:evans'
+Skipped 'GitLab:james': an account with this name already exists
+Imported 'Steam:gaben'
+Imported 2 of 3 accounts (1 conflicting)
+",
+        );
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("tags = [\"Work\"]"));
+    assert!(accounts.contains("steam = true"));
+    temp_dir.close().unwrap();
+}
+
+// KeePass databases written independently of cloak, see tests/fixtures/kdbx.py
+fn kdbx_fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+#[test]
+fn import_kdbx_databases() {
+    for database in ["argon2d-aes.kdbx", "argon2id-chacha20.kdbx"] {
+        let temp_dir = TempDir::new().unwrap();
+        cloak(&temp_dir)
+            .arg("import")
+            .arg("--format=kdbx")
+            .arg(kdbx_fixture(database))
+            .env("CLOAK_BACKUP_PASSPHRASE", "hunter2")
+            .assert()
+            .success()
+            .stdout(
+                "Imported 'GitHub:evans'\nImported 'GitLab:evans@example.com'\n\
+                 Imported 'Steam:gaben'\nImported 3 of 3 accounts\n",
+            );
+        let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+        assert!(accounts.contains(
+            "[accounts.\"GitHub:evans\"]\nkey = \"4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6\"\ntotp = true\n\
+             hash_function = \"SHA1\"\ndigits = 6\nperiod = 30\n"
+        ));
+        assert!(accounts.contains(
+            "[accounts.\"GitLab:evans@example.com\"]\nkey = \"JBSWY3DPEHPK3PXP\"\ntotp = true\n\
+             hash_function = \"SHA256\"\ndigits = 8\nperiod = 60\ntags = [\"2fa\", \"admin\", \"Work\"]\n"
+        ));
+        assert!(accounts.contains(
+            "[accounts.\"Steam:gaben\"]\nkey = \"JRZCL47CMXVOQMNPZR2F7J4RGI\"\ntotp = true\n\
+             hash_function = \"SHA1\"\ndigits = 5\nperiod = 30\nsteam = true\ntags = [\"Games\"]\n"
+        ));
+        // Entries without TOTP and the recycle bin are left out
+        assert!(!accounts.contains("Email") && !accounts.contains("Deleted"));
+
+        cloak(&temp_dir)
+            .arg("import")
+            .arg("--format=kdbx")
+            .arg(kdbx_fixture(database))
+            .env("CLOAK_BACKUP_PASSPHRASE", "hunter3")
+            .assert()
+            .success()
+            .stderr(predicates::str::starts_with("Could not decrypt"));
+        temp_dir.close().unwrap();
+    }
+}
@@ -0,0 +1,334 @@
+extern crate zbus;
+
+mod common;
+
+use crate::common::cloak;
+use assert_cmd::Command;
+use assert_fs::fixture::TempDir;
+use predicates::prelude::*;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Stdio};
+use std::sync::{Arc, Mutex};
+use zbus::blocking::{Connection, ConnectionBuilder};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::{dbus_interface, fdo, ObjectServer};
+
+// Key of the RFC 4226 test vectors, its first HOTP code is 755224
+const KEY: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+type Secret = (OwnedObjectPath, Vec<u8>, Vec<u8>, String);
+// Attributes and secret of the items, the path of an item is its index
+type Items = Arc<Mutex<Vec<(HashMap<String, String>, Vec<u8>)>>>;
+
+fn path(path: &str) -> OwnedObjectPath {
+    OwnedObjectPath::try_from(path).unwrap()
+}
+
+fn item_path(index: usize) -> String {
+    format!("/org/freedesktop/secrets/collection/default/{}", index)
+}
+
+// Mock of the Secret Service, holding unlocked items in memory
+struct Service {
+    items: Items,
+}
+
+#[dbus_interface(name = "org.freedesktop.Secret.Service")]
+impl Service {
+    #[dbus_interface(out_args("output", "result"))]
+    fn open_session(&self, _algorithm: &str, _input: OwnedValue) -> (OwnedValue, OwnedObjectPath) {
+        (
+            OwnedValue::from(0_u8),
+            path("/org/freedesktop/secrets/session/1"),
+        )
+    }
+
+    #[dbus_interface(out_args("unlocked", "locked"))]
+    fn search_items(
+        &self,
+        attributes: HashMap<String, String>,
+    ) -> (Vec<OwnedObjectPath>, Vec<OwnedObjectPath>) {
+        let items = self.items.lock().unwrap();
+        let unlocked = (0..items.len())
+            .filter(|&index| items[index].0 == attributes)
+            .map(|index| path(&item_path(index)))
+            .collect();
+        (unlocked, Vec::new())
+    }
+
+    #[dbus_interface(out_args("unlocked", "prompt"))]
+    fn unlock(&self, objects: Vec<OwnedObjectPath>) -> (Vec<OwnedObjectPath>, OwnedObjectPath) {
+        (objects, path("/"))
+    }
+}
+
+struct Collection {
+    items: Items,
+}
+
+#[dbus_interface(name = "org.freedesktop.Secret.Collection")]
+impl Collection {
+    #[dbus_interface(out_args("item", "prompt"))]
+    async fn create_item(
+        &self,
+        #[zbus(object_server)] server: &ObjectServer,
+        properties: HashMap<String, OwnedValue>,
+        secret: Secret,
+        _replace: bool,
+    ) -> fdo::Result<(OwnedObjectPath, OwnedObjectPath)> {
+        let attributes = properties
+            .get("org.freedesktop.Secret.Item.Attributes")
+            .and_then(|attributes| HashMap::try_from(attributes.clone()).ok())
+            .ok_or_else(|| fdo::Error::InvalidArgs(String::from("missing attributes")))?;
+        let index = {
+            let mut items = self.items.lock().unwrap();
+            items.push((attributes, secret.2));
+            items.len() - 1
+        };
+        let item = Item {
+            items: self.items.clone(),
+            index,
+        };
+        server.at(item_path(index), item).await?;
+        Ok((path(&item_path(index)), path("/")))
+    }
+}
+
+struct Item {
+    items: Items,
+    index: usize,
+}
+
+#[dbus_interface(name = "org.freedesktop.Secret.Item")]
+impl Item {
+    fn get_secret(&self, session: OwnedObjectPath) -> Secret {
+        let value = self.items.lock().unwrap()[self.index].1.clone();
+        (session, Vec::new(), value, String::from("text/plain"))
+    }
+}
+
+// Private session bus running the mock Secret Service, stopped when dropped
+struct SecretService {
+    bus: Child,
+    address: String,
+    items: Items,
+    _connection: Connection,
+}
+
+impl SecretService {
+    // `None` if dbus-daemon isn't installed
+    fn start() -> Option<SecretService> {
+        let mut bus = std::process::Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let mut address = String::new();
+        BufReader::new(bus.stdout.as_mut().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        let address = address.trim().to_string();
+
+        let items = Items::default();
+        let connection = ConnectionBuilder::address(address.as_str())
+            .unwrap()
+            .name("org.freedesktop.secrets")
+            .unwrap()
+            .serve_at(
+                "/org/freedesktop/secrets",
+                Service {
+                    items: items.clone(),
+                },
+            )
+            .unwrap()
+            .serve_at(
+                "/org/freedesktop/secrets/aliases/default",
+                Collection {
+                    items: items.clone(),
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        Some(SecretService {
+            bus,
+            address,
+            items,
+            _connection: connection,
+        })
+    }
+
+    fn cloak(&self, temp_dir: &TempDir) -> Command {
+        let mut cmd = cloak(temp_dir);
+        cmd.env("DBUS_SESSION_BUS_ADDRESS", &self.address);
+        cmd
+    }
+}
+
+impl Drop for SecretService {
+    fn drop(&mut self) {
+        let _ = self.bus.kill();
+        let _ = self.bus.wait();
+    }
+}
+
+#[test]
+fn keep_keys_in_keyring() {
+    let service = match SecretService::start() {
+        Some(service) => service,
+        None => {
+            eprintln!("dbus-daemon is not installed, skipping");
+            return;
+        }
+    };
+    let temp_dir = TempDir::new().unwrap();
+    service
+        .cloak(&temp_dir)
+        .args(["add", "--hotp", "--keyring", "github", KEY])
+        .assert()
+        .success()
+        .stdout("Account successfully created\n");
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("key = \"keyring:"));
+    assert!(!accounts.contains(KEY));
+    assert_eq!(service.items.lock().unwrap()[0].1, KEY.as_bytes());
+
+    service
+        .cloak(&temp_dir)
+        .args(["view", "github"])
+        .assert()
+        .success()
+        .stdout("755224\n");
+    service
+        .cloak(&temp_dir)
+        .args(["export", "github"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("secret={}", KEY)));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn migrate_keys_to_keyring() {
+    let service = match SecretService::start() {
+        Some(service) => service,
+        None => {
+            eprintln!("dbus-daemon is not installed, skipping");
+            return;
+        }
+    };
+    let temp_dir = TempDir::new().unwrap();
+    for name in ["github", "gitlab"] {
+        service
+            .cloak(&temp_dir)
+            .args(["add", "--hotp", name, KEY])
+            .assert()
+            .success();
+    }
+    service
+        .cloak(&temp_dir)
+        .args(["migrate-to-keyring", "github"])
+        .assert()
+        .success()
+        .stdout("Moved the keys of 1 accounts to the keyring\n");
+    service
+        .cloak(&temp_dir)
+        .arg("migrate-to-keyring")
+        .assert()
+        .success()
+        .stdout("Moved the keys of 1 accounts to the keyring\n");
+    service
+        .cloak(&temp_dir)
+        .arg("migrate-to-keyring")
+        .assert()
+        .success()
+        .stdout("Nothing to move, no key is stored in the accounts file\n");
+
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(!accounts.contains(KEY));
+    assert_eq!(service.items.lock().unwrap().len(), 2);
+    service
+        .cloak(&temp_dir)
+        .args(["view", "gitlab"])
+        .assert()
+        .success()
+        .stdout("755224\n");
+
+    // The keys can't be read without the keyring
+    cloak(&temp_dir)
+        .env("DBUS_SESSION_BUS_ADDRESS", "unix:path=/nonexistent")
+        .args(["view", "gitlab"])
+        .assert()
+        .stderr(predicate::str::starts_with("Could not reach the keyring"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn restore_keys_to_keyring() {
+    let service = match SecretService::start() {
+        Some(service) => service,
+        None => {
+            eprintln!("dbus-daemon is not installed, skipping");
+            return;
+        }
+    };
+    let temp_dir = TempDir::new().unwrap();
+    let backup = temp_dir.path().join("cloak.backup");
+    service
+        .cloak(&temp_dir)
+        .args(["add", "--hotp", "--keyring", "github", KEY])
+        .assert()
+        .success();
+    service
+        .cloak(&temp_dir)
+        .arg("backup")
+        .arg(&backup)
+        .env("CLOAK_NEW_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Backed up 1 accounts"));
+
+    // The key of the backup is the one held by the keyring
+    service
+        .cloak(&temp_dir)
+        .arg("restore")
+        .arg(&backup)
+        .env("CLOAK_BACKUP_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with(
+            "Nothing to restore, the accounts are up to date\n",
+        ));
+
+    service
+        .cloak(&temp_dir)
+        .args(["delete", "github"])
+        .write_stdin("y\n")
+        .assert()
+        .success();
+    service
+        .cloak(&temp_dir)
+        .arg("restore")
+        .arg(&backup)
+        .env("CLOAK_BACKUP_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with(
+            "+ github\nRestored the backup, 1 accounts changed\n",
+        ));
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("key = \"keyring:"));
+    assert!(!accounts.contains(KEY));
+    assert_eq!(service.items.lock().unwrap().len(), 2);
+    service
+        .cloak(&temp_dir)
+        .args(["view", "github"])
+        .assert()
+        .success()
+        .stdout("755224\n");
+    temp_dir.close().unwrap();
+}
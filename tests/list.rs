@@ -1,7 +1,11 @@
 mod common;
 
-use crate::common::{cloak, load_accounts_file};
+use crate::common::{
+    cloak, load_accounts_file, load_eight_digit_accounts_file, load_hotp_accounts_file,
+};
 use assert_fs::fixture::TempDir;
+use assert_fs::prelude::*;
+use std::fs;
 
 #[test]
 fn list_account() {
@@ -14,3 +18,80 @@ fn list_account() {
         .stdout(predicates::str::is_match(r"^Account: test_app\nTOTP: \d{6}\n\n\n$").unwrap());
     temp_dir.close().unwrap();
 }
+
+#[test]
+fn list_hotp_account_advances_counter() {
+    let temp_dir = TempDir::new().unwrap();
+    load_hotp_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("list")
+        .assert()
+        .success()
+        .stdout("Account: hotp_app\nHOTP: 852241\n\n\n");
+    assert!(fs::read_to_string(temp_dir.path().join("accounts"))
+        .unwrap()
+        .contains("counter = 1"));
+    cloak(&temp_dir)
+        .arg("list")
+        .arg("--no-increment")
+        .assert()
+        .success()
+        .stdout("Account: hotp_app\nHOTP: 863669\n\n\n");
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn list_account_with_stored_digits() {
+    let temp_dir = TempDir::new().unwrap();
+    load_eight_digit_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicates::str::is_match(r"^Account: test_app\nTOTP: \d{8}\n\n\n$").unwrap());
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn list_accounts_by_tag_and_issuer() {
+    let temp_dir = TempDir::new().unwrap();
+    temp_dir
+        .child("accounts")
+        .write_str(
+            "
+[\"GitHub:evans\"]
+key = \"4AZJFQFIGYM2KMTOO72I6FAOZ6ZFWJR6\"
+totp = true
+hash_function = \"SHA1\"
+tags = [\"Work\"]
+
+[gitlab]
+key = \"JBSWY3DPEHPK3PXP\"
+totp = true
+hash_function = \"SHA1\"
+issuer = \"GitLab\"
+tags = [\"Personal\"]
+",
+        )
+        .unwrap();
+    cloak(&temp_dir)
+        .arg("list")
+        .arg("--tag=work")
+        .assert()
+        .success()
+        .stdout(predicates::str::is_match(r"^Account: GitHub:evans\nTOTP: \d{6}\n\n\n$").unwrap());
+    cloak(&temp_dir)
+        .arg("list")
+        .arg("--issuer=GitLab")
+        .assert()
+        .success()
+        .stdout(predicates::str::is_match(r"^Account: gitlab\nTOTP: \d{6}\n\n\n$").unwrap());
+    cloak(&temp_dir)
+        .arg("list")
+        .arg("--issuer=GitHub")
+        .arg("--tag=Personal")
+        .assert()
+        .success()
+        .stdout("");
+    temp_dir.close().unwrap();
+}
@@ -0,0 +1,63 @@
+extern crate fs2;
+
+mod common;
+
+use crate::common::{cloak, load_accounts_file};
+use assert_fs::fixture::TempDir;
+use fs2::FileExt;
+use predicates::prelude::*;
+use std::fs::File;
+
+fn lock_file(temp_dir: &TempDir) -> File {
+    File::create(temp_dir.path().join("accounts.lock")).unwrap()
+}
+
+#[test]
+fn wait_for_writer() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    let lock = lock_file(&temp_dir);
+    FileExt::lock_exclusive(&lock).unwrap();
+
+    cloak(&temp_dir)
+        .env("CLOAK_LOCK_TIMEOUT", "0")
+        .arg("export")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The accounts file is used by another cloak process",
+        ));
+
+    FileExt::unlock(&lock).unwrap();
+    cloak(&temp_dir)
+        .env("CLOAK_LOCK_TIMEOUT", "0")
+        .arg("export")
+        .assert()
+        .success();
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn share_with_readers() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    let lock = lock_file(&temp_dir);
+    FileExt::lock_shared(&lock).unwrap();
+
+    cloak(&temp_dir)
+        .env("CLOAK_LOCK_TIMEOUT", "0")
+        .arg("export")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("otpauth://totp/test_app"));
+    cloak(&temp_dir)
+        .env("CLOAK_LOCK_TIMEOUT", "0")
+        .args(["delete", "test_app"])
+        .write_stdin("y\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The accounts file is used by another cloak process",
+        ));
+    temp_dir.close().unwrap();
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use crate::common::{cloak, load_accounts_file};
+use assert_fs::fixture::TempDir;
+use predicates::prelude::*;
+use std::fs;
+
+#[test]
+fn qr_in_terminal() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("qr")
+        .arg("test_app")
+        .write_stdin("y\n")
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::starts_with(
+                "This will reveal the secret key of test_app, are you sure [N/y]? ",
+            )
+            .and(predicate::str::contains("\u{2580}")),
+        );
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn qr_abort() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("qr")
+        .arg("test_app")
+        .write_stdin("n\n")
+        .assert()
+        .success()
+        .stdout("This will reveal the secret key of test_app, are you sure [N/y]? Abort.\n");
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn qr_non_existent_account() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("qr")
+        .arg("404app")
+        .assert()
+        .success()
+        .stdout("Account with the name '404app' does not exist. Consider adding it.\n");
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn qr_to_png_and_add_back() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    let image = temp_dir.path().join("test_app.png");
+    cloak(&temp_dir)
+        .arg("qr")
+        .arg("test_app")
+        .arg("--output")
+        .arg(&image)
+        .write_stdin("y\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with(format!(
+            "QR code written to {}\n",
+            image.display()
+        )));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&image).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    let other_dir = TempDir::new().unwrap();
+    cloak(&other_dir)
+        .arg("add")
+        .arg("--qr")
+        .arg(&image)
+        .assert()
+        .success()
+        .stdout("Account successfully created\n");
+    let accounts = fs::read_to_string(other_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("[accounts.test_app]"));
+    assert!(accounts.contains("key = \"MFZWIYLTMRQXGZCBBI\""));
+    temp_dir.close().unwrap();
+    other_dir.close().unwrap();
+}
+
+#[test]
+fn qr_to_svg() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    let image = temp_dir.path().join("test_app.svg");
+    cloak(&temp_dir)
+        .arg("qr")
+        .arg("test_app")
+        .arg("-o")
+        .arg(&image)
+        .write_stdin("y\n")
+        .assert()
+        .success();
+    assert!(fs::read_to_string(&image).unwrap().contains("<svg"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn qr_to_unsupported_format() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("qr")
+        .arg("test_app")
+        .arg("-o")
+        .arg(temp_dir.path().join("test_app.gif"))
+        .write_stdin("y\n")
+        .assert()
+        .success()
+        .stderr("Unsupported image format 'gif', use a .png or .svg file\n");
+    temp_dir.close().unwrap();
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use crate::common::{cloak, load_accounts_file};
+use assert_fs::fixture::TempDir;
+use assert_fs::prelude::*;
+use std::fs;
+
+#[test]
+fn rename_account() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("rename")
+        .arg("test_app")
+        .arg("GitHub:evans")
+        .assert()
+        .success()
+        .stdout("Account renamed from 'test_app' to 'GitHub:evans'\n");
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("[accounts.\"GitHub:evans\"]"));
+    assert!(!accounts.contains("[accounts.test_app]"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn rename_to_existing_account() {
+    let temp_dir = TempDir::new().unwrap();
+    temp_dir
+        .child("accounts")
+        .write_str(
+            "
+[github]
+key = \"JBSWY3DPEHPK3PXP\"
+totp = true
+hash_function = \"SHA1\"
+
+[test_app]
+key = \"MFZWIYLTMRQXGZCBBI\"
+totp = true
+hash_function = \"SHA1\"
+",
+        )
+        .unwrap();
+    cloak(&temp_dir)
+        .arg("rename")
+        .arg("test_app")
+        .arg("github")
+        .assert()
+        .success()
+        .stdout("Account with the name 'github' already exists\n");
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("key = \"JBSWY3DPEHPK3PXP\""));
+    assert!(accounts.contains("key = \"MFZWIYLTMRQXGZCBBI\""));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn rename_non_existent_account() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .arg("rename")
+        .arg("404app")
+        .arg("github")
+        .assert()
+        .success()
+        .stdout("Account with the name '404app' does not exist. Consider adding it.\n");
+}
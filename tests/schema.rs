@@ -0,0 +1,47 @@
+mod common;
+
+use crate::common::{cloak, load_accounts_file};
+use assert_fs::fixture::TempDir;
+use assert_fs::prelude::*;
+use std::fs;
+
+#[test]
+fn upgrade_version_1_accounts_file() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    let original = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    cloak(&temp_dir)
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicates::str::is_match(r"^Account: test_app\nTOTP: \d{6}\n\n\n$").unwrap());
+
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.starts_with("version = 2\n"));
+    assert!(accounts.contains("[accounts.test_app]"));
+    let backup = fs::read_to_string(temp_dir.path().join("accounts.v1.bak")).unwrap();
+    assert_eq!(backup, original);
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn new_accounts_file_is_not_upgraded() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir).arg("list").assert().success().stdout("");
+    assert!(!temp_dir.path().join("accounts.v1.bak").exists());
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn refuse_newer_accounts_file() {
+    let temp_dir = TempDir::new().unwrap();
+    temp_dir
+        .child("accounts")
+        .write_str("version = 99\n\n[accounts]\n")
+        .unwrap();
+    cloak(&temp_dir).arg("list").assert().failure().stderr(
+        "Unable to initialize store: The accounts file has schema version 99, this version of \
+         cloak supports up to version 2. Upgrade cloak to read it.\n",
+    );
+    temp_dir.close().unwrap();
+}
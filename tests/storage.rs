@@ -0,0 +1,169 @@
+mod common;
+
+use crate::common::cloak;
+use assert_cmd::Command;
+use assert_fs::fixture::TempDir;
+use predicates::prelude::*;
+use std::fs;
+
+fn cloak_with(temp_dir: &TempDir, storage: &str) -> Command {
+    let mut cmd = cloak(temp_dir);
+    cmd.env("CLOAK_STORAGE", storage);
+    cmd
+}
+
+#[test]
+fn directory_storage() {
+    let temp_dir = TempDir::new().unwrap();
+    for name in ["GitHub:evans", "gitlab"] {
+        cloak_with(&temp_dir, "directory")
+            .args(["add", name, "MFZWIYLTMRQXGZDRO5YWK4LXMVYXOZLRO4FA"])
+            .assert()
+            .success()
+            .stdout("Account successfully created\n");
+    }
+    let accounts_dir = temp_dir.path().join("accounts.d");
+    let github = fs::read_to_string(accounts_dir.join("GitHub%3Aevans.toml")).unwrap();
+    assert!(github.contains("key = \"MFZWIYLTMRQXGZDRO5YWK4LXMVYXOZLRO4FA\""));
+    assert_eq!(
+        fs::read_to_string(accounts_dir.join("version")).unwrap(),
+        "2\n"
+    );
+
+    cloak_with(&temp_dir, "directory")
+        .args(["delete", "gitlab"])
+        .write_stdin("y\n")
+        .assert()
+        .success();
+    assert!(!accounts_dir.join("gitlab.toml").exists());
+    cloak_with(&temp_dir, "directory")
+        .args(["view", "GitHub:evans"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"^\d{6}\n$").unwrap());
+    cloak_with(&temp_dir, "directory")
+        .args(["restore", "--previous"])
+        .assert()
+        .stderr("The directory storage doesn't keep previous versions of the accounts\n");
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn encrypted_directory_storage() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak_with(&temp_dir, "directory")
+        .args(["init", "--encrypt"])
+        .env("CLOAK_NEW_PASSPHRASE", "hunter2")
+        .assert()
+        .success();
+    cloak_with(&temp_dir, "directory")
+        .args(["add", "github", "MFZWIYLTMRQXGZDRO5YWK4LXMVYXOZLRO4FA"])
+        .env("CLOAK_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout("Account successfully created\n");
+    let data = fs::read(temp_dir.path().join("accounts.d").join("github.toml")).unwrap();
+    assert!(data.starts_with(b"CLOAKENC"));
+
+    cloak_with(&temp_dir, "directory")
+        .args(["view", "github"])
+        .env("CLOAK_PASSPHRASE", "hunter3")
+        .assert()
+        .failure();
+    cloak_with(&temp_dir, "directory")
+        .arg("decrypt")
+        .env("CLOAK_PASSPHRASE", "hunter2")
+        .assert()
+        .success();
+    let github =
+        fs::read_to_string(temp_dir.path().join("accounts.d").join("github.toml")).unwrap();
+    assert!(github.contains("totp = true"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn sqlite_storage() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak_with(&temp_dir, "sqlite")
+        .args(["add", "github", "MFZWIYLTMRQXGZDRO5YWK4LXMVYXOZLRO4FA"])
+        .assert()
+        .success()
+        .stdout("Account successfully created\n");
+    cloak_with(&temp_dir, "sqlite")
+        .args(["rename", "github", "GitHub:evans"])
+        .assert()
+        .success();
+    cloak_with(&temp_dir, "sqlite")
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"^Account: GitHub:evans\nTOTP: \d{6}\n\n\n$").unwrap());
+    assert!(temp_dir.path().join("accounts.sqlite").is_file());
+    assert!(!temp_dir.path().join("accounts").exists());
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn unknown_storage() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak_with(&temp_dir, "floppy")
+        .arg("list")
+        .assert()
+        .failure()
+        .stderr(
+            "Unable to initialize store: Unknown storage 'floppy', expected one of file, \
+             directory or sqlite\n",
+        );
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn keep_storage_of_vault() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak_with(&temp_dir, "directory")
+        .args(["add", "github", "MFZWIYLTMRQXGZDRO5YWK4LXMVYXOZLRO4FA"])
+        .assert()
+        .success();
+
+    // The storage is found from the files of the vault
+    cloak(&temp_dir)
+        .args(["view", "github"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"^\d{6}\n$").unwrap());
+    assert!(!temp_dir.path().join("accounts").exists());
+    cloak_with(&temp_dir, "sqlite")
+        .args(["view", "github"])
+        .assert()
+        .failure()
+        .stderr(format!(
+            "Unable to initialize store: The vault in {} uses the directory storage, not sqlite\n",
+            temp_dir.path().display()
+        ));
+    assert!(!temp_dir.path().join("accounts.sqlite").exists());
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn storage_of_new_vaults_from_config() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .args(["config", "set", "storage", "sqlite"])
+        .assert()
+        .success();
+    cloak(&temp_dir)
+        .args(["add", "github", "MFZWIYLTMRQXGZDRO5YWK4LXMVYXOZLRO4FA"])
+        .assert()
+        .success();
+    assert!(temp_dir.path().join("accounts.sqlite").is_file());
+    assert!(!temp_dir.path().join("accounts").exists());
+
+    // Existing vaults keep their storage
+    cloak(&temp_dir)
+        .args(["config", "set", "storage", "directory"])
+        .assert()
+        .success();
+    cloak(&temp_dir).args(["view", "github"]).assert().success();
+    assert!(!temp_dir.path().join("accounts.d").exists());
+    temp_dir.close().unwrap();
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use crate::common::{cloak, load_accounts_file};
+use assert_fs::fixture::TempDir;
+use assert_fs::prelude::*;
+
+#[test]
+fn validate_valid_accounts() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("validate")
+        .assert()
+        .success()
+        .stdout("All 1 accounts are valid\n");
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn validate_unknown_hash_function() {
+    let temp_dir = TempDir::new().unwrap();
+    temp_dir
+        .child("accounts")
+        .write_str(
+            "
+[md5_app]
+key = \"MFZWIYLTMRQXGZCBBI\"
+totp = true
+hash_function = \"MD5\"
+
+[test_app]
+key = \"MFZWIYLTMRQXGZCBBI\"
+totp = true
+hash_function = \"SHA512_256\"
+",
+        )
+        .unwrap();
+    cloak(&temp_dir).arg("validate").assert().failure().stdout(
+        "Account 'md5_app': Unsupported hash function 'MD5'\n1 of 2 accounts are invalid\n",
+    );
+    cloak(&temp_dir)
+        .arg("view")
+        .arg("md5_app")
+        .assert()
+        .success()
+        .stderr("Unsupported hash function 'MD5'\n");
+    temp_dir.close().unwrap();
+}
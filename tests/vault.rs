@@ -0,0 +1,166 @@
+mod common;
+
+use crate::common::{cloak, load_accounts_file};
+use assert_fs::fixture::TempDir;
+use predicates::prelude::*;
+use std::fs;
+
+#[test]
+fn create_and_list_vaults() {
+    let temp_dir = TempDir::new().unwrap();
+    cloak(&temp_dir)
+        .args(["vault", "create", "work"])
+        .assert()
+        .success()
+        .stdout("Vault 'work' created\n");
+    assert!(temp_dir.path().join("vaults/work").is_dir());
+    cloak(&temp_dir)
+        .args(["vault", "create", "work"])
+        .assert()
+        .stderr(predicate::str::contains("already exists"));
+    cloak(&temp_dir)
+        .args(["vault", "create", "../work"])
+        .assert()
+        .stderr(predicate::str::starts_with("Invalid vault name '../work'"));
+
+    cloak(&temp_dir)
+        .args(["vault", "list"])
+        .assert()
+        .success()
+        .stdout("* default\n  work\n");
+    cloak(&temp_dir)
+        .args(["--vault", "work", "vault", "list"])
+        .assert()
+        .success()
+        .stdout("  default\n* work\n");
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn keep_accounts_in_vaults() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .args(["vault", "create", "work"])
+        .assert()
+        .success();
+    cloak(&temp_dir)
+        .args(["--vault", "work", "add", "github", "JBSWY3DPEHPK3PXP"])
+        .assert()
+        .success();
+    let accounts = fs::read_to_string(temp_dir.path().join("vaults/work/accounts")).unwrap();
+    assert!(accounts.contains("github"));
+
+    cloak(&temp_dir)
+        .env("CLOAK_VAULT", "work")
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("github"))
+        .stdout(predicate::str::contains("test_app").not());
+    cloak(&temp_dir)
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("test_app"))
+        .stdout(predicate::str::contains("github").not());
+    cloak(&temp_dir)
+        .args(["--vault", "personal", "list"])
+        .assert()
+        .stderr(predicate::str::contains("Vault 'personal' does not exist"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn move_account_between_vaults() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .args(["vault", "create", "work"])
+        .assert()
+        .success();
+    cloak(&temp_dir)
+        .args(["vault", "move-account", "test_app", "work"])
+        .assert()
+        .success()
+        .stdout("Account 'test_app' moved from vault 'default' to 'work'\n");
+    cloak(&temp_dir)
+        .args(["vault", "remove", "work"])
+        .assert()
+        .success()
+        .stdout("Vault 'work' still holds 1 accounts, move or delete them first\n");
+
+    cloak(&temp_dir)
+        .args([
+            "--vault",
+            "work",
+            "vault",
+            "move-account",
+            "test_app",
+            "default",
+        ])
+        .assert()
+        .success()
+        .stdout("Account 'test_app' moved from vault 'work' to 'default'\n");
+    let accounts = fs::read_to_string(temp_dir.path().join("accounts")).unwrap();
+    assert!(accounts.contains("test_app"));
+    cloak(&temp_dir)
+        .args(["vault", "remove", "work"])
+        .assert()
+        .success()
+        .stdout("Vault 'work' removed\n");
+    assert!(!temp_dir.path().join("vaults/work").exists());
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn move_account_between_encrypted_vaults() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .args(["vault", "create", "work"])
+        .assert()
+        .success();
+    cloak(&temp_dir)
+        .arg("encrypt")
+        .env("CLOAK_NEW_PASSPHRASE", "hunter2")
+        .assert()
+        .success();
+
+    // The key would be written unencrypted
+    cloak(&temp_dir)
+        .args(["vault", "move-account", "test_app", "work"])
+        .env("CLOAK_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout(
+            "Vault 'default' is encrypted unlike 'work', encrypt it first with \
+             `cloak --vault work encrypt`\n",
+        );
+    assert!(
+        !fs::read_to_string(temp_dir.path().join("vaults/work/accounts"))
+            .unwrap()
+            .contains("test_app")
+    );
+
+    cloak(&temp_dir)
+        .args(["--vault", "work", "encrypt"])
+        .env("CLOAK_NEW_PASSPHRASE", "hunter2")
+        .assert()
+        .success();
+    cloak(&temp_dir)
+        .args(["vault", "move-account", "test_app", "work"])
+        .env("CLOAK_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout("Account 'test_app' moved from vault 'default' to 'work'\n");
+    let data = fs::read(temp_dir.path().join("vaults/work/accounts")).unwrap();
+    assert!(!String::from_utf8_lossy(&data).contains("MFZWIYLTMRQXGZCBBI"));
+    cloak(&temp_dir)
+        .args(["--vault", "work", "view", "test_app"])
+        .env("CLOAK_PASSPHRASE", "hunter2")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"^\d{6}\n$").unwrap());
+    temp_dir.close().unwrap();
+}
@@ -1,7 +1,10 @@
 mod common;
 
-use crate::common::{cloak, load_accounts_file};
+use crate::common::{
+    cloak, load_accounts_file, load_eight_digit_accounts_file, load_hotp_accounts_file,
+};
 use assert_fs::fixture::TempDir;
+use std::fs;
 
 #[test]
 fn view_existent_account() {
@@ -30,6 +33,39 @@ fn view_account_with_length_arg() {
     temp_dir.close().unwrap();
 }
 
+#[test]
+fn view_account_with_stored_digits() {
+    let temp_dir = TempDir::new().unwrap();
+    load_eight_digit_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("view")
+        .arg("test_app")
+        .assert()
+        .success()
+        .stdout(predicates::str::is_match(r"^\d{8}\n$").unwrap());
+    cloak(&temp_dir)
+        .arg("view")
+        .arg("test_app")
+        .arg("--length=6")
+        .assert()
+        .success()
+        .stdout(predicates::str::is_match(r"^\d{6}\n$").unwrap());
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn view_account_with_invalid_length_arg() {
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("view")
+        .arg("test_app")
+        .arg("--length=10")
+        .assert()
+        .failure();
+    temp_dir.close().unwrap();
+}
+
 #[test]
 fn view_non_existent_account() {
     let temp_dir = TempDir::new().unwrap();
@@ -42,3 +78,121 @@ fn view_non_existent_account() {
         .stdout("Account with the name '404app' does not exist. Consider adding it.\n");
     temp_dir.close().unwrap();
 }
+
+#[test]
+fn view_hotp_account_advances_counter() {
+    let temp_dir = TempDir::new().unwrap();
+    load_hotp_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("view")
+        .arg("hotp_app")
+        .assert()
+        .success()
+        .stdout("852241\n");
+    cloak(&temp_dir)
+        .arg("view")
+        .arg("hotp_app")
+        .assert()
+        .success()
+        .stdout("863669\n");
+    assert!(fs::read_to_string(temp_dir.path().join("accounts"))
+        .unwrap()
+        .contains("counter = 2"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn view_hotp_account_with_no_increment_arg() {
+    let temp_dir = TempDir::new().unwrap();
+    load_hotp_accounts_file(&temp_dir);
+    for _ in 0..2 {
+        cloak(&temp_dir)
+            .arg("view")
+            .arg("hotp_app")
+            .arg("--peek")
+            .assert()
+            .success()
+            .stdout("852241\n");
+    }
+    assert!(fs::read_to_string(temp_dir.path().join("accounts"))
+        .unwrap()
+        .contains("counter = 0"));
+    temp_dir.close().unwrap();
+}
+
+#[test]
+fn view_hotp_account_with_counter_arg() {
+    let temp_dir = TempDir::new().unwrap();
+    load_hotp_accounts_file(&temp_dir);
+    cloak(&temp_dir)
+        .arg("view")
+        .arg("hotp_app")
+        .arg("--counter=1")
+        .assert()
+        .success()
+        .stdout("863669\n");
+    assert!(fs::read_to_string(temp_dir.path().join("accounts"))
+        .unwrap()
+        .contains("counter = 2"));
+    temp_dir.close().unwrap();
+}
+
+// Fake wl-copy and wl-paste keeping the clipboard in a file
+#[cfg(all(unix, not(target_os = "macos")))]
+fn fake_clipboard(temp_dir: &TempDir) -> std::ffi::OsString {
+    use std::os::unix::fs::PermissionsExt;
+
+    let bin = temp_dir.path().join("bin");
+    let clipboard = temp_dir.path().join("clipboard");
+    fs::create_dir(&bin).unwrap();
+    for (name, script) in [
+        (
+            "wl-copy",
+            format!("#!/bin/sh\ncat > {}\n", clipboard.display()),
+        ),
+        (
+            "wl-paste",
+            format!("#!/bin/sh\ncat {}\n", clipboard.display()),
+        ),
+    ] {
+        let path = bin.join(name);
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let mut paths = vec![bin];
+    paths.extend(std::env::split_paths(&std::env::var_os("PATH").unwrap()));
+    std::env::join_paths(paths).unwrap()
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[test]
+fn view_account_copied_to_clipboard() {
+    use predicates::prelude::Predicate;
+
+    let temp_dir = TempDir::new().unwrap();
+    load_accounts_file(&temp_dir);
+    let path = fake_clipboard(&temp_dir);
+    fs::create_dir_all(temp_dir.path().join("config/cloak")).unwrap();
+    fs::write(
+        temp_dir.path().join("config/cloak/config.toml"),
+        "clipboard_timeout = 1\n",
+    )
+    .unwrap();
+    cloak(&temp_dir)
+        .env("PATH", &path)
+        .env("WAYLAND_DISPLAY", "wayland-0")
+        .arg("view")
+        .arg("test_app")
+        .arg("--copy")
+        .assert()
+        .success()
+        .stdout("OTP copied to the clipboard, it is cleared in 1 seconds\n");
+    let clipboard = temp_dir.path().join("clipboard");
+    let code = fs::read_to_string(&clipboard).unwrap();
+    assert!(predicates::str::is_match(r"^\d{6}$").unwrap().eval(&code));
+
+    // The clipboard is cleared in the background
+    std::thread::sleep(std::time::Duration::from_secs(3));
+    assert_eq!(fs::read_to_string(&clipboard).unwrap(), "");
+    temp_dir.close().unwrap();
+}